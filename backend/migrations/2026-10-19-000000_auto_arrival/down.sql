@@ -0,0 +1,5 @@
+ALTER TABLE reservations
+DROP COLUMN driver_arrived_auto;
+
+ALTER TABLE events
+DROP COLUMN auto_arrival;
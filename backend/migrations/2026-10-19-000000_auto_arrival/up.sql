@@ -0,0 +1,5 @@
+ALTER TABLE events
+ADD COLUMN auto_arrival BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE reservations
+ADD COLUMN driver_arrived_auto BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+ALTER TABLE reservations
+DROP COLUMN driver_accepted_at;
+
+DROP TABLE driver_pings;
@@ -0,0 +1,13 @@
+CREATE TABLE driver_pings (
+    id SERIAL PRIMARY KEY,
+    id_event UUID NOT NULL,
+    id_driver INT NOT NULL,
+    lat DOUBLE PRECISION NOT NULL,
+    lng DOUBLE PRECISION NOT NULL,
+    at INT NOT NULL
+);
+CREATE INDEX driver_pings_driver_at ON driver_pings (id_driver, at);
+CREATE INDEX driver_pings_at ON driver_pings (at);
+
+ALTER TABLE reservations
+ADD COLUMN driver_accepted_at INT;
@@ -0,0 +1,16 @@
+CREATE TABLE notifications (
+    id SERIAL PRIMARY KEY,
+    id_reservation UUID NOT NULL,
+    phone TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    message TEXT NOT NULL,
+    dedupe_key TEXT NOT NULL UNIQUE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at INT NOT NULL,
+    next_attempt_at INT NOT NULL,
+    sent_at INT
+);
+CREATE INDEX notifications_reservation ON notifications (id_reservation, created_at);
+CREATE INDEX notifications_due ON notifications (status, next_attempt_at);
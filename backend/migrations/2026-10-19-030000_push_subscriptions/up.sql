@@ -0,0 +1,9 @@
+CREATE TABLE push_subscriptions (
+    id SERIAL PRIMARY KEY,
+    phone TEXT NOT NULL,
+    endpoint TEXT NOT NULL UNIQUE,
+    p256dh TEXT NOT NULL,
+    auth TEXT NOT NULL,
+    created_at INT NOT NULL
+);
+CREATE INDEX push_subscriptions_phone ON push_subscriptions (phone);
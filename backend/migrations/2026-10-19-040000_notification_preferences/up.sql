@@ -0,0 +1,8 @@
+CREATE TABLE notification_preferences (
+    phone TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    is_enabled BOOLEAN NOT NULL,
+    updated_at INT NOT NULL,
+    PRIMARY KEY (phone, channel, kind)
+);
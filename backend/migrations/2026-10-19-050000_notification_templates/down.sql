@@ -0,0 +1,4 @@
+DROP TABLE notification_templates;
+
+ALTER TABLE users
+DROP COLUMN locale;
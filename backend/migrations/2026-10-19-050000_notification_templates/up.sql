@@ -0,0 +1,11 @@
+ALTER TABLE users
+ADD COLUMN locale TEXT;
+
+CREATE TABLE notification_templates (
+    id_org UUID NOT NULL,
+    kind TEXT NOT NULL,
+    locale TEXT NOT NULL,
+    body TEXT NOT NULL,
+    updated_at INT NOT NULL,
+    PRIMARY KEY (id_org, kind, locale)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE notification_mutes (
+    id_org UUID NOT NULL,
+    kind TEXT NOT NULL,
+    updated_at INT NOT NULL,
+    PRIMARY KEY (id_org, kind)
+);
@@ -0,0 +1,7 @@
+ALTER TABLE notification_templates DROP COLUMN title;
+
+ALTER TABLE notifications DROP COLUMN announcement;
+ALTER TABLE notifications DROP COLUMN eta;
+ALTER TABLE notifications DROP COLUMN id_driver;
+UPDATE notifications SET message = '' WHERE message IS NULL;
+ALTER TABLE notifications ALTER COLUMN message SET NOT NULL;
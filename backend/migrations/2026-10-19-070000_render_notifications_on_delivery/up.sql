@@ -0,0 +1,6 @@
+ALTER TABLE notifications ALTER COLUMN message DROP NOT NULL;
+ALTER TABLE notifications ADD COLUMN id_driver INT;
+ALTER TABLE notifications ADD COLUMN eta INT;
+ALTER TABLE notifications ADD COLUMN announcement TEXT;
+
+ALTER TABLE notification_templates ADD COLUMN title TEXT;
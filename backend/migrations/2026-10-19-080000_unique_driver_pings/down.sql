@@ -0,0 +1,2 @@
+DROP INDEX driver_pings_driver_at;
+CREATE INDEX driver_pings_driver_at ON driver_pings (id_driver, at);
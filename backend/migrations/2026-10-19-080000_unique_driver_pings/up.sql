@@ -0,0 +1,6 @@
+DELETE FROM driver_pings a
+USING driver_pings b
+WHERE a.id_driver = b.id_driver AND a.at = b.at AND a.id > b.id;
+
+DROP INDEX driver_pings_driver_at;
+CREATE UNIQUE INDEX driver_pings_driver_at ON driver_pings (id_driver, at);
@@ -10,6 +10,7 @@ use google_maps::prelude::GoogleMapsClient;
 
 use crate::sms::ClientTwilio;
 use crate::graphql::Schema;
+use crate::market::Market;
 
 pub struct AppState {
     pub schema: Schema,
@@ -17,7 +18,7 @@ pub struct AppState {
     pub twilio: ClientTwilio,
     pub jwt_secret: String,
     pub google_maps_client: GoogleMapsClient,
-    pub kv: kv::Store,
+    pub market: Market,
     pub is_mock: bool,
 }
 
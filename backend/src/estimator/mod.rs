@@ -0,0 +1,183 @@
+pub mod model;
+
+use std::{sync::Arc, collections::{HashMap, HashSet}, time::Instant};
+use actix::prelude::*;
+use chrono::Duration;
+use tokio::{spawn, sync::Mutex};
+use log::{info, warn, error};
+use uuid::Uuid;
+
+use crate::market::{Market, error::ErrorMarket, types::MarketResult, estimate::driver::stop::{reservation::model::DriverStopEstimationReservation, model::DriverStopEstimation}};
+
+use self::model::{EstimatorConfig, PublishedEstimate, EventRunStats};
+
+#[doc = "What the estimator remembers between runs"]
+#[derive(Debug, Default)]
+pub struct EstimatorState {
+    #[doc = "When each event is due to be refreshed"]
+    next_run: HashMap<Uuid, i32>,
+    #[doc = "The last estimate each reservation was sent, by event"]
+    published: HashMap<Uuid, HashMap<Uuid, PublishedEstimate>>,
+    next_gc: i32,
+}
+
+pub struct Estimator {
+    market: Arc<Market>,
+    config: EstimatorConfig,
+    state: Arc<Mutex<EstimatorState>>,
+}
+
+impl Estimator {
+    pub fn new(market: Arc<Market>) -> Addr<Self> {
+        Self::with_config(market, EstimatorConfig::default())
+    }
+
+    pub fn with_config(market: Arc<Market>, config: EstimatorConfig) -> Addr<Self> {
+        let actor = Estimator { market, config, state: Arc::new(Mutex::new(EstimatorState::default())) };
+        actor.start()
+    }
+
+    async fn task(market: Arc<Market>, config: EstimatorConfig, state: &mut EstimatorState) -> Result<(), ErrorMarket> {
+        let now = market.clock.now();
+
+        if now >= state.next_gc {
+            state.next_gc = now + config.gc_interval.num_seconds() as i32;
+            match market.event.gc_cache().await {
+                Ok(gc) => {
+                    let stats = market.event.cache_stats();
+                    info!(
+                        "Cache: evicted {} events, removed {} estimates, {} hits, {} misses ({})",
+                        gc.events_evicted,
+                        gc.estimates_removed,
+                        stats.hits,
+                        stats.misses,
+                        stats.hit_rate().map(|rate| format!("{:.0}% hit rate", rate * 100.0)).unwrap_or(String::from("no lookups")),
+                    );
+                },
+                Err(e) => warn!("Could not collect the event cache, {e:?}"),
+            }
+            match market.driver.prune_trails().await {
+                Ok(removed) if removed > 0 => info!("Trails: removed {removed} driver locations"),
+                Ok(_) => (),
+                Err(e) => error!("Error pruning driver trails, {e:?}"),
+            }
+        }
+
+        let events = market.event.list_active().await?;
+        let active: HashSet<Uuid> = events.iter().map(|event| event.id).collect();
+        state.next_run.retain(|id_event, _| active.contains(id_event));
+        state.published.retain(|id_event, _| active.contains(id_event));
+
+        for event in events {
+            let id_event = event.id;
+            if state.next_run.get(&id_event).map_or(false, |next_run| *next_run > now) { continue }
+
+            let published = state.published.entry(id_event).or_default();
+            let next_interval = match Self::refresh_event(&market, &config, published, &id_event).await {
+                Ok(stats) => {
+                    info!("{stats}");
+                    stats.next_interval
+                },
+                Err(e) => {
+                    // One event failing should not hold up the others
+                    error!("Error updating estimations for event {id_event}, {e:?}");
+                    config.interval
+                },
+            };
+            state.next_run.insert(id_event, now + next_interval.num_seconds() as i32);
+        }
+        Ok(())
+    }
+
+    #[doc = "Refresh the estimates for an event and send riders the ones that changed since they were last sent"]
+    pub async fn refresh_event(market: &Market, config: &EstimatorConfig, published: &mut HashMap<Uuid, PublishedEstimate>, id_event: &Uuid) -> MarketResult<EventRunStats> {
+        let started = Instant::now();
+        let strategy = market.event.refresh_estimates(id_event).await?;
+        let next_interval = config.interval_for(&strategy);
+
+        let mut estimates: Vec<(Uuid, PublishedEstimate)> = Vec::new();
+        for driver_strat in strategy.drivers.values() {
+            let mut reservations: Vec<DriverStopEstimationReservation> = driver_strat.queue
+                .iter()
+                .filter_map(|stop| if let DriverStopEstimation::Reservation(res) = stop { Some(res.clone()) } else { None })
+                .collect();
+
+            let arrival_time = match &driver_strat.dest {
+                Some(DriverStopEstimation::Reservation(dest)) => {
+                    reservations.insert(0, dest.clone());
+                    Some(dest.arrival)
+                }
+                Some(DriverStopEstimation::Event(event)) => Some(event.arrival),
+                _ => None
+            };
+
+            if let Some(arrival) = arrival_time {
+                for id in driver_strat.picked_up.keys() {
+                    estimates.push((*id, PublishedEstimate { pickup: Duration::seconds(0), arrival, queue_position: 0 }));
+                }
+            }
+
+            for (idx, reservation) in reservations.iter().enumerate() {
+                estimates.push((reservation.id_reservation, PublishedEstimate { pickup: reservation.pickup, arrival: reservation.arrival, queue_position: idx as i32 }));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut unchanged = 0;
+        for (id, estimate) in &estimates {
+            // A picked up reservation can also be in the queue for its dropoff, the first estimate wins
+            if !seen.insert(*id) { continue }
+
+            let should_publish = published.get(id).map_or(true, |last| last.differs(estimate, config.publish_threshold));
+            if !should_publish {
+                unchanged += 1;
+                continue
+            }
+
+            market.messanger.send_reservation_estimate(id, estimate.pickup, estimate.arrival, estimate.queue_position).await?;
+            published.insert(*id, *estimate);
+        }
+        published.retain(|id, _| seen.contains(id));
+        market.event.publish_summary(id_event).await;
+
+        Ok(EventRunStats {
+            id_event: *id_event,
+            drivers: strategy.drivers.len(),
+            reservations: seen.len(),
+            published: seen.len() - unchanged,
+            unchanged,
+            elapsed_ms: started.elapsed().as_millis(),
+            next_interval,
+        })
+    }
+
+    fn start_interval(&self, ctx: &mut Context<Self>) {
+        let market = self.market.clone();
+        let config = self.config.clone();
+        let state = self.state.clone();
+        let tick = self.config.tick.to_std().unwrap_or(std::time::Duration::from_secs(10));
+
+        ctx.run_interval(tick, move |_act, _ctx| {
+            // Skip this tick if the last one is still running
+            let Ok(mut state) = state.clone().try_lock_owned() else { return };
+            let market = market.clone();
+            let config = config.clone();
+            spawn(async move {
+                match Self::task(market, config, &mut state).await {
+                    Ok(_) => (),
+                    Err(e) => {
+                        error!("Error updating estimations, {e:?}");
+                    }
+                }
+            });
+        });
+    }
+}
+
+impl Actor for Estimator {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.start_interval(ctx);
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::market::estimate::{model::StrategyEstimations, driver::stop::model::DriverStopEstimation};
+
+#[derive(Debug, Clone)]
+pub struct EstimatorConfig {
+    #[doc = "How often the estimator checks which events are due"]
+    pub tick: Duration,
+    #[doc = "How often an event with drivers on the way is refreshed"]
+    pub interval: Duration,
+    #[doc = "How often an event is refreshed when a driver is close to their next stop"]
+    pub interval_close: Duration,
+    #[doc = "How often an event with nothing to drive to is refreshed"]
+    pub interval_idle: Duration,
+    #[doc = "A driver is close when they are this far from their next stop"]
+    pub close_within: Duration,
+    #[doc = "Estimates are only published when they move by at least this much"]
+    pub publish_threshold: Duration,
+    #[doc = "How often the event cache is garbage collected"]
+    pub gc_interval: Duration,
+}
+
+impl Default for EstimatorConfig {
+    fn default() -> Self {
+        Self {
+            tick: Duration::seconds(10),
+            interval: Duration::seconds(60),
+            interval_close: Duration::seconds(15),
+            interval_idle: Duration::seconds(180),
+            close_within: Duration::seconds(120),
+            publish_threshold: Duration::seconds(30),
+            gc_interval: Duration::seconds(60),
+        }
+    }
+}
+
+impl EstimatorConfig {
+    #[doc = "How long until the event should be refreshed again"]
+    pub fn interval_for(&self, strategy: &StrategyEstimations) -> Duration {
+        let next_stops: Vec<Duration> = strategy.drivers.values()
+            .filter_map(|driver| driver.dest.as_ref())
+            .map(|dest| match dest {
+                DriverStopEstimation::Reservation(stop) => stop.pickup,
+                DriverStopEstimation::Event(stop) => stop.arrival,
+            })
+            .collect();
+
+        if next_stops.is_empty() { return self.interval_idle }
+        if next_stops.iter().any(|next_stop| *next_stop <= self.close_within) { return self.interval_close }
+        self.interval
+    }
+}
+
+#[doc = "The last estimate a reservation was sent"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishedEstimate {
+    pub pickup: Duration,
+    pub arrival: Duration,
+    pub queue_position: i32,
+}
+
+impl PublishedEstimate {
+    #[doc = "If the rider should be told about the new estimate"]
+    pub fn differs(&self, other: &PublishedEstimate, threshold: Duration) -> bool {
+        self.queue_position != other.queue_position
+            || (self.pickup - other.pickup).num_seconds().abs() >= threshold.num_seconds()
+            || (self.arrival - other.arrival).num_seconds().abs() >= threshold.num_seconds()
+    }
+}
+
+#[doc = "What happened when an event was refreshed"]
+#[derive(Debug, Clone)]
+pub struct EventRunStats {
+    pub id_event: Uuid,
+    pub drivers: usize,
+    pub reservations: usize,
+    #[doc = "Reservations that were sent a new estimate"]
+    pub published: usize,
+    #[doc = "Reservations whose estimate did not move enough to be sent"]
+    pub unchanged: usize,
+    pub elapsed_ms: u128,
+    pub next_interval: Duration,
+}
+
+impl std::fmt::Display for EventRunStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Event {}: {} drivers, {} reservations, {} published, {} unchanged in {}ms, next in {}s",
+            self.id_event,
+            self.drivers,
+            self.reservations,
+            self.published,
+            self.unchanged,
+            self.elapsed_ms,
+            self.next_interval.num_seconds(),
+        )
+    }
+}
@@ -1,5 +1,4 @@
 use actix::Addr;
-use kv::Store;
 use google_maps::GoogleMapsClient;
 use std::str::FromStr;
 use uuid::Uuid;
@@ -30,6 +29,8 @@ pub struct Context {
 #[derive(Debug, Clone)]
 pub struct UserCtx {
     pub phone: Phone,
+    #[doc = "When the user's token stops being valid, as a unix timestamp. Only known for subscriptions"]
+    pub expires_at: Option<i64>,
 }
 
 impl Context {
@@ -38,7 +39,7 @@ impl Context {
         twilio: ClientTwilio,
         jwt_secret: String,
         google_maps_client: GoogleMapsClient,
-        kv: Store,
+        market: Market,
         user_phone: Option<Phone>,
         is_mock: bool,
     ) -> Self {
@@ -47,9 +48,9 @@ impl Context {
             twilio: twilio.clone(),
             jwt: JWT(jwt_secret),
             google_maps_client: google_maps_client.clone(),
-            user: user_phone.map(|phone| UserCtx { phone }),
+            user: user_phone.map(|phone| UserCtx { phone, expires_at: None }),
             is_mock,
-            market: if !is_mock { Market::new(db, kv, google_maps_client, twilio) } else { Market::mock(db, kv) },
+            market,
         }
     }
 
@@ -61,10 +62,39 @@ impl Context {
             google_maps_client: self.google_maps_client.clone(),
             is_mock: self.is_mock,
             market: self.market.clone(),
-            user: Some(UserCtx { phone }),
+            user: Some(UserCtx { phone, expires_at: None }),
         }
     }
 
+    #[doc = "Authenticate as the user of a token, remembering when it expires"]
+    pub fn as_token_user(&self, token: &str) -> Result<Self, String> {
+        let claims = self.jwt.decode_claims(token)?;
+        let mut ctx = self.as_user(claims.phone);
+        if let Some(user) = ctx.user.as_mut() {
+            user.expires_at = Some(claims.exp as i64);
+        }
+        Ok(ctx)
+    }
+
+    #[doc = "Remember when the user's token expires, for a user from a token checked elsewhere"]
+    pub fn expiring_at(mut self, expires_at: Option<i64>) -> Self {
+        if let Some(user) = self.user.as_mut() {
+            user.expires_at = expires_at;
+        }
+        self
+    }
+
+    #[doc = "A copy of the context for the same user, for a subscription to hold on to"]
+    pub fn as_subscriber(&self) -> Self {
+        let mut ctx = self.as_user(self.phone());
+        ctx.user = self.user.clone();
+        ctx
+    }
+
+    pub fn expires_at(&self) -> Option<i64> {
+        self.user.as_ref().and_then(|user| user.expires_at)
+    }
+
     pub fn phone(&self) -> Phone {
         self.user.as_ref().expect("No user").phone.clone()
     }
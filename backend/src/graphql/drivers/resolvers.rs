@@ -1,9 +1,9 @@
 use juniper::{FieldResult, FieldError, graphql_value};
 use uuid::Uuid;
 
-use crate::{graphql::{context::Context, users::User, vehicles::{Vehicle, messages::VehicleGet}, reservations::stops::model::FormLatLng, events::{Event, messages::EventGet}}, types::phone::Phone, market::estimate::driver::model::DriverStrategyEstimations};
+use crate::{graphql::{context::Context, users::User, vehicles::{Vehicle, messages::VehicleGet}, reservations::stops::model::FormLatLng, trails::model::{FormTrailPoint, TrailPoint}, events::{Event, messages::EventGet}}, types::phone::Phone, market::{estimate::driver::model::DriverStrategyEstimations, strategy::driver::{model::DriverStrategy, stop::model::DriverStop}}};
 
-use super::{model::Driver, DriverWithVehicle};
+use super::{model::Driver, DriverWithVehicle, messages::EventDriverGet};
 
 #[juniper::graphql_object(Context = Context)]
 impl Driver {
@@ -96,29 +96,43 @@ impl DriverMutation {
     }
 }
 
-// #[juniper::graphql_object(Context = Context)]
-// impl DriverStrategy {
-//     async fn driver(&self, ctx: &Context) -> FieldResult<Driver> {
-//         let driver = ctx.db.send(EventDriverGet { id: self.id }).await??;
-//         Ok(driver.into())
-//     }
+#[juniper::graphql_object(Context = Context)]
+impl DriverStrategy {
+    fn id(&self) -> i32 {
+        self.id
+    }
 
-//     fn picked_up(&self) -> Vec<Uuid> {
-//         self.picked_up.clone().keys().cloned().collect()
-//     }
+    fn id_event(&self) -> Uuid {
+        self.id_event
+    }
 
-//     fn dest(&self) -> Option<DriverStop> {
-//         self.dest.clone()
-//     }
+    async fn driver(&self, ctx: &Context) -> FieldResult<Driver> {
+        let driver = ctx.db.send(EventDriverGet { id: self.id }).await??;
+        Ok(driver.into())
+    }
 
-//     fn queue(&self) -> Vec<DriverStop> {
-//         self.queue.clone()
-//     }
+    fn picked_up(&self) -> Vec<Uuid> {
+        self.picked_up.clone().keys().cloned().collect()
+    }
 
-//     async fn time_till_free(&self, ctx: &Context) -> FieldResult<i32> {
-//         Ok(ctx.market.estimate_driver_till_free(&self.id_event, &self.id).await?)
-//     }
-// }
+    fn dest(&self) -> Option<DriverStop> {
+        self.dest.clone()
+    }
+
+    fn queue(&self) -> Vec<DriverStop> {
+        self.queue.clone()
+    }
+
+    fn max_capacity(&self) -> i32 {
+        self.max_capacity
+    }
+
+    #[graphql(description = "Seconds until the driver has finished their queue")]
+    async fn time_till_free(&self, ctx: &Context) -> FieldResult<i32> {
+        let driver = ctx.market.event.get_estimates(&self.id_event).await?.driver(&self.id)?;
+        Ok(driver.duration().num_seconds() as i32)
+    }
+}
 
 #[juniper::graphql_object(Context = Context)]
 impl DriverMutation {
@@ -129,6 +143,14 @@ impl DriverMutation {
         Ok(driver_strat)
     }
 
+    #[graphql(description = "Sync the locations the driver has been since the last ping, only the latest one updates the queue")]
+    async fn ping_batch(ctx: &Context, id_event: Uuid, id_driver: i32, samples: Vec<FormTrailPoint>) -> FieldResult<DriverStrategyEstimations> {
+        if !ctx.validate_is_driver_for_event(&id_event, &id_driver).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        let samples = samples.into_iter().map(TrailPoint::from).collect();
+        let driver_strat = ctx.market.driver.ping_batch(&id_event, &id_driver, samples).await?;
+        Ok(driver_strat)
+    }
+
     #[graphql(description = "Accept a reservation")]
     async fn accept_reservation(ctx: &Context, id_driver: i32, id_reservation: Uuid) -> FieldResult<DriverStrategyEstimations> {
         if !ctx.validate_is_driver_able_to_accept_reservation(&id_driver, &id_reservation).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
@@ -4,7 +4,6 @@ use diesel::prelude::*;
 
 use crate::db_util::DBActor;
 use crate::graphql::locations::DBLocation;
-use crate::market::util::now;
 use crate::schema::events::dsl::*;
 use crate::schema::locations::dsl as locations;
 
@@ -76,9 +75,9 @@ impl Handler<EventUpdate> for DBActor {
 impl Handler<GetActiveEvents> for DBActor {
     type Result = QueryResult<Vec<DBEvent>>;
 
-    fn handle(&mut self, _msg: GetActiveEvents, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: GetActiveEvents, _ctx: &mut Self::Context) -> Self::Result {
         let mut conn = self.0.get().expect("Could not get DB connection from pool");
-        let now = now();
+        let now = msg.now;
 
         events
             .filter(time_start.le(now))
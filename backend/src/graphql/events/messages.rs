@@ -31,5 +31,8 @@ pub struct EventUpdate {
 
 #[derive(Message)]
 #[rtype(result = "QueryResult<Vec<DBEvent>>")]
-pub struct GetActiveEvents;
+pub struct GetActiveEvents {
+    #[doc = "The market's time, events running at it are active"]
+    pub now: i32,
+}
 
@@ -19,6 +19,7 @@ pub struct Event {
     pub reservations_end: i32,
     pub obsolete_at: Option<i32>,
     pub published_at: Option<i32>,
+    pub auto_arrival: bool,
 }
 
 #[derive(Debug, Serialize, Queryable)]
@@ -35,6 +36,7 @@ pub struct DBEvent {
     pub obsolete_at: Option<i32>,
     pub published_at: Option<i32>,
     pub id: Uuid,
+    pub auto_arrival: bool,
 }
 
 #[derive(Debug, Serialize, Insertable, AsChangeset)]
@@ -52,6 +54,7 @@ pub struct DBEventInsertable {
     pub id_org: Uuid,
     pub obsolete_at: Option<i32>,
     pub published_at: Option<i32>,
+    pub auto_arrival: bool,
 }
 
 
@@ -67,6 +70,8 @@ pub struct FormEvent {
     pub id_location: Option<Uuid>,
     pub obsolete_at: Option<i32>,
     pub published_at: Option<i32>,
+    #[graphql(description = "Confirm a driver has arrived when they stay at the pickup, off when not given")]
+    pub auto_arrival: Option<bool>,
 }
 
 impl From<DBEvent> for Event {
@@ -85,6 +90,7 @@ impl From<DBEvent> for Event {
             reservations_end: db_event.reservations_end,
             obsolete_at: db_event.obsolete_at,
             published_at: db_event.published_at,
+            auto_arrival: db_event.auto_arrival,
         }
     }
 }
@@ -6,7 +6,8 @@ use crate::{graphql::{
     drivers::{messages::{EventDriversList, EventDriverFind}, model::Driver},
     locations::{messages::OrgLocationGet, OrgLocation},
     orgs::{messages::{OrganizationGet, OrganizationCollegeGet}, model::Organization},
-    reservations::{messages::ReservationsList, Reservation, FormReservation, stops::model::{FormLatLng, FormReservationStop}}, colleges::model::College, vehicles::{Vehicle, messages::VehiclesList}
+    reservations::{messages::ReservationsList, Reservation, FormReservation, stops::model::{FormLatLng, FormReservationStop}}, colleges::model::College, vehicles::{Vehicle, messages::VehiclesList},
+    reports::model::DriverReport,
 }, market::{types::ReservationEstimate, estimate::model::StrategyEstimations, strategy::model::IdEventDriver}};
 
 use super::{messages::EventGet, Event};
@@ -120,6 +121,10 @@ impl Event {
         &self.published_at
     }
 
+    fn auto_arrival(&self) -> bool {
+        self.auto_arrival
+    }
+
     async fn drivers(&self, ctx: &Context) -> FieldResult<Vec<Driver>> {
         if !ctx.validate_is_member(self.id_org).await {
             return Err(FieldError::new(
@@ -142,6 +147,19 @@ impl Event {
         Ok(drivers)
     }
 
+    #[graphql(description = "What each driver did at the event")]
+    async fn driver_report(&self, ctx: &Context) -> FieldResult<DriverReport> {
+        if !ctx.validate_is_admin(self.id_org).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+
+        let report = DriverReport::load(&ctx.db, vec![self.id]).await?;
+        Ok(report)
+    }
+
     async fn is_driver(&self, ctx: &Context) -> FieldResult<bool> {
         if !ctx.validate_is_member(self.id_org).await {
             return Err(FieldError::new(
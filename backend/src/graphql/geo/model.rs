@@ -1,4 +1,3 @@
-// use std::f64::consts::PI;
 use google_maps::prelude::{
    LatLng as GoogleLatLng, Location as GoogleLocation, Prediction
 };
@@ -27,31 +26,28 @@ impl SearchResult {
 
 }
 
-#[derive(Debug, GraphQLObject, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, GraphQLObject, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct LatLng {
     pub lat: f64,
     pub lng: f64,
 }
 
-// fn to_radians(degrees: f64) -> f64 {
-//     degrees * PI / 180.0
-// }
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
 
 impl LatLng {
-    // pub(crate) fn distance(&self, other: LatLng) -> f64 {
-    //     let r = 6371.0; // Earth's radius in km
-
-    //     let lat1_rad = to_radians(self.lng);
-    //     let lat2_rad = to_radians(other.lng);
-    //     let delta_lat = to_radians(other.lng - self.lat);
-    //     let delta_lon = to_radians(other.lng - self.lng);
-
-    //     let a = (delta_lat / 2.0).sin().powi(2)
-    //         + lat1_rad.cos() * lat2_rad.cos() * (delta_lon / 2.0).sin().powi(2);
-    //     let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-
-    //     r * c
-    // }
+    #[doc = "The great circle distance to another point in meters"]
+    pub fn distance_meters(&self, other: &LatLng) -> f64 {
+        let lat_from = self.lat.to_radians();
+        let lat_to = other.lat.to_radians();
+        let delta_lat = (other.lat - self.lat).to_radians();
+        let delta_lng = (other.lng - self.lng).to_radians();
+
+        let a = (delta_lat / 2.0).sin().powi(2)
+            + lat_from.cos() * lat_to.cos() * (delta_lng / 2.0).sin().powi(2);
+        let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+        EARTH_RADIUS_METERS * c
+    }
 
     pub fn new(lat: f64, lng: f64) -> Self {
         Self { lat, lng }
@@ -1,10 +1,15 @@
-use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web::{dev, web, Error, FromRequest, HttpRequest, HttpResponse};
+use futures::{Stream, StreamExt};
 use juniper_actix::{graphql_handler, playground_handler, subscriptions::subscriptions_handler};
+use juniper::Variables;
 use juniper_graphql_ws::ConnectionConfig;
+use log::debug;
+use tokio::sync::watch;
 
 use crate::{
     db_util::AppState,
     middleware::UserId,
+    market::clock::Clock,
 };
 
 use super::context::Context;
@@ -21,7 +26,7 @@ pub async fn graphql(
     let twilio = data.twilio.clone();
     let jwt_secret = data.jwt_secret.clone();
     let google_maps_client = data.google_maps_client.clone();
-    let kv = data.kv.clone();
+    let market = data.market.clone();
     let is_mock = data.is_mock;
 
     let ctx = Context::new(
@@ -29,7 +34,7 @@ pub async fn graphql(
         twilio,
         jwt_secret,
         google_maps_client,
-        kv,
+        market,
         user_id.phone,
         is_mock,
     );
@@ -45,23 +50,83 @@ pub async fn subscriptions(data: web::Data<AppState>, req: HttpRequest, stream:
     let twilio = data.twilio.clone();
     let jwt_secret = data.jwt_secret.clone();
     let google_maps_client = data.google_maps_client.clone();
-    let kv = data.kv.clone();
+    let market = data.market.clone();
     let is_mock = data.is_mock;
 
 
+    let clock = market.clock.box_clone();
     let ctx = Context::new(
         db,
         twilio,
         jwt_secret,
         google_maps_client,
-        kv,
+        market,
         user_id.phone,
         is_mock,
-    );
+    ).expiring_at(user_id.expires_at);
+
+    // Authenticate once for the connection from the `connection_init` payload, a token in the
+    // authorization header still works for clients that can set it
+    let (expires_at_tx, expires_at_rx) = watch::channel(ctx.expires_at());
+    let init = move |params: Variables| async move {
+        match connection_token(&params) {
+            Some(token) => {
+                let ctx_authed = ctx.as_token_user(&token)?;
+                let _ = expires_at_tx.send(ctx_authed.expires_at());
+                Ok::<_, String>(ConnectionConfig::new(ctx_authed))
+            },
+            None => Ok(ConnectionConfig::new(ctx)),
+        }
+    };
 
-    let config = ConnectionConfig::new(ctx);
+    // Stop reading from the client once their token expires, which closes the connection
+    let mut payload: dev::Payload = dev::Payload::Stream { payload: Box::pin(until_token_expires(stream, expires_at_rx, clock)) };
+    let stream = web::Payload::from_request(&req, &mut payload).await?;
+    subscriptions_handler(req, stream, schema, init).await
+}
+
+#[doc = "End a stream when the token expires. The expiry can change, like when the token comes in the `connection_init` payload after connecting"]
+pub fn until_token_expires<S: Stream + Unpin>(stream: S, mut expires_at: watch::Receiver<Option<i64>>, clock: Box<dyn Clock>) -> impl Stream<Item = S::Item> {
+    async_stream::stream! {
+        let mut stream = stream;
+        let mut is_watching = true;
+        loop {
+            let remaining = (*expires_at.borrow()).map(|expires_at| (expires_at - clock.now() as i64).max(0) as u64);
+            let expired = async move {
+                match remaining {
+                    Some(remaining) => tokio::time::sleep(std::time::Duration::from_secs(remaining)).await,
+                    None => futures::future::pending().await,
+                }
+            };
+            // None when the expiry changed and the wait has to start over
+            let next = tokio::select! {
+                item = stream.next() => Some(item),
+                changed = expires_at.changed(), if is_watching => {
+                    // Nothing will change the expiry anymore
+                    if changed.is_err() { is_watching = false }
+                    None
+                },
+                _ = expired => {
+                    debug!("Closing a subscription connection, the token expired");
+                    Some(None)
+                },
+            };
+            match next {
+                Some(Some(item)) => yield item,
+                Some(None) => break,
+                None => continue,
+            }
+        }
+    }
+}
 
-    subscriptions_handler(req, stream,schema, config).await
+#[doc = "Get the token from a `connection_init` payload, sent as `token` or as an `Authorization` header"]
+pub fn connection_token(params: &Variables) -> Option<String> {
+    ["token", "authorization", "Authorization"]
+        .iter()
+        .find_map(|key| params.get(*key).and_then(|value| value.as_string_value()))
+        .map(|token| token.trim_start_matches("Bearer ").to_string())
+        .filter(|token| !token.is_empty())
 }
 
 pub async fn graphql_playground() -> Result<HttpResponse, Error> {
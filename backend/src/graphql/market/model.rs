@@ -0,0 +1,53 @@
+use juniper::GraphQLObject;
+
+use crate::{graphql::geo::model::LatLng, market::{geocoder::EstimateConfidence, event::cache::CachedEstimate, strategy::model::IdEventDriver}};
+
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct CachedDriverLocation {
+    pub id_driver: IdEventDriver,
+    pub location: LatLng,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct CachedEstimateEntry {
+    #[graphql(description = "The cache key, driver estimates are `{id_driver}-{stop}` and stop estimates are `{stop}-{stop}`")]
+    pub key: String,
+    #[graphql(description = "The estimated drive in seconds")]
+    pub duration: i32,
+    pub made_at: i32,
+    #[graphql(description = "How many seconds ago the estimate was made")]
+    pub age: i32,
+    pub confidence: EstimateConfidence,
+}
+
+impl CachedEstimateEntry {
+    pub fn new(key: String, est: &CachedEstimate, now: i32) -> Self {
+        Self {
+            key,
+            duration: est.duration.num_seconds() as i32,
+            made_at: est.made_at,
+            age: now - est.made_at,
+            confidence: est.confidence,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct CacheStats {
+    pub hits: i32,
+    pub misses: i32,
+    pub hit_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct MessangerStats {
+    pub published: i32,
+    pub publish_errors: i32,
+    pub subscriptions: i32,
+    #[graphql(description = "How many times a subscriber lost its connection and tried again")]
+    pub reconnects: i32,
+    #[graphql(description = "Messages subscribers skipped because they could not be read")]
+    pub decode_errors: i32,
+    #[graphql(description = "Messages subscribers fell too far behind to get")]
+    pub dropped: i32,
+}
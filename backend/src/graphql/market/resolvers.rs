@@ -0,0 +1,131 @@
+use juniper::{FieldResult, FieldError, graphql_value};
+use uuid::Uuid;
+
+use crate::{graphql::{context::Context, geo::model::LatLng}, market::{event::cache::{EventCacheSnapshot, CachedEstimate}, strategy::{model::IdEventDriver, driver::model::DriverStrategy}, estimate::model::StrategyEstimations}};
+
+use super::model::{CachedDriverLocation, CachedEstimateEntry, CacheStats, MessangerStats};
+
+pub struct MarketQuery;
+
+impl MarketQuery {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+pub struct MarketMutation;
+
+impl MarketMutation {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn estimate_entries<'a>(entries: impl Iterator<Item = (&'a String, &'a CachedEstimate)>, now: i32) -> Vec<CachedEstimateEntry> {
+    let mut entries: Vec<CachedEstimateEntry> = entries
+        .map(|(key, est)| CachedEstimateEntry::new(key.clone(), est, now))
+        .collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    entries
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl EventCacheSnapshot {
+    fn id_event(&self) -> Uuid {
+        self.id_event
+    }
+
+    fn property_location(&self) -> Option<LatLng> {
+        self.property_location
+    }
+
+    #[graphql(description = "The cached strategy of every driver, empty if there is no strategy in cache")]
+    fn drivers(&self) -> Vec<DriverStrategy> {
+        let mut drivers: Vec<DriverStrategy> = self.strategy.iter()
+            .flat_map(|strategy| strategy.drivers.values().cloned())
+            .collect();
+        drivers.sort_by_key(|driver| driver.id);
+        drivers
+    }
+
+    fn driver_locations(&self) -> Vec<CachedDriverLocation> {
+        let mut locations: Vec<CachedDriverLocation> = self.driver_locations.iter()
+            .map(|(id_driver, location)| CachedDriverLocation { id_driver: *id_driver, location: *location })
+            .collect();
+        locations.sort_by_key(|location| location.id_driver);
+        locations
+    }
+
+    fn estimates_drivers(&self, ctx: &Context) -> Vec<CachedEstimateEntry> {
+        let now = ctx.market.clock.now();
+        self.estimates_drivers.as_ref()
+            .map(|ests| estimate_entries(ests.drivers.iter(), now))
+            .unwrap_or_default()
+    }
+
+    fn estimates_stops(&self, ctx: &Context) -> Vec<CachedEstimateEntry> {
+        let now = ctx.market.clock.now();
+        self.estimates_stops.as_ref()
+            .map(|ests| estimate_entries(ests.connections.iter(), now))
+            .unwrap_or_default()
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl MarketQuery {
+    #[graphql(description = "What the market has in cache for an event")]
+    async fn event_cache(ctx: &Context, id_event: Uuid) -> FieldResult<EventCacheSnapshot> {
+        if !ctx.validate_is_superuser().await { return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        let snapshot = ctx.market.event.get_cache_snapshot(&id_event).await?;
+        Ok(snapshot)
+    }
+
+    #[graphql(description = "How often estimates have been found in cache since the server started")]
+    async fn cache_stats(ctx: &Context) -> FieldResult<CacheStats> {
+        if !ctx.validate_is_superuser().await { return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        let stats = ctx.market.event.cache_stats();
+        Ok(CacheStats {
+            hits: stats.hits as i32,
+            misses: stats.misses as i32,
+            hit_rate: stats.hit_rate(),
+        })
+    }
+
+    #[graphql(description = "How real time messages have been sent since the server started")]
+    async fn messanger_stats(ctx: &Context) -> FieldResult<MessangerStats> {
+        if !ctx.validate_is_superuser().await { return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        let stats = ctx.market.messanger.stats();
+        Ok(MessangerStats {
+            published: stats.published as i32,
+            publish_errors: stats.publish_errors as i32,
+            subscriptions: stats.subscriptions as i32,
+            reconnects: stats.reconnects as i32,
+            decode_errors: stats.decode_errors as i32,
+            dropped: stats.dropped as i32,
+        })
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl MarketMutation {
+    #[graphql(description = "Remove everything in cache for an event, drivers will be offline until they ping again")]
+    async fn evict_event_cache(ctx: &Context, id_event: Uuid) -> FieldResult<bool> {
+        if !ctx.validate_is_superuser().await { return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        ctx.market.event.evict_cache(&id_event).await?;
+        Ok(true)
+    }
+
+    #[graphql(description = "Drop the estimates for an event and calculate them again")]
+    async fn rebuild_event_cache(ctx: &Context, id_event: Uuid) -> FieldResult<StrategyEstimations> {
+        if !ctx.validate_is_superuser().await { return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        let estimations = ctx.market.event.rebuild_cache(&id_event).await?;
+        Ok(estimations)
+    }
+
+    #[graphql(description = "Take the reservations a driver has not picked up off of them, those go back to the pool")]
+    async fn reset_driver_strategy(ctx: &Context, id_event: Uuid, id_driver: IdEventDriver) -> FieldResult<StrategyEstimations> {
+        if !ctx.validate_is_superuser().await { return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        let estimations = ctx.market.event.reset_driver_strategy(&id_event, &id_driver).await?;
+        Ok(estimations)
+    }
+}
@@ -15,7 +15,12 @@ pub mod invites;
 pub mod media;
 pub mod colleges;
 pub mod groups;
+pub mod market;
+pub mod trails;
+pub mod reports;
+pub mod notifications;
+pub mod push_subscriptions;
 
 mod schema;
 
-pub use schema::{create_schema, Schema};
+pub use schema::{create_schema, Schema, with_snapshot, until_expired};
@@ -0,0 +1,228 @@
+use actix::Handler;
+use diesel::QueryResult;
+use diesel::prelude::*;
+
+use crate::db_util::DBActor;
+use crate::schema::notifications::dsl::*;
+use crate::schema::{notification_preferences, notification_templates, notification_mutes};
+
+use super::messages::{NotificationInsert, NotificationsClaimDue, NotificationMarkDone, NotificationMarkFailed, NotificationsForReservation, NotificationPreferencesForUser, NotificationPreferenceUpsert, NotificationTemplatesForOrg, NotificationTemplateGet, NotificationTemplateUpsert, NotificationTemplateDelete, NotificationMutesForOrg, NotificationMuteGet, NotificationMuteInsert, NotificationMuteDelete};
+use super::model::{DBNotification, NotificationStatus, DBNotificationPreference, DBNotificationTemplate, DBNotificationMute};
+
+impl Handler<NotificationInsert> for DBActor {
+    type Result = QueryResult<Option<DBNotification>>;
+
+    fn handle(&mut self, msg: NotificationInsert, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::insert_into(notifications)
+            .values(&msg.notification)
+            .on_conflict(dedupe_key)
+            .do_nothing()
+            .get_result::<DBNotification>(&mut conn)
+            .optional()
+    }
+}
+
+impl Handler<NotificationsClaimDue> for DBActor {
+    type Result = QueryResult<Vec<DBNotification>>;
+
+    fn handle(&mut self, msg: NotificationsClaimDue, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        conn.transaction(|conn| {
+            let due = notifications
+                .select(id)
+                .filter(status.eq(NotificationStatus::Pending.as_str()))
+                .filter(next_attempt_at.le(msg.now));
+            let ids: Vec<i32> = match msg.id_reservation {
+                Some(id_res) => due
+                    .filter(id_reservation.eq(id_res))
+                    .order(next_attempt_at.asc())
+                    .limit(msg.limit)
+                    .for_update()
+                    .skip_locked()
+                    .get_results(conn)?,
+                None => due
+                    .order(next_attempt_at.asc())
+                    .limit(msg.limit)
+                    .for_update()
+                    .skip_locked()
+                    .get_results(conn)?,
+            };
+
+            diesel::update(notifications.filter(id.eq_any(ids)))
+                .set(next_attempt_at.eq(msg.lease_until))
+                .get_results::<DBNotification>(conn)
+        })
+    }
+}
+
+impl Handler<NotificationMarkDone> for DBActor {
+    type Result = QueryResult<DBNotification>;
+
+    fn handle(&mut self, msg: NotificationMarkDone, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::update(notifications.find(msg.id))
+            .set((
+                status.eq(msg.status.as_str()),
+                message.eq(Some(msg.message)),
+                attempts.eq(attempts + 1),
+                sent_at.eq(Some(msg.at)),
+                last_error.eq(None::<String>),
+            ))
+            .get_result::<DBNotification>(&mut conn)
+    }
+}
+
+impl Handler<NotificationMarkFailed> for DBActor {
+    type Result = QueryResult<DBNotification>;
+
+    fn handle(&mut self, msg: NotificationMarkFailed, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        let query = diesel::update(notifications.find(msg.id));
+        match msg.retry_at {
+            Some(retry_at) => query
+                .set((status.eq(NotificationStatus::Pending.as_str()), attempts.eq(attempts + 1), last_error.eq(Some(msg.error)), next_attempt_at.eq(retry_at)))
+                .get_result::<DBNotification>(&mut conn),
+            None => query
+                .set((status.eq(NotificationStatus::Failed.as_str()), attempts.eq(attempts + 1), last_error.eq(Some(msg.error))))
+                .get_result::<DBNotification>(&mut conn),
+        }
+    }
+}
+
+impl Handler<NotificationsForReservation> for DBActor {
+    type Result = QueryResult<Vec<DBNotification>>;
+
+    fn handle(&mut self, msg: NotificationsForReservation, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        notifications
+            .filter(id_reservation.eq(msg.id_reservation))
+            .order((created_at.asc(), id.asc()))
+            .get_results::<DBNotification>(&mut conn)
+    }
+}
+
+impl Handler<NotificationPreferencesForUser> for DBActor {
+    type Result = QueryResult<Vec<DBNotificationPreference>>;
+
+    fn handle(&mut self, msg: NotificationPreferencesForUser, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        notification_preferences::table
+            .filter(notification_preferences::phone.eq(msg.phone))
+            .get_results::<DBNotificationPreference>(&mut conn)
+    }
+}
+
+impl Handler<NotificationPreferenceUpsert> for DBActor {
+    type Result = QueryResult<DBNotificationPreference>;
+
+    fn handle(&mut self, msg: NotificationPreferenceUpsert, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::insert_into(notification_preferences::table)
+            .values(&msg.preference)
+            .on_conflict((notification_preferences::phone, notification_preferences::channel, notification_preferences::kind))
+            .do_update()
+            .set((
+                notification_preferences::is_enabled.eq(msg.preference.is_enabled),
+                notification_preferences::updated_at.eq(msg.preference.updated_at),
+            ))
+            .get_result::<DBNotificationPreference>(&mut conn)
+    }
+}
+
+impl Handler<NotificationTemplatesForOrg> for DBActor {
+    type Result = QueryResult<Vec<DBNotificationTemplate>>;
+
+    fn handle(&mut self, msg: NotificationTemplatesForOrg, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        notification_templates::table
+            .filter(notification_templates::id_org.eq(msg.id_org))
+            .get_results::<DBNotificationTemplate>(&mut conn)
+    }
+}
+
+impl Handler<NotificationTemplateGet> for DBActor {
+    type Result = QueryResult<Option<DBNotificationTemplate>>;
+
+    fn handle(&mut self, msg: NotificationTemplateGet, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        notification_templates::table
+            .find((msg.id_org, msg.kind.as_str(), msg.locale.as_str()))
+            .get_result::<DBNotificationTemplate>(&mut conn)
+            .optional()
+    }
+}
+
+impl Handler<NotificationTemplateUpsert> for DBActor {
+    type Result = QueryResult<DBNotificationTemplate>;
+
+    fn handle(&mut self, msg: NotificationTemplateUpsert, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::insert_into(notification_templates::table)
+            .values(&msg.template)
+            .on_conflict((notification_templates::id_org, notification_templates::kind, notification_templates::locale))
+            .do_update()
+            .set((
+                notification_templates::body.eq(&msg.template.body),
+                notification_templates::title.eq(&msg.template.title),
+                notification_templates::updated_at.eq(msg.template.updated_at),
+            ))
+            .get_result::<DBNotificationTemplate>(&mut conn)
+    }
+}
+
+impl Handler<NotificationTemplateDelete> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: NotificationTemplateDelete, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::delete(notification_templates::table.find((msg.id_org, msg.kind.as_str(), msg.locale.as_str())))
+            .execute(&mut conn)
+    }
+}
+
+impl Handler<NotificationMutesForOrg> for DBActor {
+    type Result = QueryResult<Vec<DBNotificationMute>>;
+
+    fn handle(&mut self, msg: NotificationMutesForOrg, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        notification_mutes::table
+            .filter(notification_mutes::id_org.eq(msg.id_org))
+            .get_results::<DBNotificationMute>(&mut conn)
+    }
+}
+
+impl Handler<NotificationMuteGet> for DBActor {
+    type Result = QueryResult<Option<DBNotificationMute>>;
+
+    fn handle(&mut self, msg: NotificationMuteGet, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        notification_mutes::table
+            .find((msg.id_org, msg.kind.as_str()))
+            .get_result::<DBNotificationMute>(&mut conn)
+            .optional()
+    }
+}
+
+impl Handler<NotificationMuteInsert> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: NotificationMuteInsert, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::insert_into(notification_mutes::table)
+            .values(&msg.mute)
+            .on_conflict((notification_mutes::id_org, notification_mutes::kind))
+            .do_nothing()
+            .execute(&mut conn)
+    }
+}
+
+impl Handler<NotificationMuteDelete> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: NotificationMuteDelete, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::delete(notification_mutes::table.find((msg.id_org, msg.kind.as_str())))
+            .execute(&mut conn)
+    }
+}
@@ -0,0 +1,119 @@
+use actix::Message;
+use diesel::QueryResult;
+use uuid::Uuid;
+
+use crate::types::{phone::Phone, locale::Locale};
+
+use super::model::{DBNotification, DBNotificationInsertable, NotificationStatus, DBNotificationPreference, DBNotificationTemplate, NotificationKind, DBNotificationMute};
+
+#[doc = "Add a notification to the outbox, returns None if one with the same dedupe key is already there"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<Option<DBNotification>>")]
+pub struct NotificationInsert {
+    pub notification: DBNotificationInsertable,
+}
+
+#[doc = "Take the pending notifications that are due, they are not due again until `lease_until` so other workers skip them. Only the ones of `id_reservation` when it is set"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBNotification>>")]
+pub struct NotificationsClaimDue {
+    pub now: i32,
+    pub lease_until: i32,
+    pub limit: i64,
+    pub id_reservation: Option<Uuid>,
+}
+
+#[doc = "Record an attempt that went through, it was sent or there was nowhere to send it. `message` is what it said"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<DBNotification>")]
+pub struct NotificationMarkDone {
+    pub id: i32,
+    pub status: NotificationStatus,
+    pub message: String,
+    pub at: i32,
+}
+
+#[doc = "Record a failed attempt, the notification is tried again at `retry_at` or given up on when it is None"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<DBNotification>")]
+pub struct NotificationMarkFailed {
+    pub id: i32,
+    pub error: String,
+    pub retry_at: Option<i32>,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBNotification>>")]
+pub struct NotificationsForReservation {
+    pub id_reservation: Uuid,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBNotificationPreference>>")]
+pub struct NotificationPreferencesForUser {
+    pub phone: Phone,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<DBNotificationPreference>")]
+pub struct NotificationPreferenceUpsert {
+    pub preference: DBNotificationPreference,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBNotificationTemplate>>")]
+pub struct NotificationTemplatesForOrg {
+    pub id_org: Uuid,
+}
+
+#[doc = "The org's wording for a kind of notification in a language, None if they use the default"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<Option<DBNotificationTemplate>>")]
+pub struct NotificationTemplateGet {
+    pub id_org: Uuid,
+    pub kind: NotificationKind,
+    pub locale: Locale,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<DBNotificationTemplate>")]
+pub struct NotificationTemplateUpsert {
+    pub template: DBNotificationTemplate,
+}
+
+#[doc = "Go back to the default wording"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct NotificationTemplateDelete {
+    pub id_org: Uuid,
+    pub kind: NotificationKind,
+    pub locale: Locale,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBNotificationMute>>")]
+pub struct NotificationMutesForOrg {
+    pub id_org: Uuid,
+}
+
+#[doc = "If the org muted a kind of notification, None if they send it"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<Option<DBNotificationMute>>")]
+pub struct NotificationMuteGet {
+    pub id_org: Uuid,
+    pub kind: NotificationKind,
+}
+
+#[doc = "Stop sending a kind of notification, nothing happens if it is already muted"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct NotificationMuteInsert {
+    pub mute: DBNotificationMute,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct NotificationMuteDelete {
+    pub id_org: Uuid,
+    pub kind: NotificationKind,
+}
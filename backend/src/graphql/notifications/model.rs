@@ -0,0 +1,262 @@
+use diesel::{Queryable, Insertable};
+use juniper::{GraphQLObject, GraphQLEnum};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{schema::{notifications, notification_preferences, notification_templates, notification_mutes}, types::{phone::Phone, locale::Locale}, market::error::ErrorMarket};
+
+#[doc = "What a notification tells the rider"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, GraphQLEnum)]
+pub enum NotificationKind {
+    DriverAccepted,
+    DriverApproaching,
+    DriverArrived,
+    ReservationCancelled,
+    EventAnnouncement,
+}
+
+impl NotificationKind {
+    pub const ALL: [Self; 5] = [Self::DriverAccepted, Self::DriverApproaching, Self::DriverArrived, Self::ReservationCancelled, Self::EventAnnouncement];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DriverAccepted => "driver_accepted",
+            Self::DriverApproaching => "driver_approaching",
+            Self::DriverArrived => "driver_arrived",
+            Self::ReservationCancelled => "reservation_cancelled",
+            Self::EventAnnouncement => "event_announcement",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == s)
+    }
+}
+
+#[doc = "How a notification reaches the rider"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, GraphQLEnum)]
+pub enum NotificationChannel {
+    Sms,
+    WebPush,
+}
+
+impl NotificationChannel {
+    pub const ALL: [Self; 2] = [Self::Sms, Self::WebPush];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sms => "sms",
+            Self::WebPush => "web_push",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|channel| channel.as_str() == s)
+    }
+}
+
+#[doc = "Where a notification is in being delivered"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, GraphQLEnum)]
+pub enum NotificationStatus {
+    #[graphql(description = "Waiting to be sent, or to be tried again")]
+    Pending,
+    Sent,
+    #[graphql(description = "Gave up after too many attempts")]
+    Failed,
+    #[graphql(description = "The rider has no channel to be reached on")]
+    Skipped,
+}
+
+impl NotificationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(Self::Pending),
+            "sent" => Some(Self::Sent),
+            "failed" => Some(Self::Failed),
+            "skipped" => Some(Self::Skipped),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct DBNotification {
+    pub id: i32,
+    pub id_reservation: Uuid,
+    pub phone: Phone,
+    pub kind: String,
+    pub message: Option<String>,
+    pub dedupe_key: String,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: i32,
+    pub next_attempt_at: i32,
+    pub sent_at: Option<i32>,
+    pub id_driver: Option<i32>,
+    pub eta: Option<i32>,
+    pub announcement: Option<String>,
+}
+
+#[doc = "What a notification is about, it is written from the org's template when it is delivered"]
+#[derive(Debug, Serialize, Insertable)]
+#[diesel(table_name=notifications)]
+pub struct DBNotificationInsertable {
+    pub id_reservation: Uuid,
+    pub phone: Phone,
+    pub kind: String,
+    pub dedupe_key: String,
+    pub created_at: i32,
+    pub next_attempt_at: i32,
+    pub id_driver: Option<i32>,
+    pub eta: Option<i32>,
+    pub announcement: Option<String>,
+}
+
+#[doc = "A message for a rider about their reservation, and how delivering it went"]
+#[derive(Debug, Clone, Serialize, GraphQLObject)]
+pub struct Notification {
+    pub id: i32,
+    pub id_reservation: Uuid,
+    pub phone: Phone,
+    pub kind: NotificationKind,
+    #[graphql(description = "What the rider was sent, written from the org's template when it was delivered")]
+    pub message: Option<String>,
+    pub status: NotificationStatus,
+    pub attempts: i32,
+    #[graphql(description = "Why the last attempt failed")]
+    pub last_error: Option<String>,
+    pub created_at: i32,
+    #[graphql(description = "When it will be tried again, if it is pending")]
+    pub next_attempt_at: i32,
+    pub sent_at: Option<i32>,
+    #[graphql(description = "The driver it is about, if any")]
+    pub id_driver: Option<i32>,
+    #[graphql(description = "How many seconds away the driver was, for approaching notifications")]
+    pub eta: Option<i32>,
+    #[graphql(description = "What the org announced, for event announcements")]
+    pub announcement: Option<String>,
+}
+
+#[doc = "Fails for a kind or status this version doesn't know, such as one added by a newer server"]
+impl TryFrom<DBNotification> for Notification {
+    type Error = ErrorMarket;
+
+    fn try_from(row: DBNotification) -> Result<Self, Self::Error> {
+        let kind = NotificationKind::from_str(&row.kind)
+            .ok_or_else(|| ErrorMarket::BadValue(format!("Unknown kind '{}' for notification {}", row.kind, row.id)))?;
+        let status = NotificationStatus::from_str(&row.status)
+            .ok_or_else(|| ErrorMarket::BadValue(format!("Unknown status '{}' for notification {}", row.status, row.id)))?;
+        Ok(Self {
+            id: row.id,
+            id_reservation: row.id_reservation,
+            phone: row.phone,
+            kind,
+            message: row.message,
+            status,
+            attempts: row.attempts,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            next_attempt_at: row.next_attempt_at,
+            sent_at: row.sent_at,
+            id_driver: row.id_driver,
+            eta: row.eta,
+            announcement: row.announcement,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Queryable, Insertable)]
+#[diesel(table_name=notification_preferences)]
+pub struct DBNotificationPreference {
+    pub phone: Phone,
+    pub channel: String,
+    pub kind: String,
+    pub is_enabled: bool,
+    pub updated_at: i32,
+}
+
+#[doc = "If a user gets one kind of notification on one channel"]
+#[derive(Debug, Clone, Serialize, GraphQLObject)]
+pub struct NotificationPreference {
+    pub channel: NotificationChannel,
+    pub kind: NotificationKind,
+    pub is_enabled: bool,
+    #[graphql(description = "If the user chose this, otherwise it is the default for the channel")]
+    pub is_set: bool,
+}
+
+#[doc = "A user's choices of which notifications they get where, anything they have not chosen uses the default"]
+#[derive(Debug, Clone)]
+pub struct NotificationPreferences {
+    chosen: Vec<(NotificationChannel, NotificationKind, bool)>,
+}
+
+impl NotificationPreferences {
+    pub fn new(rows: Vec<DBNotificationPreference>) -> Self {
+        let chosen = rows.into_iter()
+            .filter_map(|row| Some((NotificationChannel::from_str(&row.channel)?, NotificationKind::from_str(&row.kind)?, row.is_enabled)))
+            .collect();
+        Self { chosen }
+    }
+
+    #[doc = "What the user chose for a kind on a channel, None if they have not"]
+    pub fn chosen(&self, channel: NotificationChannel, kind: NotificationKind) -> Option<bool> {
+        self.chosen.iter()
+            .find(|(c, k, _)| *c == channel && *k == kind)
+            .map(|(_, _, is_enabled)| *is_enabled)
+    }
+
+    #[doc = "Every channel and kind, `is_default` is what the channel uses when nothing was chosen"]
+    pub fn all(&self, is_default: impl Fn(NotificationChannel) -> bool) -> Vec<NotificationPreference> {
+        NotificationChannel::ALL.into_iter()
+            .flat_map(|channel| NotificationKind::ALL.into_iter().map(move |kind| (channel, kind)))
+            .map(|(channel, kind)| {
+                let chosen = self.chosen(channel, kind);
+                NotificationPreference { channel, kind, is_enabled: chosen.unwrap_or_else(|| is_default(channel)), is_set: chosen.is_some() }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Queryable, Insertable)]
+#[diesel(table_name=notification_templates)]
+pub struct DBNotificationTemplate {
+    pub id_org: Uuid,
+    pub kind: String,
+    pub locale: String,
+    pub body: String,
+    pub updated_at: i32,
+    pub title: Option<String>,
+}
+
+#[doc = "The wording of a kind of notification in a language"]
+#[derive(Debug, Clone, Serialize, GraphQLObject)]
+pub struct NotificationTemplate {
+    pub kind: NotificationKind,
+    pub locale: Locale,
+    #[graphql(description = "The text, with placeholders such as {vehicle} in braces")]
+    pub body: String,
+    #[graphql(description = "Who the notification is from, the title of web pushes and the start of texts. It can have placeholders too")]
+    pub title: String,
+    #[graphql(description = "If the org wrote this, otherwise it is the default wording")]
+    pub is_custom: bool,
+}
+
+#[doc = "A kind of notification an org doesn't send to its riders"]
+#[derive(Debug, Serialize, Queryable, Insertable)]
+#[diesel(table_name=notification_mutes)]
+pub struct DBNotificationMute {
+    pub id_org: Uuid,
+    pub kind: String,
+    pub updated_at: i32,
+}
@@ -0,0 +1,28 @@
+use juniper::{FieldResult, FieldError, graphql_value};
+use uuid::Uuid;
+
+use crate::graphql::{context::Context, reservations::messages::ReservationGet, events::{Event, messages::EventGet}};
+
+use super::model::Notification;
+
+pub struct NotificationQuery;
+
+impl NotificationQuery {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl NotificationQuery {
+    #[graphql(description = "Every notification sent, or being sent, to the rider of a reservation")]
+    async fn reservation(ctx: &Context, id_reservation: Uuid) -> FieldResult<Vec<Notification>> {
+        let reservation = ctx.db.send(ReservationGet { id: id_reservation }).await??;
+        let event: Event = ctx.db.send(EventGet { id: reservation.id_event }).await??.into();
+        if !ctx.validate_is_admin(event.id_org).await && !ctx.validate_is_superuser().await {
+            return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not an admin" })));
+        }
+        let notifications = ctx.market.notification.list_for_reservation(&id_reservation).await?;
+        Ok(notifications)
+    }
+}
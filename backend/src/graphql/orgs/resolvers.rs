@@ -14,6 +14,7 @@ use crate::{
             messages::{EventGet, EventUpdate, EventsList},
             Event, FormEvent, DBEventInsertable,
         },
+        reports::model::{DriverReport, FormSemester},
         locations::{
             messages::{OrgLocationGet, OrgLocationUpdate, OrgLocations},
             FormLocation, OrgLocation,
@@ -26,8 +27,9 @@ use crate::{
             messages::{VehicleGet, VehicleUpdate, VehiclesList},
             FormVehicle, Vehicle,
         }, invites::{messages::{OrgInviteCreate, GetInvite, OrgInviteRevoke, OrgInvites}, model::Invite}, colleges::{model::College, messages::CollegeGet}, groups::{model::{Group, DBGroupInsertable, FormGroup, GroupMembership, DBGroupMembershipInsertable}, messages::{OrgGroupList, OrgGroupUpdate, OrgGroupGet, OrgGroupMemberUpdate}},
+        notifications::{model::{NotificationTemplate, NotificationKind, DBNotificationTemplate, DBNotificationMute}, messages::{NotificationTemplatesForOrg, NotificationTemplateUpsert, NotificationTemplateDelete, NotificationMutesForOrg, NotificationMuteInsert, NotificationMuteDelete}},
     },
-    types::phone::Phone, market::{error::ErrorMarket, util::now, event},
+    types::{phone::Phone, locale::Locale}, market::{error::ErrorMarket, util::now, event, template},
 };
 
 use super::{
@@ -170,6 +172,25 @@ impl Organization {
         Ok(events)
     }
 
+    #[graphql(description = "What each driver did at the events that started in the semester")]
+    async fn driver_report(&self, ctx: &Context, semester: FormSemester) -> FieldResult<DriverReport> {
+        if !ctx.validate_is_admin(self.id).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+
+        let Some(range) = semester.range() else {
+            return Err(FieldError::new(
+                "Invalid semester",
+                graphql_value!({ "internal_error": "The year is out of range" }),
+            ));
+        };
+        let report = DriverReport::load_between(&ctx.db, self.id, range).await?;
+        Ok(report)
+    }
+
     async fn invites(&self, ctx: &Context) -> FieldResult<Vec<Invite>> {
         if !ctx.validate_is_admin(self.id).await {
             return Err(FieldError::new(
@@ -205,6 +226,47 @@ impl Organization {
         let groups = result.into_iter().map(Group::from).collect();
         Ok(groups)
     }
+
+    #[graphql(description = "The wording of every kind of notification in every language, the org's own or the default")]
+    async fn notification_templates(&self, ctx: &Context) -> FieldResult<Vec<NotificationTemplate>> {
+        if !ctx.validate_is_admin(self.id).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+
+        let db = ctx.db.clone();
+        let custom = db.send(NotificationTemplatesForOrg { id_org: self.id }).await??;
+        let templates = NotificationKind::ALL.into_iter()
+            .flat_map(|kind| Locale::ALL.into_iter().map(move |locale| (kind, locale)))
+            .map(|(kind, locale)| {
+                let custom = custom.iter()
+                    .find(|custom| custom.kind == kind.as_str() && custom.locale == locale.as_str());
+                NotificationTemplate {
+                    kind,
+                    locale,
+                    is_custom: custom.is_some(),
+                    body: custom.map_or_else(|| template::default_template(kind, locale).to_owned(), |custom| custom.body.clone()),
+                    title: custom.and_then(|custom| custom.title.clone()).unwrap_or_else(|| template::DEFAULT_TITLE.to_owned()),
+                }
+            })
+            .collect();
+        Ok(templates)
+    }
+
+    #[graphql(description = "The kinds of notification the org doesn't send to its riders")]
+    async fn muted_notifications(&self, ctx: &Context) -> FieldResult<Vec<NotificationKind>> {
+        if !ctx.validate_is_admin(self.id).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+
+        let mutes = ctx.db.send(NotificationMutesForOrg { id_org: self.id }).await??;
+        Ok(NotificationKind::ALL.into_iter().filter(|kind| mutes.iter().any(|mute| mute.kind == kind.as_str())).collect())
+    }
 }
 
 #[juniper::graphql_object(Context = Context)]
@@ -492,10 +554,12 @@ impl OrgMutation {
             id_org,
             obsolete_at: form.obsolete_at,
             published_at: form.published_at,
+            auto_arrival: form.auto_arrival.unwrap_or(false),
             id: id_event,
         };
 
         let _result_upsert = db.send(EventUpdate { event }).await??;
+        ctx.market.event.evict_settings(&id_event).await?;
         let result = db.send(EventGet { id: id_event }).await.map_err(|_| {
             FieldError::new(
                 "Error getting event",
@@ -710,6 +774,67 @@ impl OrgMutation {
         }}).await??;
         Ok(result)
     }
+
+    #[graphql(description = "Change the wording of a kind of notification in a language, no body goes back to the default. No title uses the org's name")]
+    async fn update_notification_template(ctx: &Context, id_org: Uuid, kind: NotificationKind, locale: Locale, body: Option<String>, title: Option<String>) -> FieldResult<NotificationTemplate> {
+        if !ctx.validate_is_admin(id_org).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+        let db = ctx.db.clone();
+        let Some(body) = body else {
+            db.send(NotificationTemplateDelete { id_org, kind, locale }).await??;
+            return Ok(NotificationTemplate { kind, locale, body: template::default_template(kind, locale).to_owned(), title: template::DEFAULT_TITLE.to_owned(), is_custom: false })
+        };
+        template::validate(&body)?;
+        if let Some(title) = &title { template::validate(title)? }
+        let result = db.send(NotificationTemplateUpsert { template: DBNotificationTemplate {
+            id_org,
+            kind: kind.as_str().to_owned(),
+            locale: locale.as_str().to_owned(),
+            body,
+            updated_at: ctx.market.clock.now(),
+            title,
+        }}).await??;
+        Ok(NotificationTemplate { kind, locale, body: result.body, title: result.title.unwrap_or_else(|| template::DEFAULT_TITLE.to_owned()), is_custom: true })
+    }
+
+    #[graphql(description = "Stop or start sending a kind of notification to the org's riders, returns the kinds that are muted")]
+    async fn update_notification_mute(ctx: &Context, id_org: Uuid, kind: NotificationKind, is_muted: bool) -> FieldResult<Vec<NotificationKind>> {
+        if !ctx.validate_is_admin(id_org).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+        let db = ctx.db.clone();
+        if is_muted {
+            db.send(NotificationMuteInsert { mute: DBNotificationMute {
+                id_org,
+                kind: kind.as_str().to_owned(),
+                updated_at: ctx.market.clock.now(),
+            }}).await??;
+        } else {
+            db.send(NotificationMuteDelete { id_org, kind }).await??;
+        }
+        let mutes = db.send(NotificationMutesForOrg { id_org }).await??;
+        Ok(NotificationKind::ALL.into_iter().filter(|kind| mutes.iter().any(|mute| mute.kind == kind.as_str())).collect())
+    }
+
+    #[graphql(description = "Send a message to every rider with a ride still to come at an event, returns how many riders it went to")]
+    async fn announce_event(ctx: &Context, id_event: Uuid, message: String) -> FieldResult<i32> {
+        let event: Event = ctx.db.send(EventGet { id: id_event }).await??.into();
+        if !ctx.validate_is_admin(event.id_org).await {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not an admin" }),
+            ));
+        }
+        let count = ctx.market.notification.send_event_announcement(&id_event, &message).await?;
+        Ok(count as i32)
+    }
  }
 
 #[doc = "Tells the nextjs server to invalidate the cache for an event."]
@@ -0,0 +1,61 @@
+use actix::Handler;
+use diesel::QueryResult;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+use crate::db_util::DBActor;
+use crate::schema::push_subscriptions::dsl::*;
+
+use super::messages::{PushSubscriptionUpsert, PushSubscriptionDelete, PushSubscriptionDeleteEndpoint, PushSubscriptionsForUser};
+use super::model::DBPushSubscription;
+
+impl Handler<PushSubscriptionUpsert> for DBActor {
+    type Result = QueryResult<DBPushSubscription>;
+
+    fn handle(&mut self, msg: PushSubscriptionUpsert, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::insert_into(push_subscriptions)
+            .values(&msg.subscription)
+            .on_conflict(endpoint)
+            .do_update()
+            .set((
+                phone.eq(excluded(phone)),
+                p256dh.eq(excluded(p256dh)),
+                auth.eq(excluded(auth)),
+                created_at.eq(excluded(created_at)),
+            ))
+            .get_result::<DBPushSubscription>(&mut conn)
+    }
+}
+
+impl Handler<PushSubscriptionDelete> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: PushSubscriptionDelete, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::delete(push_subscriptions.filter(phone.eq(msg.phone)).filter(endpoint.eq(msg.endpoint)))
+            .execute(&mut conn)
+    }
+}
+
+impl Handler<PushSubscriptionDeleteEndpoint> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: PushSubscriptionDeleteEndpoint, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::delete(push_subscriptions.filter(endpoint.eq(msg.endpoint)))
+            .execute(&mut conn)
+    }
+}
+
+impl Handler<PushSubscriptionsForUser> for DBActor {
+    type Result = QueryResult<Vec<DBPushSubscription>>;
+
+    fn handle(&mut self, msg: PushSubscriptionsForUser, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        push_subscriptions
+            .filter(phone.eq(msg.phone))
+            .order(id.asc())
+            .get_results::<DBPushSubscription>(&mut conn)
+    }
+}
@@ -0,0 +1,33 @@
+use actix::Message;
+use diesel::QueryResult;
+
+use crate::types::phone::Phone;
+
+use super::model::{DBPushSubscription, DBPushSubscriptionInsertable};
+
+#[doc = "Save a subscription, an endpoint that is already saved moves to this user"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<DBPushSubscription>")]
+pub struct PushSubscriptionUpsert {
+    pub subscription: DBPushSubscriptionInsertable,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct PushSubscriptionDelete {
+    pub phone: Phone,
+    pub endpoint: String,
+}
+
+#[doc = "Remove a subscription the push service says is gone"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct PushSubscriptionDeleteEndpoint {
+    pub endpoint: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBPushSubscription>>")]
+pub struct PushSubscriptionsForUser {
+    pub phone: Phone,
+}
@@ -0,0 +1,3 @@
+pub mod actors;
+pub mod messages;
+pub mod model;
@@ -0,0 +1,35 @@
+use diesel::{Queryable, Insertable};
+use juniper::GraphQLInputObject;
+use serde::Serialize;
+
+use crate::{schema::push_subscriptions, types::phone::Phone};
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+pub struct DBPushSubscription {
+    pub id: i32,
+    pub phone: Phone,
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+    pub created_at: i32,
+}
+
+#[derive(Debug, Serialize, Insertable)]
+#[diesel(table_name=push_subscriptions)]
+pub struct DBPushSubscriptionInsertable {
+    pub phone: Phone,
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+    pub created_at: i32,
+}
+
+#[doc = "A browser's Web Push subscription, from `PushSubscription.toJSON()`"]
+#[derive(Debug, Clone, GraphQLInputObject)]
+pub struct FormPushSubscription {
+    pub endpoint: String,
+    #[graphql(description = "The `keys.p256dh` of the subscription")]
+    pub p256dh: String,
+    #[graphql(description = "The `keys.auth` of the subscription")]
+    pub auth: String,
+}
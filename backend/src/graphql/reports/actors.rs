@@ -0,0 +1,46 @@
+use actix::Handler;
+use diesel::QueryResult;
+use diesel::prelude::*;
+
+use crate::db_util::DBActor;
+use crate::graphql::{drivers::DBDriver, reservations::DBReservation, trails::model::DBDriverPing};
+use crate::schema::{event_drivers, driver_pings, reservations};
+
+use super::messages::{ReportDrivers, ReportPings, ReportReservations};
+
+impl Handler<ReportDrivers> for DBActor {
+    type Result = QueryResult<Vec<DBDriver>>;
+
+    fn handle(&mut self, msg: ReportDrivers, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        event_drivers::table
+            .filter(event_drivers::id_event.eq_any(msg.id_events))
+            .get_results::<DBDriver>(&mut conn)
+    }
+}
+
+impl Handler<ReportPings> for DBActor {
+    type Result = QueryResult<Vec<DBDriverPing>>;
+
+    fn handle(&mut self, msg: ReportPings, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        driver_pings::table
+            .filter(driver_pings::id_event.eq_any(msg.id_events))
+            .order((driver_pings::id_driver.asc(), driver_pings::at.asc(), driver_pings::id.asc()))
+            .get_results::<DBDriverPing>(&mut conn)
+    }
+}
+
+impl Handler<ReportReservations> for DBActor {
+    type Result = QueryResult<Vec<DBReservation>>;
+
+    fn handle(&mut self, msg: ReportReservations, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        reservations::table
+            .filter(reservations::id_event.eq_any(msg.id_events))
+            .filter(reservations::id_driver.is_not_null())
+            .filter(reservations::is_complete.eq(true))
+            .filter(reservations::is_cancelled.eq(false))
+            .get_results::<DBReservation>(&mut conn)
+    }
+}
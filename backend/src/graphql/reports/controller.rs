@@ -0,0 +1,86 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use actix::Addr;
+use uuid::Uuid;
+
+use crate::{db_util::DBActor, graphql::{events::messages::EventsList, drivers::DBDriver, reservations::DBReservation, trails::model::DBDriverPing, geo::model::LatLng}, market::{types::MarketResult, strategy::model::IdEventDriver}, types::phone::Phone};
+
+use super::{messages::{ReportDrivers, ReportPings, ReportReservations}, model::{DriverReport, DriverReportRow, PING_GAP_SECONDS, METERS_PER_MILE}};
+
+#[derive(Debug, Default)]
+struct DriverTotals {
+    events: HashSet<Uuid>,
+    trips: i32,
+    passengers: i32,
+    distance_meters: f64,
+    active_seconds: i32,
+}
+
+impl DriverReport {
+    #[doc = "Get the report for every driver of the events"]
+    pub async fn load(db: &Addr<DBActor>, id_events: Vec<Uuid>) -> MarketResult<Self> {
+        let drivers = db.send(ReportDrivers { id_events: id_events.clone() }).await??;
+        let pings = db.send(ReportPings { id_events: id_events.clone() }).await??;
+        let reservations = db.send(ReportReservations { id_events }).await??;
+        Ok(Self::build(&drivers, &pings, &reservations))
+    }
+
+    #[doc = "Get the report for the events of an org that started between start and end, the end is not included"]
+    pub async fn load_between(db: &Addr<DBActor>, id_org: Uuid, (start, end): (i32, i32)) -> MarketResult<Self> {
+        let id_events = db.send(EventsList { id_org }).await??
+            .into_iter()
+            .filter(|event| start <= event.time_start && event.time_start < end)
+            .map(|event| event.id)
+            .collect();
+        Self::load(db, id_events).await
+    }
+
+    #[doc = "Total up the report, pings should be in order by driver and time"]
+    pub fn build(drivers: &[DBDriver], pings: &[DBDriverPing], reservations: &[DBReservation]) -> Self {
+        let phones: HashMap<IdEventDriver, &str> = drivers.iter().map(|driver| (driver.id, driver.phone.as_str())).collect();
+        let mut totals: BTreeMap<&str, DriverTotals> = BTreeMap::new();
+
+        for pair in pings.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if from.id_driver != to.id_driver { continue }
+            let Some(phone) = phones.get(&from.id_driver) else { continue };
+            let gap = to.at - from.at;
+            if gap > PING_GAP_SECONDS { continue }
+
+            let driver = totals.entry(phone).or_default();
+            driver.events.insert(from.id_event);
+            driver.active_seconds += gap;
+            driver.distance_meters += LatLng { lat: from.lat, lng: from.lng }.distance_meters(&LatLng { lat: to.lat, lng: to.lng });
+        }
+
+        for reservation in reservations {
+            let Some(phone) = reservation.id_driver.and_then(|id_driver| phones.get(&id_driver)) else { continue };
+            let driver = totals.entry(phone).or_default();
+            driver.events.insert(reservation.id_event);
+            driver.trips += 1;
+            driver.passengers += reservation.passenger_count;
+        }
+
+        let rows = totals.into_iter()
+            .filter_map(|(phone, totals)| Some(DriverReportRow {
+                phone: Phone::new(phone).ok()?,
+                events: totals.events.len() as i32,
+                trips: totals.trips,
+                passengers: totals.passengers,
+                distance_meters: totals.distance_meters,
+                distance_miles: totals.distance_meters / METERS_PER_MILE,
+                active_seconds: totals.active_seconds,
+                active_hours: totals.active_seconds as f64 / 3600.0,
+            }))
+            .collect();
+        Self { rows }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("phone,events,trips,passengers,distance_miles,active_hours\n");
+        for row in &self.rows {
+            csv.push_str(&format!("{},{},{},{},{:.2},{:.2}\n", row.phone, row.events, row.trips, row.passengers, row.distance_miles, row.active_hours));
+        }
+        csv
+    }
+}
@@ -0,0 +1,25 @@
+use actix::Message;
+use diesel::QueryResult;
+use uuid::Uuid;
+
+use crate::graphql::{drivers::DBDriver, reservations::DBReservation, trails::model::DBDriverPing};
+
+#[doc = "Every driver of the events, including removed drivers"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBDriver>>")]
+pub struct ReportDrivers {
+    pub id_events: Vec<Uuid>,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBDriverPing>>")]
+pub struct ReportPings {
+    pub id_events: Vec<Uuid>,
+}
+
+#[doc = "The reservations of the events that were completed by a driver"]
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBReservation>>")]
+pub struct ReportReservations {
+    pub id_events: Vec<Uuid>,
+}
@@ -0,0 +1,5 @@
+pub mod actors;
+pub mod controller;
+pub mod resolvers;
+pub mod messages;
+pub mod model;
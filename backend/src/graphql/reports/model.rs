@@ -0,0 +1,69 @@
+use chrono::{TimeZone, Utc};
+use juniper::{GraphQLObject, GraphQLEnum, GraphQLInputObject};
+use serde::{Serialize, Deserialize};
+
+use crate::types::phone::Phone;
+
+#[doc = "Pings further apart than this are from different drives, the time between them is not counted"]
+pub const PING_GAP_SECONDS: i32 = 600;
+
+pub const METERS_PER_MILE: f64 = 1609.344;
+
+#[derive(Debug, Clone, Serialize, GraphQLObject)]
+pub struct DriverReportRow {
+    pub phone: Phone,
+    #[graphql(description = "How many events the driver drove for")]
+    pub events: i32,
+    #[graphql(description = "Completed reservations")]
+    pub trips: i32,
+    #[graphql(description = "Passengers of the completed reservations")]
+    pub passengers: i32,
+    pub distance_meters: f64,
+    pub distance_miles: f64,
+    #[graphql(description = "Time spent online, from the driver locations")]
+    pub active_seconds: i32,
+    pub active_hours: f64,
+}
+
+#[doc = "What each driver did over a set of events"]
+#[derive(Debug, Clone, Serialize)]
+pub struct DriverReport {
+    pub rows: Vec<DriverReportRow>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, GraphQLEnum)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Term {
+    #[graphql(description = "January through June")]
+    Spring,
+    #[graphql(description = "July through December")]
+    Fall,
+}
+
+#[derive(Debug, Clone, Deserialize, GraphQLInputObject)]
+pub struct FormSemester {
+    pub year: i32,
+    pub term: Term,
+}
+
+impl FormSemester {
+    #[doc = "When the semester starts and ends, the end is not included. None if the semester does not fit in a timestamp"]
+    pub fn range(&self) -> Option<(i32, i32)> {
+        let start = |year: i32, month: u32| Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+            .single()
+            .and_then(|date| i32::try_from(date.timestamp()).ok());
+        match self.term {
+            Term::Spring => Some((start(self.year, 1)?, start(self.year, 7)?)),
+            Term::Fall => Some((start(self.year, 7)?, start(self.year.checked_add(1)?, 1)?)),
+        }
+    }
+
+    #[doc = "A name for the file of the report"]
+    pub fn filename(&self) -> String {
+        let term = match self.term {
+            Term::Spring => "spring",
+            Term::Fall => "fall",
+        };
+        format!("drivers-{}-{}.csv", self.year, term)
+    }
+}
@@ -0,0 +1,15 @@
+use crate::graphql::context::Context;
+
+use super::model::{DriverReport, DriverReportRow};
+
+#[juniper::graphql_object(Context = Context)]
+impl DriverReport {
+    fn rows(&self) -> Vec<DriverReportRow> {
+        self.rows.clone()
+    }
+
+    #[graphql(description = "The report as a CSV file, distances are in miles. It can also be downloaded from /reports/{id_org}/drivers.csv?year=&term=")]
+    fn csv(&self) -> String {
+        self.to_csv()
+    }
+}
@@ -95,7 +95,7 @@ impl Handler<ReservationReserve> for DBActor {
         let mut conn = self.0.get().expect("Could not get DB connection from pool");
 
         let reservation = DBReservation {
-            made_at: now(),
+            made_at: msg.made_at,
             reserver: msg.phone.to_string(),
             passenger_count: msg.form.passenger_count,
             is_cancelled: false,
@@ -109,6 +109,8 @@ impl Handler<ReservationReserve> for DBActor {
             id_event: msg.id_event,
             is_driver_arrived: false,
             driver_arrived_at: None,
+            driver_arrived_auto: false,
+            driver_accepted_at: None,
             est_pickup: msg.est_pickup,
             est_dropoff: msg.est_dropoff,
             rating: None,
@@ -137,7 +139,7 @@ impl Handler<ReservationCancel> for DBActor {
         let mut conn = self.0.get().expect("Could not get DB connection from pool");
 
         diesel::update(reservations.find(msg.id))
-            .set((is_cancelled.eq(true), cancelled_at.eq(now() as i32)))
+            .set((is_cancelled.eq(true), cancelled_at.eq(msg.cancelled_at)))
             .get_result::<DBReservation>(&mut conn)
     }
 }
@@ -166,7 +168,7 @@ impl Handler<ReservationConfirmPickup> for DBActor {
             let (idx, stop_not_complete) = stops_incomplete.first().expect("No more incomplete stops").clone();
             let mut stop_complete = stop_not_complete.clone();
             stop_complete.is_complete = true;
-            stop_complete.complete_at = Some(now().try_into().unwrap());
+            stop_complete.complete_at = Some(msg.at);
 
             let mut stops_new = reservation.stops.clone().get_stops_mut().clone();
             stops_new.remove(idx);
@@ -203,18 +205,18 @@ impl Handler<ReservationConfirmDropoff> for DBActor {
             let (idx, stop_not_complete) = stops_incomplete.first().expect("No more incomplete stops").clone();
             let mut stop_complete = stop_not_complete.clone();
             stop_complete.is_complete = true;
-            stop_complete.complete_at = Some(now().try_into().unwrap());
+            stop_complete.complete_at = Some(msg.at);
 
             let mut stops_new = reservation.stops.clone().get_stops_mut().clone();
             stops_new.remove(idx);
             stops_new.insert(idx, stop_complete);
 
             diesel::update(reservations.find(msg.id))
-                .set((stops.eq(ReservationStops(stops_new)), is_complete.eq(is_final_stop), complete_at.eq(if is_final_stop { Some(now()) } else { None })))
+                .set((stops.eq(ReservationStops(stops_new)), is_complete.eq(is_final_stop), complete_at.eq(if is_final_stop { Some(msg.at) } else { None })))
                 .get_result::<DBReservation>(&mut conn)
         } else {
             diesel::update(reservations.find(msg.id))
-                .set((is_complete.eq(true), complete_at.eq(Some(now()))))
+                .set((is_complete.eq(true), complete_at.eq(Some(msg.at))))
                 .get_result::<DBReservation>(&mut conn)
         }
 
@@ -239,7 +241,7 @@ impl Handler<ReservationAssignDriver> for DBActor {
                 Ok(_) => {
                     // Proceed to update if the reservation is found and not yet assigned
                     diesel::update(reservations.find(msg.id))
-                        .set(id_driver.eq(msg.id_driver))
+                        .set((id_driver.eq(msg.id_driver), driver_accepted_at.eq(Some(msg.at))))
                         .get_result::<DBReservation>(c)
                 },
                 Err(_) => Err(diesel::result::Error::NotFound),
@@ -256,7 +258,7 @@ impl Handler<ReservationRemoveDriver> for DBActor {
 
         error!("Removing driver assignment to reservation with id: {}", msg.id);
         diesel::update(reservations.find(msg.id))
-            .set(id_driver.eq(None::<i32>))
+            .set((id_driver.eq(None::<i32>), driver_accepted_at.eq(None::<i32>)))
             .get_result::<DBReservation>(&mut conn)
     }
 }
@@ -280,7 +282,7 @@ impl Handler<ReservationConfirmArrival> for DBActor {
     fn handle(&mut self, msg: ReservationConfirmArrival, _ctx: &mut Self::Context) -> Self::Result {
         let mut conn = self.0.get().expect("Unable to establish connection");
             diesel::update(reservations.find(msg.id))
-                .set((is_driver_arrived.eq(true), driver_arrived_at.eq(Some(now()))))
+                .set((is_driver_arrived.eq(true), driver_arrived_at.eq(Some(msg.at)), driver_arrived_auto.eq(msg.is_auto)))
                 .get_result::<DBReservation>(&mut conn)
     }
 
@@ -45,12 +45,14 @@ pub struct ReservationReserve {
     pub form: FormReservationGeocoded,
     pub est_pickup: i32,
     pub est_dropoff: i32,
+    pub made_at: i32,
 }
 
 #[derive(Message)]
 #[rtype(result = "QueryResult<DBReservation>")]
 pub struct ReservationCancel {
     pub id: Uuid,
+    pub cancelled_at: i32,
 }
 
 #[derive(Message)]
@@ -65,6 +67,7 @@ pub struct ReservationUpdate {
 pub struct ReservationAssignDriver {
     pub id: Uuid,
     pub id_driver: i32,
+    pub at: i32,
 }
 
 #[derive(Message)]
@@ -77,18 +80,22 @@ pub struct ReservationRemoveDriver {
 #[rtype(result = "QueryResult<DBReservation>")]
 pub struct ReservationConfirmPickup {
     pub id: Uuid,
+    pub at: i32,
 }
 
 #[derive(Message)]
 #[rtype(result = "QueryResult<DBReservation>")]
 pub struct ReservationConfirmDropoff {
     pub id: Uuid,
+    pub at: i32,
 }
 
 #[derive(Message)]
 #[rtype(result = "QueryResult<DBReservation>")]
 pub struct ReservationConfirmArrival {
     pub id: Uuid,
+    pub at: i32,
+    pub is_auto: bool,
 }
 
 
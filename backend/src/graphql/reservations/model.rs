@@ -23,6 +23,9 @@ pub struct Reservation {
     pub is_dropoff: bool,
     pub is_driver_arrived: bool,
     pub driver_arrived_at: Option<i32>,
+    #[doc = "If the arrival was confirmed from the driver location instead of by the driver"]
+    pub driver_arrived_auto: bool,
+    pub driver_accepted_at: Option<i32>,
     pub est_pickup: i32,
     pub est_dropoff: i32,
     pub rating: Option<i32>,
@@ -56,6 +59,8 @@ pub struct DBReservation {
     pub driver_arrived_at: Option<i32>,
     pub est_pickup: i32,
     pub est_dropoff: i32,
+    pub driver_arrived_auto: bool,
+    pub driver_accepted_at: Option<i32>,
 }
 
 impl
@@ -82,6 +87,8 @@ impl
         diesel::sql_types::Nullable<diesel::sql_types::Integer>,  // driver_arrived_at
         diesel::sql_types::Integer,  // est_pickup
         diesel::sql_types::Integer,  // est_dropoff
+        diesel::sql_types::Bool,              // driver_arrived_auto
+        diesel::sql_types::Nullable<diesel::sql_types::Integer>,  // driver_accepted_at
         ),
         Pg,
     > for DBReservation
@@ -90,7 +97,7 @@ impl
         i32, String, i32, Option<i32>, Option<i32>, Uuid, Uuid,
         Option<i32>, Option<i32>, Option<i32>, Option<i32>, Option<i32>,
         bool, bool, Option<i32>, ReservationStops, bool, bool,
-        Option<i32>, i32, i32,
+        Option<i32>, i32, i32, bool, Option<i32>,
     );
 
     fn build(row: Self::Row) -> Result<Self, Box<dyn Error + Send + Sync>> {
@@ -116,6 +123,8 @@ impl
             driver_arrived_at: row.18,
             est_pickup: row.19,
             est_dropoff: row.20,
+            driver_arrived_auto: row.21,
+            driver_accepted_at: row.22,
         })
     }
 }
@@ -145,6 +154,8 @@ impl From<DBReservation> for Reservation {
             is_dropoff: db_res.is_dropoff,
             is_driver_arrived: db_res.is_driver_arrived,
             driver_arrived_at: db_res.driver_arrived_at,
+            driver_arrived_auto: db_res.driver_arrived_auto,
+            driver_accepted_at: db_res.driver_accepted_at,
             est_pickup: db_res.est_pickup,
             est_dropoff: db_res.est_dropoff,
             rating: db_res.rating,
@@ -110,6 +110,15 @@ impl Reservation {
         &self.driver_arrived_at
     }
 
+    fn driver_accepted_at(&self) -> &Option<i32> {
+        &self.driver_accepted_at
+    }
+
+    #[graphql(description = "If the arrival was confirmed from the driver location instead of by the driver")]
+    fn driver_arrived_auto(&self) -> bool {
+        self.driver_arrived_auto
+    }
+
     fn rating(&self) -> &Option<i32> {
         &self.rating
     }
@@ -202,6 +211,13 @@ impl ReservationMutation {
         Ok(reservation)
     }
 
+    #[graphql(description = "Send a message to the driver of a reservation")]
+    async fn message_driver(ctx: &Context, id: Uuid, message: String) -> FieldResult<bool> {
+        if !ctx.validate_owns_reservation(id).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+        ctx.market.reservation.message_driver(&id, message).await?;
+        Ok(true)
+    }
+
     #[graphql(description = "Rate a reservation")]
     async fn rate(ctx: &Context, id: Uuid, rating: i32, feedback: i32) -> FieldResult<Reservation> {
         if !ctx.validate_owns_reservation(id).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
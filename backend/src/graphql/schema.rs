@@ -1,4 +1,4 @@
-use crate::market::types::StreamMessageMarket;
+use crate::market::{types::StreamMessageMarket, strategy::model::IdEventDriver, messages::MessageMarket, messanger::Messanger};
 
 use super::{
     auth::AuthMutation,
@@ -11,10 +11,15 @@ use super::{
     drivers::DriverMutation,
     invites::resolvers::{InviteQuery, InviteMutation},
     colleges::resolvers::{CollegeMutation, CollegeQuery},
+    market::resolvers::{MarketMutation, MarketQuery},
+    trails::resolvers::TrailQuery,
+    notifications::resolvers::NotificationQuery,
 };
+use futures::{StreamExt, future::Either, stream::SelectAll};
+use log::warn;
 use juniper::{graphql_object, graphql_value, RootNode, FieldError, FieldResult};
 use uuid::Uuid;
-use std::sync::Arc;
+use std::{sync::Arc, collections::HashSet};
 
 
 
@@ -29,6 +34,9 @@ pub struct QueryRoot {
     vehicle_query: VehicleQuery,
     invite_query: InviteQuery,
     college_query: CollegeQuery,
+    market_query: MarketQuery,
+    trail_query: TrailQuery,
+    notification_query: NotificationQuery,
 }
 
 #[graphql_object(context = Context)]
@@ -69,6 +77,18 @@ impl QueryRoot {
     fn colleges(&self) -> &CollegeQuery {
         &self.college_query
     }
+
+    fn market(&self) -> &MarketQuery {
+        &self.market_query
+    }
+
+    fn trails(&self) -> &TrailQuery {
+        &self.trail_query
+    }
+
+    fn notifications(&self) -> &NotificationQuery {
+        &self.notification_query
+    }
 }
 
 pub struct MutationRoot {
@@ -79,6 +99,7 @@ pub struct MutationRoot {
     driver_mutation: DriverMutation,
     invite_mutation: InviteMutation,
     college_mutation: CollegeMutation,
+    market_mutation: MarketMutation,
 }
 
 #[graphql_object(context = Context)]
@@ -110,43 +131,177 @@ impl MutationRoot {
     fn colleges(&self) -> &CollegeMutation {
         &self.college_mutation
     }
+
+    fn market(&self) -> &MarketMutation {
+        &self.market_mutation
+    }
 }
 
 pub struct Subscription;
 
+#[doc = "Get who is subscribing, from the connection or from the deprecated token argument"]
+fn subscriber(ctx: &Context, token: Option<String>) -> FieldResult<Context> {
+    if ctx.user.is_some() { return Ok(ctx.as_subscriber()) }
+    let Some(token) = token else {
+        return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not logged in" })))
+    };
+    warn!("A subscription was authenticated with the deprecated token argument");
+    ctx.as_token_user(&token)
+        .map_err(|_| FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Invalid token" })))
+}
 
+#[doc = "End the stream when the subscriber's token expires, the connection is closed by the handler"]
+pub fn until_expired(ctx: &Context, stream: StreamMessageMarket) -> StreamMessageMarket {
+    let Some(expires_at) = ctx.expires_at() else { return stream };
+    let remaining = (expires_at - ctx.market.clock.now() as i64).max(0) as u64;
+    Box::pin(async_stream::stream! {
+        let mut stream = stream;
+        let expired = tokio::time::sleep(std::time::Duration::from_secs(remaining));
+        tokio::pin!(expired);
+        loop {
+            // None once the token has expired
+            let next = tokio::select! {
+                _ = &mut expired => None,
+                message = stream.next() => Some(message),
+            };
+            match next {
+                Some(Some(message)) => yield message,
+                Some(None) => break,
+                None => {
+                    yield Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Token expired" })));
+                    break
+                },
+            }
+        }
+    })
+}
 
+#[doc = "Send the snapshot then the stream. The snapshot has the cursor of the last message it includes, so a client can catch up from it"]
+pub fn with_snapshot(snapshot: Vec<MessageMarket>, cursor: Option<String>, stream: StreamMessageMarket) -> StreamMessageMarket {
+    let snapshot = snapshot.into_iter().map(move |message| Ok(match &cursor {
+        Some(cursor) => message.with_cursor(cursor.clone()),
+        None => message,
+    }));
+    Box::pin(futures::stream::iter(snapshot).chain(stream))
+}
 
+#[doc = "Send the summaries of an org and what happens at its events, joining an event the first time a summary of it comes"]
+fn follow_org_events(messanger: Box<dyn Messanger>, stream_org: StreamMessageMarket, streams_event: SelectAll<StreamMessageMarket>, followed: HashSet<Uuid>) -> StreamMessageMarket {
+    Box::pin(async_stream::stream! {
+        let mut stream_org = stream_org;
+        let mut streams_event = streams_event;
+        let mut followed = followed;
+        loop {
+            // Left for the org, Right for its events. An empty SelectAll ends right away, so it's only polled when there are events
+            let next = tokio::select! {
+                message = stream_org.next() => Either::Left(message),
+                Some(message) = streams_event.next(), if !streams_event.is_empty() => Either::Right(message),
+            };
+            match next {
+                Either::Left(Some(Ok(MessageMarket::EventSummary(summary)))) => {
+                    if followed.insert(summary.id_event) {
+                        match messanger.subscribe(format!("event:{}", summary.id_event), None).await {
+                            Ok(stream) => streams_event.push(stream),
+                            Err(e) => warn!("Could not follow event {} of an org subscription, {e:?}", summary.id_event),
+                        }
+                    }
+                    yield Ok(MessageMarket::EventSummary(summary))
+                },
+                Either::Left(Some(message)) => yield message,
+                Either::Left(None) => break,
+                Either::Right(message) => yield message,
+            }
+        }
+    })
+}
 
 #[juniper::graphql_subscription(context = Context)]
 impl Subscription {
-    #[graphql(description = "Subscribe to real time reservation data")]
-    async fn reservation(ctx: &Context, token: String, id: Uuid) -> FieldResult<StreamMessageMarket> {
-        let phone = ctx.jwt.decode(token)
-            .map_err(|_| FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Invalid token" })))?;
-
-        let ctx_authed = ctx.as_user(phone);
+    #[graphql(description = "Subscribe to real time reservation data, starting with the reservation, its estimate and where the driver is. Pass the cursor of the last message you got as `since` to get what you missed instead, a `ReplayTruncated` first means some of it is gone and the state should be loaded again")]
+    async fn reservation(
+        ctx: &Context,
+        #[graphql(description = "Deprecated, send the token in the connection_init payload instead")] token: Option<String>,
+        id: Uuid,
+        since: Option<String>,
+    ) -> FieldResult<StreamMessageMarket> {
+        let ctx_authed = subscriber(ctx, token)?;
         if !ctx_authed.validate_owns_reservation(id).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
 
-        let stream = ctx.market.messanger.subscribe(format!("res:{id}")).await?;
+        // A client catching up from a cursor already has the state, the snapshot would be newer than what is replayed
+        if since.is_some() {
+            let stream = ctx.market.messanger.subscribe(format!("res:{id}"), since).await?;
+            return Ok(until_expired(&ctx_authed, stream))
+        }
 
-        Ok(stream)
+        // Join before taking the snapshot so nothing that changes in between is missed
+        let (cursor, stream) = ctx.market.messanger.subscribe_from_end(format!("res:{id}")).await?;
+        let snapshot = ctx.market.reservation.get_snapshot(&id).await?;
+        Ok(until_expired(&ctx_authed, with_snapshot(snapshot, cursor, stream)))
     }
 
-    #[graphql(description = "Subscribe to real time event data")]
-    async fn event(ctx: &Context, token: String, id_event: Uuid) -> FieldResult<StreamMessageMarket> {
-        let phone = ctx.jwt.decode(token)
-            .map_err(|_| FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Invalid token" })))?;
+    #[graphql(description = "Subscribe to real time event data, starting with the event's estimations. Pass the cursor of the last message you got as `since` to get what you missed instead, a `ReplayTruncated` first means some of it is gone and the state should be loaded again")]
+    async fn event(
+        ctx: &Context,
+        #[graphql(description = "Deprecated, send the token in the connection_init payload instead")] token: Option<String>,
+        id_event: Uuid,
+        since: Option<String>,
+    ) -> FieldResult<StreamMessageMarket> {
+        let ctx_authed = subscriber(ctx, token)?;
 
         let event = ctx.db.send(EventGet { id: id_event }).await
             .map_err(|_| FieldError::new("Not Found", graphql_value!({ "internal_error": "Event not found" })))??;
 
-        let ctx_authed = ctx.as_user(phone);
         if !ctx_authed.validate_is_admin(event.id_org).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
 
-        let stream = ctx.market.messanger.subscribe(format!("event:{id_event}")).await?;
+        if since.is_some() {
+            let stream = ctx.market.messanger.subscribe(format!("event:{id_event}"), since).await?;
+            return Ok(until_expired(&ctx_authed, stream))
+        }
+
+        let (cursor, stream) = ctx.market.messanger.subscribe_from_end(format!("event:{id_event}")).await?;
+        let snapshot = ctx.market.event.get_snapshot(&id_event).await?;
+        Ok(until_expired(&ctx_authed, with_snapshot(snapshot, cursor, stream)))
+    }
+
+    #[graphql(description = "Subscribe to real time data for every event of an org happening now, starting with a summary of each. Summaries come every few seconds at most, and events that start later are followed from their first summary")]
+    async fn org(
+        ctx: &Context,
+        #[graphql(description = "Deprecated, send the token in the connection_init payload instead")] token: Option<String>,
+        id_org: Uuid,
+    ) -> FieldResult<StreamMessageMarket> {
+        let ctx_authed = subscriber(ctx, token)?;
+        if !ctx_authed.validate_is_admin(id_org).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+
+        // Join the org before listing the events so a summary of one that starts in between isn't missed
+        let (_, stream_org) = ctx.market.messanger.subscribe_from_end(format!("org:{id_org}")).await?;
+        let events = ctx.market.event.list_active_for_org(&id_org).await?;
+        let mut streams_event = futures::stream::SelectAll::new();
+        let mut followed = HashSet::new();
+        let mut snapshot = Vec::new();
+        for event in events {
+            streams_event.push(ctx.market.messanger.subscribe(format!("event:{}", event.id), None).await?);
+            snapshot.push(MessageMarket::EventSummary(ctx.market.event.get_summary(&event.id).await?));
+            followed.insert(event.id);
+        }
+
+        let stream = follow_org_events(ctx.market.messanger.box_clone(), stream_org, streams_event, followed);
+        Ok(until_expired(&ctx_authed, with_snapshot(snapshot, None, stream)))
+    }
+
+    #[graphql(description = "Subscribe to real time data for a driver at an event")]
+    async fn driver(
+        ctx: &Context,
+        #[graphql(description = "Deprecated, send the token in the connection_init payload instead")] token: Option<String>,
+        id_event: Uuid,
+        id_driver: IdEventDriver,
+    ) -> FieldResult<StreamMessageMarket> {
+        let ctx_authed = subscriber(ctx, token)?;
+        if !ctx_authed.validate_is_driver_for_event(&id_event, &id_driver).await { return Err(FieldError::new("Not authorized", graphql_value!({ "internal_error": "Not authorized" }))) }
+
+        let stream_driver = ctx.market.messanger.subscribe(format!("driver:{id_driver}"), None).await?;
+        let stream_offers = ctx.market.messanger.subscribe(format!("drivers:{id_event}"), None).await?;
 
-        Ok(stream)
+        Ok(until_expired(&ctx_authed, Box::pin(futures::stream::select(stream_driver, stream_offers))))
     }
 }
 
@@ -162,6 +317,9 @@ pub fn create_schema() -> Schema {
         vehicle_query: VehicleQuery::new(),
         invite_query: InviteQuery::new(),
         college_query: CollegeQuery::new(),
+        market_query: MarketQuery::new(),
+        trail_query: TrailQuery::new(),
+        notification_query: NotificationQuery::new(),
     };
 
     let mutation = MutationRoot {
@@ -172,6 +330,7 @@ pub fn create_schema() -> Schema {
         driver_mutation: DriverMutation::new(),
         invite_mutation: InviteMutation::new(),
         college_mutation: CollegeMutation::new(),
+        market_mutation: MarketMutation::new(),
     };
 
     Arc::new(RootNode::new(query, mutation, Subscription))
@@ -0,0 +1,56 @@
+use actix::Handler;
+use diesel::QueryResult;
+use diesel::prelude::*;
+
+use crate::db_util::DBActor;
+use crate::schema::driver_pings::dsl::*;
+
+use super::messages::{DriverPingsInsert, DriverPingsBetween, DriverPingsPrune};
+use super::model::DBDriverPing;
+
+#[doc = "Rows per insert, Postgres allows at most 65535 bind parameters in a query and a ping has five"]
+const PINGS_PER_INSERT: usize = 5000;
+
+impl Handler<DriverPingsInsert> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: DriverPingsInsert, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        conn.transaction(|conn| {
+            let mut inserted = 0;
+            for chunk in msg.pings.chunks(PINGS_PER_INSERT) {
+                // A retried batch has samples that were already saved
+                inserted += diesel::insert_into(driver_pings)
+                    .values(chunk)
+                    .on_conflict((id_driver, at))
+                    .do_nothing()
+                    .execute(conn)?;
+            }
+            Ok(inserted)
+        })
+    }
+}
+
+impl Handler<DriverPingsBetween> for DBActor {
+    type Result = QueryResult<Vec<DBDriverPing>>;
+
+    fn handle(&mut self, msg: DriverPingsBetween, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        driver_pings
+            .filter(id_driver.eq(msg.id_driver))
+            .filter(at.ge(msg.from))
+            .filter(at.le(msg.to))
+            .order((at.asc(), id.asc()))
+            .get_results::<DBDriverPing>(&mut conn)
+    }
+}
+
+impl Handler<DriverPingsPrune> for DBActor {
+    type Result = QueryResult<usize>;
+
+    fn handle(&mut self, msg: DriverPingsPrune, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+        diesel::delete(driver_pings.filter(at.lt(msg.before)))
+            .execute(&mut conn)
+    }
+}
@@ -0,0 +1,26 @@
+use actix::Message;
+use diesel::QueryResult;
+
+use crate::market::strategy::model::IdEventDriver;
+
+use super::model::{DBDriverPing, DBDriverPingInsertable};
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct DriverPingsInsert {
+    pub pings: Vec<DBDriverPingInsertable>,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<Vec<DBDriverPing>>")]
+pub struct DriverPingsBetween {
+    pub id_driver: IdEventDriver,
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<usize>")]
+pub struct DriverPingsPrune {
+    pub before: i32,
+}
@@ -0,0 +1,4 @@
+pub mod actors;
+pub mod resolvers;
+pub mod messages;
+pub mod model;
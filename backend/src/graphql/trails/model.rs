@@ -0,0 +1,93 @@
+use diesel::{Queryable, Insertable};
+use juniper::{GraphQLObject, GraphQLInputObject};
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{schema::driver_pings, graphql::{geo::model::LatLng, reservations::stops::model::FormLatLng}, market::strategy::model::IdEventDriver};
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct DBDriverPing {
+    pub id: i32,
+    pub id_event: Uuid,
+    pub id_driver: IdEventDriver,
+    pub lat: f64,
+    pub lng: f64,
+    pub at: i32,
+}
+
+#[derive(Debug, Serialize, Insertable)]
+#[diesel(table_name=driver_pings)]
+pub struct DBDriverPingInsertable {
+    pub id_event: Uuid,
+    pub id_driver: IdEventDriver,
+    pub lat: f64,
+    pub lng: f64,
+    pub at: i32,
+}
+
+#[doc = "Where a driver was at a point in time"]
+#[derive(Debug, Clone, Serialize, GraphQLObject)]
+pub struct TrailPoint {
+    pub location: LatLng,
+    pub at: i32,
+}
+
+impl From<DBDriverPing> for TrailPoint {
+    fn from(ping: DBDriverPing) -> Self {
+        Self {
+            location: LatLng { lat: ping.lat, lng: ping.lng },
+            at: ping.at,
+        }
+    }
+}
+
+#[doc = "Where a driver was, taken by their phone"]
+#[derive(Debug, Clone, GraphQLInputObject)]
+pub struct FormTrailPoint {
+    pub location: FormLatLng,
+    #[graphql(description = "When the location was taken, in unix seconds")]
+    pub at: i32,
+}
+
+impl From<FormTrailPoint> for TrailPoint {
+    fn from(point: FormTrailPoint) -> Self {
+        Self {
+            location: point.location.into(),
+            at: point.at,
+        }
+    }
+}
+
+#[doc = "The path a driver took for a reservation, from when they accepted it until the dropoff"]
+#[derive(Debug, Clone, Serialize)]
+pub struct Trip {
+    pub id_reservation: Uuid,
+    pub id_driver: IdEventDriver,
+    pub started_at: i32,
+    #[doc = "None when the reservation has not been dropped off yet"]
+    pub ended_at: Option<i32>,
+    pub points: Vec<TrailPoint>,
+}
+
+impl Trip {
+    #[doc = "The trip as a GeoJSON feature with a line string of the path"]
+    pub fn geojson(&self) -> serde_json::Value {
+        let coordinates: Vec<[f64; 2]> = self.points.iter().map(|point| [point.location.lng, point.location.lat]).collect();
+        let times: Vec<i32> = self.points.iter().map(|point| point.at).collect();
+        json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": coordinates,
+            },
+            "properties": {
+                "id_reservation": self.id_reservation,
+                "id_driver": self.id_driver,
+                "started_at": self.started_at,
+                "ended_at": self.ended_at,
+                "times": times,
+            },
+        })
+    }
+}
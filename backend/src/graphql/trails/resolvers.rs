@@ -0,0 +1,56 @@
+use juniper::{FieldResult, FieldError, graphql_value};
+use uuid::Uuid;
+
+use crate::{graphql::{context::Context, reservations::messages::ReservationGet, events::{Event, messages::EventGet}}, market::strategy::model::IdEventDriver};
+
+use super::model::{Trip, TrailPoint};
+
+pub struct TrailQuery;
+
+impl TrailQuery {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl Trip {
+    fn id_reservation(&self) -> Uuid {
+        self.id_reservation
+    }
+
+    fn id_driver(&self) -> IdEventDriver {
+        self.id_driver
+    }
+
+    fn started_at(&self) -> i32 {
+        self.started_at
+    }
+
+    fn ended_at(&self) -> Option<i32> {
+        self.ended_at
+    }
+
+    fn points(&self) -> Vec<TrailPoint> {
+        self.points.clone()
+    }
+
+    #[graphql(description = "The trip as a GeoJSON feature")]
+    fn geojson(&self) -> String {
+        self.geojson().to_string()
+    }
+}
+
+#[juniper::graphql_object(Context = Context)]
+impl TrailQuery {
+    #[graphql(description = "The path a driver took for a reservation, from accepting it until the dropoff")]
+    async fn trip(ctx: &Context, id_reservation: Uuid) -> FieldResult<Trip> {
+        let reservation = ctx.db.send(ReservationGet { id: id_reservation }).await??;
+        let event: Event = ctx.db.send(EventGet { id: reservation.id_event }).await??.into();
+        if !ctx.validate_is_admin(event.id_org).await && !ctx.validate_is_superuser().await {
+            return Err(FieldError::new("Unauthorized", graphql_value!({ "internal_error": "Not an admin" })));
+        }
+        let trip = ctx.market.driver.get_trip(&id_reservation).await?;
+        Ok(trip)
+    }
+}
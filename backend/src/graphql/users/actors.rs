@@ -9,7 +9,7 @@ use diesel::{QueryDsl, QueryResult};
 use crate::db_util::DBActor;
 
 use super::{DBUser, DBUserInsertable};
-use super::messages::{UserUpdate, UserList, UserGet, UserDelete, UserSMSOpt};
+use super::messages::{UserUpdate, UserList, UserGet, UserDelete, UserSMSOpt, UserLocaleSet};
 
 impl Handler<UserList> for DBActor {
     type Result = QueryResult<Vec<DBUser>>;
@@ -54,7 +54,7 @@ impl Handler<UserUpdate> for DBActor {
                 image_url.eq(excluded(image_url)),
                 updated_at.eq(now()),
             ))
-            .returning((phone, name, image_url, created_at, updated_at, is_opted_in_sms))
+            .returning((phone, name, image_url, created_at, updated_at, is_opted_in_sms, locale))
             .get_result::<DBUser>(&mut conn)
     }
 }
@@ -83,7 +83,24 @@ impl Handler<UserSMSOpt> for DBActor {
                 updated_at.eq(now())
             ))
             .filter(phone.eq(msg.phone))
-            .returning((phone, name, image_url, created_at, updated_at, is_opted_in_sms))
+            .returning((phone, name, image_url, created_at, updated_at, is_opted_in_sms, locale))
+            .get_result::<DBUser>(&mut conn)
+    }
+}
+
+impl Handler<UserLocaleSet> for DBActor {
+    type Result = QueryResult<DBUser>;
+
+    fn handle(&mut self, msg: UserLocaleSet, _ctx: &mut Self::Context) -> Self::Result {
+        let mut conn = self.0.get().expect("Could not get DB connection from pool");
+
+        diesel::update(users)
+            .set((
+                locale.eq(Some(msg.locale.as_str())),
+                updated_at.eq(now())
+            ))
+            .filter(phone.eq(msg.phone))
+            .returning((phone, name, image_url, created_at, updated_at, is_opted_in_sms, locale))
             .get_result::<DBUser>(&mut conn)
     }
 }
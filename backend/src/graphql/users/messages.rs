@@ -1,5 +1,5 @@
 use actix::Message;
-use crate::types::phone::Phone;
+use crate::types::{phone::Phone, locale::Locale};
 
 use super::{model::DBUser, FormUserInsert};
 use diesel::QueryResult;
@@ -33,3 +33,10 @@ pub struct UserSMSOpt {
     pub phone: Phone,
     pub opt_in: bool
 }
+
+#[derive(Message)]
+#[rtype(result = "QueryResult<DBUser>")]
+pub struct UserLocaleSet {
+    pub phone: Phone,
+    pub locale: Locale,
+}
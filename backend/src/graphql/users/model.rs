@@ -6,7 +6,7 @@ use crate::r#const::{ANONYMOUS_NAME, ANONYMOUS_IMAGE_URL};
 use crate::schema::users;
 
 use crate::graphql::memberships::model::Membership;
-use crate::types::phone::Phone;
+use crate::types::{phone::Phone, locale::Locale};
 
 #[derive(Debug, Queryable, Insertable, AsChangeset)]
 #[diesel(table_name=users)]
@@ -17,6 +17,7 @@ pub struct DBUser {
     pub created_at: i32,
     pub updated_at: i32,
     pub is_opted_in_sms: Option<bool>,
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
@@ -50,6 +51,7 @@ pub struct User {
     pub created_at: i32,
     pub updated_at: i32,
     pub is_opted_in_sms: Option<bool>,
+    pub locale: Locale,
 }
 
 impl User {
@@ -62,6 +64,7 @@ impl User {
             created_at: 0,
             updated_at: 0,
             is_opted_in_sms: None,
+            locale: Locale::default(),
         }
     }
 }
@@ -76,6 +79,7 @@ impl From<DBUser> for User {
             created_at: db_user.created_at,
             updated_at: db_user.updated_at,
             is_opted_in_sms: db_user.is_opted_in_sms,
+            locale: db_user.locale.as_deref().and_then(Locale::from_tag).unwrap_or_default(),
         }
     }
 }
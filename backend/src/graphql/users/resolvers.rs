@@ -3,10 +3,10 @@ use std::collections::{HashMap, HashSet};
 use crate::{graphql::{
     context::Context,
     memberships::{messages::UserMemberships, model::Membership}, geo::model::SearchResult, reservations::{messages::ReservationsListByReserver, stops::model::ReservationStop, Reservation}, media::messages::MediaGet,
-}, types::phone::Phone};
+}, types::{phone::Phone, locale::Locale}};
 
 use super::{
-    messages::{UserGet, UserList, UserUpdate, UserDelete, UserSMSOpt},
+    messages::{UserGet, UserList, UserUpdate, UserDelete, UserSMSOpt, UserLocaleSet},
     model::User,
     FormUser, FormUserInsert,
 };
@@ -38,6 +38,11 @@ impl User {
         &self.is_opted_in_sms
     }
 
+    #[graphql(description = "The language notifications are sent in")]
+    fn locale(&self) -> Locale {
+        self.locale
+    }
+
     async fn memberships(&self, ctx: &Context) -> FieldResult<Vec<Membership>> {
         let is_authed = ctx.validate_is_authed().await;
         if !is_authed {
@@ -73,6 +78,33 @@ impl User {
         Ok(memberships)
     }
 
+    #[graphql(description = "Which notifications the user gets on each channel")]
+    async fn notification_preferences(&self, ctx: &Context) -> FieldResult<Vec<NotificationPreference>> {
+        let is_authed = ctx.validate_is_authed().await;
+        if !is_authed {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not authorized" }),
+            ));
+        }
+        if ctx.phone().ne(&self.phone) {
+            let ok = ctx.validate_is_superuser().await;
+            if !ok {
+                return Err(FieldError::new(
+                    "Unauthorized",
+                    graphql_value!({ "internal_error": "Not authorized" }),
+                ));
+            }
+        }
+
+        let rows = ctx.db.send(NotificationPreferencesForUser { phone: self.phone.clone() }).await??;
+        let is_opted_in_sms = self.is_opted_in_sms == Some(true);
+        Ok(NotificationPreferences::new(rows).all(|channel| match channel {
+            NotificationChannel::Sms => is_opted_in_sms,
+            NotificationChannel::WebPush => true,
+        }))
+    }
+
     async fn common_stops(&self, ctx: &Context) -> FieldResult<Vec<SearchResult>> {
         let is_authed = ctx.validate_is_authed().await;
         if !is_authed {
@@ -267,4 +299,83 @@ impl UserMutation {
         Ok(result)
     }
 
+    #[graphql(description = "Choose the language notifications are sent in")]
+    async fn me_locale(ctx: &Context, locale: Locale) -> FieldResult<User> {
+        let ok = ctx.validate_is_authed().await;
+        if !ok {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not authorized" }),
+            ));
+        }
+
+        let db = ctx.db.clone();
+        let result: User = db.send(UserLocaleSet { phone: ctx.phone(), locale }).await??.into();
+        Ok(result)
+    }
+
+    #[graphql(description = "Turn a kind of notification on or off for a channel")]
+    async fn me_notification_preference(ctx: &Context, channel: NotificationChannel, kind: NotificationKind, is_enabled: bool) -> FieldResult<User> {
+        let ok = ctx.validate_is_authed().await;
+        if !ok {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not authorized" }),
+            ));
+        }
+
+        let db = ctx.db.clone();
+        db.send(NotificationPreferenceUpsert {
+            preference: DBNotificationPreference {
+                phone: ctx.phone(),
+                channel: channel.as_str().to_owned(),
+                kind: kind.as_str().to_owned(),
+                is_enabled,
+                updated_at: ctx.market.clock.now(),
+            },
+        }).await??;
+        match db.send(UserGet { phone: ctx.phone() }).await? {
+            Ok(result) => Ok(result.into()),
+            Err(_) => Ok(User::anonymous(&ctx.phone())),
+        }
+    }
+
+    #[graphql(description = "Get notifications on this browser with web push")]
+    async fn me_push_subscribe(ctx: &Context, form: FormPushSubscription) -> FieldResult<bool> {
+        let ok = ctx.validate_is_authed().await;
+        if !ok {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not authorized" }),
+            ));
+        }
+
+        let db = ctx.db.clone();
+        db.send(PushSubscriptionUpsert {
+            subscription: DBPushSubscriptionInsertable {
+                phone: ctx.phone(),
+                endpoint: form.endpoint,
+                p256dh: form.p256dh,
+                auth: form.auth,
+                created_at: ctx.market.clock.now(),
+            },
+        }).await??;
+        Ok(true)
+    }
+
+    #[graphql(description = "Stop getting web push notifications on a browser")]
+    async fn me_push_unsubscribe(ctx: &Context, endpoint: String) -> FieldResult<bool> {
+        let ok = ctx.validate_is_authed().await;
+        if !ok {
+            return Err(FieldError::new(
+                "Unauthorized",
+                graphql_value!({ "internal_error": "Not authorized" }),
+            ));
+        }
+
+        let db = ctx.db.clone();
+        let removed = db.send(PushSubscriptionDelete { phone: ctx.phone(), endpoint }).await??;
+        Ok(removed > 0)
+    }
+
 }
@@ -16,15 +16,19 @@ impl JWT {
     }
 
     pub fn decode(&self, token: String) -> Result<Phone, String> {
+        let claims = self.decode_claims(&token)?;
+        Ok(claims.phone)
+    }
+
+    pub fn decode_claims(&self, token: &str) -> Result<Claims, String> {
         let secret = self.0.as_bytes();
         let key = &DecodingKey::from_secret(secret);
         let validation = &Validation::new(jsonwebtoken::Algorithm::HS256);
-        let decode: Result<TokenData<Claims>, ErrorJwt> = decode(&token, key, validation);
+        let decode: Result<TokenData<Claims>, ErrorJwt> = decode(token, key, validation);
 
         match decode {
-            Ok(token) => Ok(token.claims.phone),
+            Ok(token) => Ok(token.claims),
             Err(_err) => Err("Invalid token".to_owned()),
         }
-
     }
 }
@@ -9,5 +9,8 @@ pub mod jwt;
 pub mod sms;
 pub mod market;
 pub mod estimator;
+pub mod outbox;
+pub mod simulation;
 pub mod types;
 pub mod upload;
+pub mod reports;
@@ -11,7 +11,9 @@ use google_maps::GoogleMapsClient;
 use nujade_backend::db_util::{get_pool, AppState, DBActor};
 
 use nujade_backend::estimator::Estimator;
-use nujade_backend::market::Market;
+use nujade_backend::outbox::OutboxWorker;
+use nujade_backend::market::{Market, config::MarketConfig};
+use nujade_backend::market::store::CacheStore;
 use nujade_backend::sms::ClientTwilio;
 use nujade_backend::graphql::handlers::{graphql, graphql_playground, subscriptions};
 use nujade_backend::graphql::create_schema;
@@ -41,14 +43,22 @@ async fn main() -> std::io::Result<()> {
     let google_secret = std::env::var("GOOGLE_SECRET").expect("GOOGLE_SECRET must be set");
     let google_maps_client = GoogleMapsClient::new(&google_secret);
 
-    let cfg = kv::Config::new("./kv");
-    let kv = kv::Store::new(cfg).unwrap();
+    let market_config = MarketConfig::from_env().expect("Invalid market config");
+    let store = <dyn CacheStore>::from_env(&market_config).expect("Could not open the cache store");
 
     let schema = create_schema();
 
-    // let is_mock = false;
+    let is_mock = false;
 
-    let _addr = Estimator::new(Arc::new(Market::new(db_addr.clone(), kv.clone(), google_maps_client.clone(), twilio.clone())));   
+    // The market is shared so state such as the geocoder circuit breaker and a mock market's cache live across requests
+    let market = if is_mock {
+        Market::mock(db_addr.clone()).expect("Could not start the mock market")
+    } else {
+        Market::new(db_addr.clone(), store, google_maps_client.clone(), twilio.clone(), market_config).expect("Could not start the market")
+    };
+
+    let _addr = Estimator::new(Arc::new(market.clone()));
+    let _outbox = OutboxWorker::new(Arc::new(market.clone()));
 
     HttpServer::new(move || {
         App::new()
@@ -62,6 +72,7 @@ async fn main() -> std::io::Result<()> {
                     .route(web::get().to(graphql)),
             )
             .configure(nujade_backend::upload::register_urls)
+            .configure(nujade_backend::reports::register_urls)
             .route("/playground", web::get().to(graphql_playground))
             .app_data(Data::new(AppState {
                 schema: schema.clone(),
@@ -69,8 +80,8 @@ async fn main() -> std::io::Result<()> {
                 twilio: twilio.clone(),
                 jwt_secret: jwt_secret.clone(),
                 google_maps_client: google_maps_client.clone(),
-                kv: kv.clone(),
-                is_mock: false,
+                market: market.clone(),
+                is_mock,
             }))
     })
     .bind((server_host, server_port))?
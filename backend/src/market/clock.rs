@@ -0,0 +1,70 @@
+use std::sync::{Arc, atomic::{AtomicI32, Ordering}};
+
+use chrono::Duration;
+
+use super::util::now;
+
+
+#[doc = "The source of the current time for the market, in unix seconds"]
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn box_clone(&self) -> Box<dyn Clock>;
+
+    fn now(&self) -> i32;
+}
+
+#[doc = "The wall clock"]
+#[derive(Debug, Clone, Default)]
+pub struct ClockSystem;
+
+impl ClockSystem {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Clock for ClockSystem {
+    fn box_clone(&self) -> Box<dyn Clock> {
+        Box::new(self.clone())
+    }
+
+    fn now(&self) -> i32 {
+        now()
+    }
+}
+
+#[doc = "A clock that only moves when it is told to, clones share the same time"]
+#[derive(Debug, Clone)]
+pub struct ClockManual {
+    now: Arc<AtomicI32>,
+}
+
+impl ClockManual {
+    pub fn new(start: i32) -> Self {
+        Self {
+            now: Arc::new(AtomicI32::new(start)),
+        }
+    }
+
+    #[doc = "Start the clock at the current wall clock time"]
+    pub fn starting_now() -> Self {
+        Self::new(now())
+    }
+
+    pub fn set(&self, time: i32) {
+        self.now.store(time, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.fetch_add(duration.num_seconds() as i32, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ClockManual {
+    fn box_clone(&self) -> Box<dyn Clock> {
+        Box::new(self.clone())
+    }
+
+    fn now(&self) -> i32 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
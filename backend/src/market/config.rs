@@ -0,0 +1,102 @@
+use chrono::Duration;
+
+use super::{types::MarketResult, error::ErrorMarket};
+
+#[doc = "Settings for how the market treats drivers and riders"]
+#[derive(Debug, Clone)]
+pub struct MarketConfig {
+    #[doc = "Riders are told their driver is almost here when the driver is this far from the pickup"]
+    pub approaching_within: Duration,
+    #[doc = "How close a driver has to be to their stop to be at it, in meters"]
+    pub arrival_radius_meters: f64,
+    #[doc = "How long a driver has to stay at their stop before the arrival is confirmed for them"]
+    pub arrival_dwell: Duration,
+    #[doc = "How long driver locations are kept for"]
+    pub trail_retention: Duration,
+    #[doc = "Where real time messages are sent through"]
+    pub messanger: MessangerBackend,
+    #[doc = "The redis server for the redis messanger and cache store"]
+    pub redis_url: String,
+    #[doc = "The url safe base64 VAPID private key web pushes are signed with, web push is off without it"]
+    pub web_push_vapid_key: Option<String>,
+    #[doc = "How push services can contact us about our web pushes, a mailto: or https: url"]
+    pub web_push_subject: String,
+}
+
+#[doc = "Which messanger the market sends real time messages through"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessangerBackend {
+    #[doc = "Redis pub/sub, works across many servers"]
+    Redis,
+    #[doc = "Channels in this process, for a single server or development without redis"]
+    Memory,
+}
+
+impl std::str::FromStr for MessangerBackend {
+    type Err = ErrorMarket;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "redis" => Ok(Self::Redis),
+            "memory" => Ok(Self::Memory),
+            _ => Err(ErrorMarket::BadValue(format!("MARKET_MESSANGER must be 'redis' or 'memory', got '{s}'"))),
+        }
+    }
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self {
+            approaching_within: Duration::seconds(120),
+            arrival_radius_meters: 50.0,
+            arrival_dwell: Duration::seconds(20),
+            trail_retention: Duration::days(30),
+            messanger: MessangerBackend::Redis,
+            redis_url: String::from("redis://127.0.0.1:6379"),
+            web_push_vapid_key: None,
+            web_push_subject: String::from("https://elytrarides.com"),
+        }
+    }
+}
+
+impl MarketConfig {
+    #[doc = "Read the config from the environment, anything not set uses the default"]
+    pub fn from_env() -> MarketResult<Self> {
+        let default = Self::default();
+        Ok(Self {
+            approaching_within: env_seconds("MARKET_APPROACHING_SECONDS")?.unwrap_or(default.approaching_within),
+            arrival_radius_meters: env_meters("MARKET_ARRIVAL_RADIUS_METERS")?.unwrap_or(default.arrival_radius_meters),
+            arrival_dwell: env_seconds("MARKET_ARRIVAL_DWELL_SECONDS")?.unwrap_or(default.arrival_dwell),
+            trail_retention: env_seconds("MARKET_TRAIL_RETENTION_SECONDS")?.unwrap_or(default.trail_retention),
+            messanger: match std::env::var("MARKET_MESSANGER") {
+                Ok(value) => value.parse()?,
+                Err(_) => default.messanger,
+            },
+            redis_url: std::env::var("REDIS_URL").unwrap_or(default.redis_url),
+            web_push_vapid_key: std::env::var("WEB_PUSH_VAPID_PRIVATE_KEY").ok().or(default.web_push_vapid_key),
+            web_push_subject: std::env::var("WEB_PUSH_SUBJECT").unwrap_or(default.web_push_subject),
+        })
+    }
+}
+
+fn env_seconds(name: &str) -> MarketResult<Option<Duration>> {
+    match std::env::var(name) {
+        Ok(value) => {
+            let seconds: i64 = value.parse()
+                .map_err(|_| ErrorMarket::BadValue(format!("{name} must be a number of seconds, got '{value}'")))?;
+            Ok(Some(Duration::seconds(seconds)))
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+fn env_meters(name: &str) -> MarketResult<Option<f64>> {
+    match std::env::var(name) {
+        Ok(value) => {
+            let meters: f64 = value.parse()
+                .map_err(|_| ErrorMarket::BadValue(format!("{name} must be a number of meters, got '{value}'")))?;
+            Ok(Some(meters))
+        },
+        Err(_) => Ok(None),
+    }
+}
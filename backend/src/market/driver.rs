@@ -1,40 +1,48 @@
 use actix::Addr;
-use kv::Store;
-use log::warn;
+use log::{warn, debug};
 use uuid::Uuid;
 
-use crate::{db_util::DBActor, graphql::{drivers::{Driver, messages::{EventDriverGet, EventDriverFind}, DriverWithVehicle}, geo::model::LatLng, reservations::{Reservation, messages::{ReservationAssignDriver, ReservationConfirmPickup, ReservationConfirmDropoff, ReservationGet, ReservationConfirmArrival}}, users::{messages::UserGet, User}}, types::phone::Phone, market::{error::ErrorMarket, estimate::driver::stop::model::DriverStopEstimation}};
+use crate::{db_util::DBActor, graphql::{drivers::{Driver, messages::{EventDriverGet, EventDriverFind}, DriverWithVehicle}, geo::model::LatLng, trails::{messages::{DriverPingsInsert, DriverPingsBetween, DriverPingsPrune}, model::{DBDriverPingInsertable, Trip, TrailPoint}}, reservations::{Reservation, messages::{ReservationAssignDriver, ReservationConfirmPickup, ReservationConfirmDropoff, ReservationGet, ReservationConfirmArrival}}}, types::phone::Phone, market::{error::ErrorMarket, estimate::driver::stop::model::DriverStopEstimation}};
 
-use super::{types::MarketResult, event::MarketEvent, messanger::Messanger, strategy::{model::IdEventDriver, driver::model::DriverStrategy}, estimate::driver::model::DriverStrategyEstimations, pusher::Pushers};
+use super::{types::MarketResult, event::MarketEvent, messanger::Messanger, strategy::{model::IdEventDriver, driver::model::DriverStrategy}, estimate::driver::model::DriverStrategyEstimations, notification::MarketNotification, clock::Clock, config::MarketConfig, event::cache::GeofenceEntry};
+
+#[doc = "Location samples can be this far ahead of the server clock, anything further is dropped"]
+const MAX_SAMPLE_AHEAD_SECONDS: i32 = 60;
+
+#[doc = "Only this many of the latest samples of a batch are kept, a driver that was offline for longer loses the oldest part of the trail"]
+const MAX_BATCH_SAMPLES: usize = 3600;
 
 pub struct MarketDriver {
     db: Addr<DBActor>,
-    kv: Store,
     event: MarketEvent,
     messanger: Box<dyn Messanger>,
-    push: Pushers,
+    notification: MarketNotification,
+    clock: Box<dyn Clock>,
+    config: MarketConfig,
 }
 
 impl Clone for MarketDriver {
     fn clone(&self) -> Self {
         Self {
             db: self.db.clone(),
-            kv: self.kv.clone(),
             event: self.event.clone(),
             messanger: self.messanger.box_clone(),
-            push: self.push.clone()
+            notification: self.notification.clone(),
+            clock: self.clock.box_clone(),
+            config: self.config.clone(),
         }
     }
 }
 
 impl MarketDriver {
-    pub fn new(db: Addr<DBActor>, kv: Store, messanger: Box<dyn Messanger>, event: MarketEvent, push: Pushers) -> Self {
+    pub fn new(db: Addr<DBActor>, messanger: Box<dyn Messanger>, event: MarketEvent, notification: MarketNotification, clock: Box<dyn Clock>, config: MarketConfig) -> Self {
         Self {
             db,
-            kv,
             messanger,
             event,
-            push,
+            notification,
+            clock,
+            config,
         }
     }
 
@@ -62,12 +70,142 @@ impl MarketDriver {
 
     #[doc = "Tells the server where the driver is and gets the strategy"]
     pub async fn ping(&self, id_event: &Uuid, id_driver: &IdEventDriver, location: &LatLng) -> MarketResult<DriverStrategyEstimations> {
+        let sample = TrailPoint { location: *location, at: self.clock.now() };
+        self.ping_batch(id_event, id_driver, vec![sample]).await
+    }
+
+    #[doc = "Tells the server where the driver has been and gets the strategy, only the latest sample updates the strategy and samples older than the last one are dropped"]
+    pub async fn ping_batch(&self, id_event: &Uuid, id_driver: &IdEventDriver, mut samples: Vec<TrailPoint>) -> MarketResult<DriverStrategyEstimations> {
+        let now = self.clock.now();
+        let last_at = self.event.get_driver_location_at(id_driver).await?;
+        let count = samples.len();
+        samples.retain(|sample| sample.at <= now + MAX_SAMPLE_AHEAD_SECONDS && last_at.map_or(true, |last_at| sample.at >= last_at));
+        samples.sort_by_key(|sample| sample.at);
+        if samples.len() > MAX_BATCH_SAMPLES {
+            samples.drain(..samples.len() - MAX_BATCH_SAMPLES);
+        }
+        if samples.len() != count {
+            debug!("Dropped {} out of order location samples from driver {}", count - samples.len(), id_driver);
+        }
+
         if !self.event.is_driver_online(&id_driver).await? {
             let driver = self.get_with_vehicle(&id_driver).await?;
             self.event.add_driver(&id_event, &driver).await?;
         }
-        self.event.update_driver_location(id_event, id_driver, location).await?;
-        self.get_driver(id_event, id_driver).await
+        let Some(latest) = samples.last().cloned() else { return self.get_driver(id_event, id_driver).await };
+        self.event.update_driver_location(id_event, id_driver, &latest.location).await?;
+        self.event.set_driver_location_at(id_driver, latest.at).await?;
+        if let Err(err) = self.record_pings(id_event, id_driver, &samples).await {
+            warn!("Could not save the driver location, got error: {:?}", err)
+        }
+        let driver = self.get_driver(id_event, id_driver).await?;
+        if let Err(err) = self.notify_approaching(&driver).await {
+            warn!("Could not notify riders that their driver is almost here, got error: {:?}", err)
+        }
+        if let Err(err) = self.detect_arrival(&driver, &latest.location).await {
+            warn!("Could not detect the arrival of the driver, got error: {:?}", err)
+        }
+        Ok(driver)
+    }
+
+    #[doc = "Add locations to the trail of the driver"]
+    async fn record_pings(&self, id_event: &Uuid, id_driver: &IdEventDriver, samples: &[TrailPoint]) -> MarketResult<()> {
+        let pings = samples.iter()
+            .map(|sample| DBDriverPingInsertable {
+                id_event: *id_event,
+                id_driver: *id_driver,
+                lat: sample.location.lat,
+                lng: sample.location.lng,
+                at: sample.at,
+            })
+            .collect();
+        self.db.send(DriverPingsInsert { pings }).await??;
+        Ok(())
+    }
+
+    #[doc = "Get where a driver was between two times"]
+    pub async fn get_trail(&self, id_driver: &IdEventDriver, from: i32, to: i32) -> MarketResult<Vec<TrailPoint>> {
+        let pings = self.db.send(DriverPingsBetween { id_driver: *id_driver, from, to }).await??;
+        Ok(pings.into_iter().map(TrailPoint::from).collect())
+    }
+
+    #[doc = "Get the path the driver took for a reservation, from accepting it until the dropoff"]
+    pub async fn get_trip(&self, id_reservation: &Uuid) -> MarketResult<Trip> {
+        let reservation: Reservation = self.db.send(ReservationGet { id: *id_reservation }).await??.into();
+        let (Some(id_driver), Some(started_at)) = (reservation.id_driver, reservation.driver_accepted_at) else {
+            return Err(ErrorMarket::NotAccepted)
+        };
+        let ended_at = if reservation.is_complete { reservation.complete_at } else { None };
+        let points = self.get_trail(&id_driver, started_at, ended_at.unwrap_or(self.clock.now())).await?;
+        Ok(Trip {
+            id_reservation: *id_reservation,
+            id_driver,
+            started_at,
+            ended_at,
+            points,
+        })
+    }
+
+    #[doc = "Remove driver locations older than the retention, returns how many were removed"]
+    pub async fn prune_trails(&self) -> MarketResult<usize> {
+        let before = self.clock.now() - self.config.trail_retention.num_seconds() as i32;
+        let removed = self.db.send(DriverPingsPrune { before }).await??;
+        Ok(removed)
+    }
+
+    #[doc = "Confirm the arrival for the driver when they have stayed at their pickup long enough, if the event allows it"]
+    async fn detect_arrival(&self, driver: &DriverStrategyEstimations, location: &LatLng) -> MarketResult<()> {
+        if !self.event.is_auto_arrival_cached(&driver.id_event).await? { return Ok(()) }
+
+        let stop = match &driver.dest {
+            Some(dest @ DriverStopEstimation::Reservation(stop)) if !stop.is_dropoff => Some((dest.strip_estimate().key(), stop.location.coords)),
+            Some(dest @ DriverStopEstimation::Event(_)) if !driver.queue.get_pickup_reservations_from_event().is_empty() => {
+                let coords = self.event.get_property_location_cached(&driver.id_event).await?;
+                Some((dest.strip_estimate().key(), coords))
+            },
+            _ => None,
+        };
+        let Some((key, coords)) = stop.filter(|(_, coords)| location.distance_meters(coords) <= self.config.arrival_radius_meters) else {
+            self.event.delete_geofence(&driver.id).await?;
+            return Ok(())
+        };
+
+        let now = self.clock.now();
+        let entry = match self.event.get_geofence(&driver.id).await? {
+            Some(entry) if entry.stop == key => entry,
+            _ => {
+                let entry = GeofenceEntry { stop: key, entered_at: now, is_confirmed: false };
+                self.event.set_geofence(&driver.id, &entry).await?;
+                entry
+            },
+        };
+        if entry.is_confirmed || now - entry.entered_at < self.config.arrival_dwell.num_seconds() as i32 { return Ok(()) }
+
+        self.confirm_arrival(&driver.id_event, &driver.id, true).await?;
+        self.event.set_geofence(&driver.id, &GeofenceEntry { is_confirmed: true, ..entry }).await?;
+        Ok(())
+    }
+
+    #[doc = "Tell the riders at the next pickup that their driver is almost here, once per reservation"]
+    async fn notify_approaching(&self, driver: &DriverStrategyEstimations) -> MarketResult<()> {
+        let (eta, ids) = match &driver.dest {
+            Some(DriverStopEstimation::Reservation(stop)) if !stop.is_dropoff => (stop.pickup, driver.get_pickup_reservations()?),
+            Some(DriverStopEstimation::Event(stop)) => (stop.arrival, driver.queue.get_pickup_reservations_from_event()),
+            _ => return Ok(()),
+        };
+        if eta > self.config.approaching_within { return Ok(()) }
+
+        for id in ids {
+            if self.event.is_driver_approaching(&driver.id_event, &id).await? { continue }
+            let reservation: Reservation = self.db.send(ReservationGet { id }).await??.into();
+            // Only remembered once it was added to the outbox, so a failure is tried again on the next ping
+            if let Err(e) = self.notification.send_driver_approaching(&reservation, &driver.id, eta).await {
+                warn!("Could not tell rider of reservation {id} that driver {} is approaching, {e:?}", driver.id);
+                continue
+            }
+            self.event.mark_driver_approaching(&driver.id_event, &id).await?;
+        }
+        Ok(())
     }
 
     #[doc = "Accepts a reservation"]
@@ -75,63 +213,44 @@ impl MarketDriver {
         let reservation = self.db.send(ReservationGet { id: id_reservation.to_owned() }).await??;
         if reservation.id_driver.is_some() { return Err(ErrorMarket::HasDriver); }
 
-        let reservation: Reservation = self.db.send(ReservationAssignDriver { id: id_reservation.to_owned(), id_driver: id_driver.to_owned() }).await??.into();
+        let reservation: Reservation = self.db.send(ReservationAssignDriver { id: id_reservation.to_owned(), id_driver: id_driver.to_owned(), at: self.clock.now() }).await??.into();
         self.messanger.send_reservation_update(reservation.clone()).await?;
-        let pusher = self.push.get(&reservation);
-        match self.db.send(UserGet { phone: reservation.reserver.clone() }).await {
-            Ok(Ok(user)) => {
-                if let Err(err) = pusher.send_driver_accepted(&reservation, &user.into()).await {
-                    warn!("Could not send text, got error: {}", err)
-                }
-            },
-            _ => warn!("Could not find user")
-        }
 
         let id_event = reservation.id_event;
+        let reservation_accepted = reservation.clone();
         
         let driver_strategy = self.event.update_driver_strategy(&id_event, id_driver, Box::new(move |driver: DriverStrategy| {
             if let Some(_) = driver.dest { return Err(ErrorMarket::HasDest) }
-            let driver_new = driver.add_reservation(reservation);
+            let driver_new = driver.add_reservation(reservation.clone());
             Ok(driver_new)
         })).await?;
+        // The reservation is already accepted, a rider that isn't told doesn't undo that
+        if let Err(e) = self.notification.send_driver_accepted(&reservation_accepted, id_driver).await {
+            warn!("Could not tell rider of reservation {} that driver {id_driver} accepted, {e:?}", reservation_accepted.id);
+        }
+        self.event.publish_pool(&id_event).await;
+        self.event.publish_summary(&id_event).await;
 
         Ok(driver_strategy)
     }
 
     #[doc = "Confirm the arrival of the driver to their destination"]
     pub async fn arrive(&self, id_event: &Uuid, id_driver: &IdEventDriver) -> MarketResult<DriverStrategyEstimations> {
+        self.confirm_arrival(id_event, id_driver, false).await
+    }
+
+    #[doc = "Confirm the arrival of the driver to their destination, `is_auto` is if it was from their location"]
+    async fn confirm_arrival(&self, id_event: &Uuid, id_driver: &IdEventDriver, is_auto: bool) -> MarketResult<DriverStrategyEstimations> {
         let driver = self.get_driver(id_event, id_driver).await?;
         match &driver.dest {
             Some(DriverStopEstimation::Reservation(stop)) => {
-                let reservation = self.db.send(ReservationConfirmArrival { id: stop.id_reservation }).await??.into();
-                let pusher = self.push.get(&reservation);
-                match self.db.send(UserGet { phone: reservation.reserver.clone() }).await {
-                    Ok(Ok(user)) => {
-                        if let Err(err) = pusher.send_driver_arrival(&reservation, &user.into()).await {
-                            warn!("Could not send text, got error: {}", err)
-                        }
-                    },
-                    _ => warn!("Could not find user")
-                }
-
-                self.messanger.send_reservation_update(reservation).await?;
+                self.mark_arrived(&stop.id_reservation, id_driver, stop.is_dropoff, is_auto).await?;
                 Ok(driver)
             },
             Some(DriverStopEstimation::Event(_)) => {
-                let driver = self.get_driver(&id_event, &id_driver).await?;
                 let ids = driver.queue.get_pickup_reservations_from_event();
                 for id in ids {
-                    let reservation = self.db.send(ReservationConfirmArrival { id }).await??.into();
-                    let pusher = self.push.get(&reservation);
-                    match self.db.send(UserGet { phone: reservation.reserver.clone() }).await {
-                        Ok(Ok(user)) => {
-                            if let Err(err) = pusher.send_driver_arrival(&reservation, &user.into()).await {
-                                warn!("Could not send text, got error: {}", err)
-                            }
-                        },
-                        _ => warn!("Could not find user")
-                    }
-                    self.messanger.send_reservation_update(reservation).await?;
+                    self.mark_arrived(&id, id_driver, false, is_auto).await?;
                 }
                 Ok(driver)
             },
@@ -139,6 +258,19 @@ impl MarketDriver {
         }
     }
 
+    #[doc = "Mark that the driver arrived for a reservation, the riders a driver already arrived for are only told again when the driver says so"]
+    async fn mark_arrived(&self, id_reservation: &Uuid, id_driver: &IdEventDriver, is_dropoff: bool, is_auto: bool) -> MarketResult<()> {
+        if is_auto && !is_dropoff {
+            let reservation: Reservation = self.db.send(ReservationGet { id: *id_reservation }).await??.into();
+            if reservation.driver_arrived_at.is_some() { return Ok(()) }
+        }
+        let reservation: Reservation = self.db.send(ReservationConfirmArrival { id: *id_reservation, at: self.clock.now(), is_auto }).await??.into();
+        self.messanger.send_reservation_update(reservation.clone()).await?;
+        if let Err(e) = self.notification.send_driver_arrival(&reservation, id_driver, is_dropoff).await {
+            warn!("Could not tell rider of reservation {id_reservation} that driver {id_driver} arrived, {e:?}");
+        }
+        Ok(())
+    }
 
     #[doc = "Get a driver for an event"]
     async fn get_driver(&self, id_event: &Uuid, id_driver: &IdEventDriver) -> MarketResult<DriverStrategyEstimations> {
@@ -151,7 +283,7 @@ impl MarketDriver {
         let id_reservations = driver.get_pickup_reservations()?;
         let driver_strategy = self.event.update_driver_strategy(&id_event, id_driver, Box::new(move |driver: DriverStrategy| { driver.pickup() })).await?;
         for id in id_reservations {
-            let reservation = self.db.send(ReservationConfirmPickup { id }).await??.into();
+            let reservation = self.db.send(ReservationConfirmPickup { id, at: self.clock.now() }).await??.into();
             self.messanger.send_reservation_update(reservation).await?;
         }
         Ok(driver_strategy)
@@ -163,7 +295,7 @@ impl MarketDriver {
         let id_reservations = driver.get_dropoff_reservations();
         let driver_strategy = self.event.update_driver_strategy(&id_event, id_driver, Box::new(move |driver: DriverStrategy| { driver.dropoff() })).await?;
         for id in id_reservations {
-            let reservation = self.db.send(ReservationConfirmDropoff { id }).await??.into();
+            let reservation = self.db.send(ReservationConfirmDropoff { id, at: self.clock.now() }).await??.into();
             self.messanger.send_reservation_update(reservation).await?;
         }
         Ok(driver_strategy)
@@ -46,6 +46,10 @@ pub enum ErrorMarket {
     ReservationIsPickedUp,
     #[error("Twillio request failed")]
     TwillioError,
+    #[error("Web push failed")]
+    WebPushError,
+    #[error("Every browser subscription of the rider has expired")]
+    WebPushGone,
     #[error("Google maps error")]
     GoogleMapsError,
     #[error("Could not estimate for the route")]
@@ -54,6 +58,10 @@ pub enum ErrorMarket {
     NoRouteLegs,
     #[error("No vehicle for this driver")]
     NoDriverVehicle,
+    #[error("The reservation has not been accepted by a driver")]
+    NotAccepted,
+    #[error("The strategy kept changing while it was being updated")]
+    StrategyConflict,
     #[error("Bad Value")]
     BadValue(String)
 }
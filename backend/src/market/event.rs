@@ -1,15 +1,21 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::{HashMap, HashSet}, str::FromStr};
 
 use actix::Addr;
 use chrono::Duration;
-use kv::Store;
+use log::{warn, debug};
 use uuid::Uuid;
 
-use crate::{db_util::DBActor, graphql::{reservations::{messages::{ReservationsInPool, ReservationRemoveDriver}, FormReservation, Reservation, DBReservation, stops::model::{ReservationStops, FormReservationStop, FormLatLng}, FormReservationGeocoded}, geo::model::LatLng, locations::OrgLocation, events::{messages::{EventLocationGet, GetActiveEvents}, Event}, drivers::{Driver, messages::EventDriversList, DriverWithVehicle}, colleges::model::College}, market::util::now};
+use crate::{db_util::DBActor, graphql::{reservations::{messages::{ReservationsInPool, ReservationRemoveDriver}, FormReservation, Reservation, DBReservation, stops::model::{ReservationStops, FormReservationStop, FormLatLng}, FormReservationGeocoded}, geo::model::LatLng, locations::OrgLocation, events::{messages::{EventLocationGet, GetActiveEvents, EventGet}, Event}, drivers::{Driver, messages::EventDriversList, DriverWithVehicle}, colleges::model::College}};
 
-use self::cache::MarketEventCache;
+use self::cache::{MarketEventCache, CacheGcReport, CacheStatsSnapshot, EventCacheSnapshot, GeofenceEntry};
 
-use super::{types::{MarketResult, ReservationEstimate, TimeEstimate}, error::ErrorMarket, vehicle::MarketVehicle, geocoder::Geocoder, messanger::Messanger, util::add_reservation_arrivals_to_queue, strategy::{driver::{stop::model::DriverStop, model::DriverStrategy}, model::{Strategy, IdEventDriver}}, estimate::{model::StrategyEstimations, driver::{model::DriverStrategyEstimations, stop::model::DriverStopEstimation}}};
+use super::{types::{MarketResult, ReservationEstimate, TimeEstimate}, messages::{MessageMarket, MessageEventSummary}, error::ErrorMarket, vehicle::MarketVehicle, geocoder::Geocoder, messanger::Messanger, clock::Clock, store::CacheStore, util::add_reservation_arrivals_to_queue, strategy::{driver::{stop::model::DriverStop, model::DriverStrategy}, model::{Strategy, IdEventDriver}}, estimate::{model::StrategyEstimations, driver::{model::DriverStrategyEstimations, stop::model::DriverStopEstimation}}};
+
+#[doc = "How many times a strategy update is tried when other servers keep changing the strategy"]
+const STRATEGY_UPDATE_ATTEMPTS: usize = 5;
+
+#[doc = "Event summaries are sent at most this often, in seconds"]
+const SUMMARY_INTERVAL_SECONDS: i32 = 5;
 
 pub mod cache;
 
@@ -19,6 +25,7 @@ pub struct MarketEvent {
     messanger: Box<dyn Messanger>,
     cache: MarketEventCache,
     vehicle: MarketVehicle,
+    clock: Box<dyn Clock>,
 }
 
 impl Clone for MarketEvent {
@@ -29,24 +36,149 @@ impl Clone for MarketEvent {
             messanger: self.messanger.box_clone(),
             cache: self.cache.clone(),
             vehicle: self.vehicle.clone(),
+            clock: self.clock.box_clone(),
         }
     }
 }
 
 impl MarketEvent {
-    pub fn new(db: Addr<DBActor>, geocoder: Box<dyn Geocoder>, messanger: Box<dyn Messanger>, kv: Store, vehicle: MarketVehicle) -> Self {
+    pub fn new(db: Addr<DBActor>, geocoder: Box<dyn Geocoder>, messanger: Box<dyn Messanger>, store: Box<dyn CacheStore>, vehicle: MarketVehicle, clock: Box<dyn Clock>) -> Self {
         Self {
             db,
-            cache: MarketEventCache::new(kv),
+            cache: MarketEventCache::new(store, clock.box_clone()),
             geocoder,
             messanger,
             vehicle,
+            clock,
         }
     }
 
     #[doc = "Clear the cache for events"]
-    pub fn clear_cache(&self) -> MarketResult<()> {
-        self.cache.clear()?;
+    pub async fn clear_cache(&self) -> MarketResult<()> {
+        self.cache.clear().await?;
+        Ok(())
+    }
+
+    #[doc = "Get how often estimates have been found in cache"]
+    pub fn cache_stats(&self) -> CacheStatsSnapshot {
+        self.cache.stats()
+    }
+
+    #[doc = "Whether a rider was already told their driver is almost here"]
+    pub async fn is_driver_approaching(&self, id_event: &Uuid, id_reservation: &Uuid) -> MarketResult<bool> {
+        self.cache.is_approaching(id_event, id_reservation).await
+    }
+
+    #[doc = "Remember that a rider was told their driver is almost here, this is false if they were already told"]
+    pub async fn mark_driver_approaching(&self, id_event: &Uuid, id_reservation: &Uuid) -> MarketResult<bool> {
+        self.cache.mark_approaching(id_event, id_reservation).await
+    }
+
+    #[doc = "Get the last known location of a driver"]
+    pub async fn get_driver_location(&self, id_driver: &IdEventDriver) -> MarketResult<Option<LatLng>> {
+        self.cache.get_driver_location(id_driver).await
+    }
+
+    #[doc = "Get when the driver location was taken"]
+    pub async fn get_driver_location_at(&self, id_driver: &IdEventDriver) -> MarketResult<Option<i32>> {
+        self.cache.get_driver_location_at(id_driver).await
+    }
+
+    #[doc = "Set when the driver location was taken"]
+    pub async fn set_driver_location_at(&self, id_driver: &IdEventDriver, at: i32) -> MarketResult<()> {
+        self.cache.set_driver_location_at(id_driver, at).await
+    }
+
+    #[doc = "Get when a driver got to their stop"]
+    pub async fn get_geofence(&self, id_driver: &IdEventDriver) -> MarketResult<Option<GeofenceEntry>> {
+        self.cache.get_geofence(id_driver).await
+    }
+
+    #[doc = "Set when a driver got to their stop"]
+    pub async fn set_geofence(&self, id_driver: &IdEventDriver, entry: &GeofenceEntry) -> MarketResult<()> {
+        self.cache.set_geofence(id_driver, entry).await
+    }
+
+    #[doc = "Forget that a driver is at their stop"]
+    pub async fn delete_geofence(&self, id_driver: &IdEventDriver) -> MarketResult<()> {
+        self.cache.delete_geofence(id_driver).await
+    }
+
+    #[doc = "Get everything in cache for an event"]
+    pub async fn get_cache_snapshot(&self, id_event: &Uuid) -> MarketResult<EventCacheSnapshot> {
+        self.cache.snapshot(id_event).await
+    }
+
+    #[doc = "Remove everything in cache for an event, drivers will be offline until they ping again"]
+    pub async fn evict_cache(&self, id_event: &Uuid) -> MarketResult<()> {
+        self.cache.evict_event(id_event).await
+    }
+
+    #[doc = "Drop the estimates for an event and calculate them again from the strategy and driver locations"]
+    pub async fn rebuild_cache(&self, id_event: &Uuid) -> MarketResult<StrategyEstimations> {
+        self.cache.evict_estimates(id_event).await?;
+        self.refresh_estimates(id_event).await
+    }
+
+    #[doc = "Take every reservation a driver has not picked up yet off of them, those go back to the pool. Riders in the car stay with the driver"]
+    pub async fn reset_driver_strategy(&self, id_event: &Uuid, id_driver: &IdEventDriver) -> MarketResult<StrategyEstimations> {
+        let strategy = self.get_strategy_cached(id_event).await?;
+        let driver = strategy.drivers.get(id_driver).ok_or(ErrorMarket::DriverNotFound)?;
+
+        let waiting: HashSet<Uuid> = driver.get_reservations().into_iter()
+            .filter(|id| !driver.picked_up.contains_key(id))
+            .collect();
+        for id in waiting {
+            let reservation: Reservation = self.db.send(ReservationRemoveDriver { id }).await??.into();
+            self.messanger.send_reservation_update(reservation.clone()).await?;
+            self.messanger.send_driver_offer(reservation).await?;
+        }
+
+        let id_driver = *id_driver;
+        let id_event_cloned = *id_event;
+        let strategy = self.update_strategy(id_event, Box::new(move |mut strategy: Strategy| {
+            let driver = strategy.drivers.get(&id_driver).ok_or(ErrorMarket::DriverNotFound)?;
+            strategy.drivers.insert(id_driver, driver.keep_picked_up());
+            Ok(strategy)
+        })).await?;
+        self.messanger.send_driver_strategy(strategy.driver(&id_driver)?).await?;
+        self.publish_pool(&id_event_cloned).await;
+        self.publish_summary(&id_event_cloned).await;
+        Ok(strategy)
+    }
+
+    #[doc = "Remove ended or removed events from the cache, and expired estimates or estimates for finished reservations from the rest"]
+    pub async fn gc_cache(&self) -> MarketResult<CacheGcReport> {
+        let now = self.clock.now();
+        let mut report = CacheGcReport::default();
+
+        for id_event in self.cache.list_events().await? {
+            // One event failing should not keep the others from being collected
+            if let Err(e) = self.gc_event(&id_event, now, &mut report).await {
+                warn!("Could not collect event {id_event} from the cache, {e:?}");
+            }
+        }
+        Ok(report)
+    }
+
+    async fn gc_event(&self, id_event: &Uuid, now: i32, report: &mut CacheGcReport) -> MarketResult<()> {
+        // Upcoming events and ones still being set up keep their drivers and strategy
+        let is_over = match self.db.send(EventGet { id: *id_event }).await? {
+            Ok(event) => event.obsolete_at.is_some() || event.time_end < now,
+            Err(diesel::result::Error::NotFound) => true,
+            Err(err) => return Err(err.into()),
+        };
+        if is_over {
+            self.cache.evict_event(id_event).await?;
+            report.events_evicted += 1;
+            return Ok(())
+        }
+
+        let mut reservations: HashSet<Uuid> = self.get_pool(id_event).await?.iter().map(|res| res.id).collect();
+        if let Some(strategy) = self.cache.get_strategy(id_event).await? {
+            reservations.extend(strategy.drivers.values().flat_map(|driver| driver.get_reservations()));
+        }
+        report.estimates_removed += self.cache.prune_event(id_event, &reservations).await?;
         Ok(())
     }
 
@@ -87,13 +219,13 @@ impl MarketEvent {
 
     #[doc = "Return whether or not the driver is online, this currently works by seeing if they have pinged for an event"]
     pub async fn is_driver_online(&self, id_driver: &IdEventDriver) -> MarketResult<bool> {
-        let is_online = self.cache.get_driver_location(&id_driver)?.is_some();
+        let is_online = self.cache.get_driver_location(&id_driver).await?.is_some();
         Ok(is_online)
     }
 
     #[doc = "Get the current active events"]
     pub async fn list_active(&self) -> MarketResult<Vec<Event>> {
-        let events = self.db.send(GetActiveEvents).await??.into_iter().map(Event::from).collect();
+        let events = self.db.send(GetActiveEvents { now: self.clock.now() }).await??.into_iter().map(Event::from).collect();
         Ok(events)
     }
 
@@ -189,7 +321,7 @@ impl MarketEvent {
 
     #[doc = "Get stop estimation"]
     async fn get_stop_estimation(&self, id_event: &Uuid, from: &DriverStop, to: &DriverStop) -> MarketResult<Duration> {
-        match self.cache.get_estimate_between_stops(id_event, from, to)? {
+        match self.cache.get_estimate_between_stops(id_event, from, to).await? {
             Some(est) => Ok(est),
             None => self.refresh_and_get_stop_estimate(id_event, from, to).await,
         }
@@ -198,7 +330,7 @@ impl MarketEvent {
     #[doc = "Gets the driver estimation to their destination, if none exists, will calculate and store it in cache."]
     async fn get_estimate_driver_cached(&self, id_event: &Uuid, driver_strategy: &DriverStrategy) -> MarketResult<Option<Duration>> {
         if let Some(_) = &driver_strategy.dest {
-            match self.cache.get_estimate_driver(id_event, &driver_strategy)? {
+            match self.cache.get_estimate_driver(id_event, &driver_strategy).await? {
                 Some(est) => Ok(Some(est)),
                 None => Ok(Some(self.refresh_and_get_driver_estimate(id_event, driver_strategy).await?)),
             }
@@ -208,14 +340,14 @@ impl MarketEvent {
     }
 
     async fn refresh_and_get_driver_estimate(&self, id_event: &Uuid, driver_strategy: &DriverStrategy) -> MarketResult<Duration> {
-        println!("refresh driver");
-        let driver_location = self.cache.get_driver_location(&driver_strategy.id)?;
+        debug!("Refreshing the estimate of driver {} for event {id_event}", driver_strategy.id);
+        let driver_location = self.cache.get_driver_location(&driver_strategy.id).await?;
         match (&driver_strategy.dest, driver_location) {
             (Some(dest), Some(location)) => {
                 let dest_location = self.get_stop_location(id_event, dest.to_owned()).await?;
-                let est = self.geocoder.estimate(location, dest_location).await?;
-                self.cache.update_estimate_driver(id_event, &driver_strategy, est.clone())?;
-                Ok(est)
+                let est = self.geocoder.estimate_route(location, dest_location).await?;
+                self.cache.update_estimate_driver(id_event, &driver_strategy, est).await?;
+                Ok(est.duration)
             },
             (_, None) => Err(ErrorMarket::NoDriverLocation),
             (None, _) => Err(ErrorMarket::NoDest)
@@ -223,12 +355,12 @@ impl MarketEvent {
     }
 
     async fn refresh_and_get_stop_estimate(&self, id_event: &Uuid, stop_from: &DriverStop, stop_to: &DriverStop) -> MarketResult<Duration> {
-        println!("refresh stop");
+        debug!("Refreshing an estimate between stops for event {id_event}");
         let from = self.get_stop_location(id_event, stop_from.clone()).await?;
         let to = self.get_stop_location(id_event, stop_to.clone()).await?;
-        let est = self.geocoder.estimate(from, to).await?;
-        self.cache.update_estimate_stop(id_event, stop_from, stop_to, est)?;
-        Ok(est)
+        let est = self.geocoder.estimate_route(from, to).await?;
+        self.cache.update_estimate_stop(id_event, stop_from, stop_to, est).await?;
+        Ok(est.duration)
     }
 
     #[doc = "Get the location of a driver stop, will return the event property location if it is an event, otherwise, will return the location of the reservation"]
@@ -247,13 +379,15 @@ impl MarketEvent {
 
     #[doc = "Get a cached strategy, if one is not found, create one and set it in cache, then return it"]
     async fn get_strategy_cached(&self, id_event: &Uuid) -> MarketResult<Strategy> {
-        if let Some(strategy) = self.cache.get_strategy(id_event)? {
-            Ok(strategy)
-        } else {
-            let strategy = self.create_new_strategy(id_event).await?;
-            self.cache.set_strategy(id_event, strategy.clone())?;
-            Ok(strategy)
+        if let Some(strategy) = self.cache.get_strategy(id_event).await? {
+            return Ok(strategy)
+        }
+        let strategy = self.create_new_strategy(id_event).await?;
+        if self.cache.replace_strategy(id_event, None, &strategy).await? {
+            return Ok(strategy)
         }
+        // Someone else made it first, theirs may already have been updated
+        self.cache.get_strategy(id_event).await?.ok_or(ErrorMarket::StrategyConflict)
     }
 
     #[doc = "Create a new strategy from an event id"]
@@ -328,7 +462,7 @@ impl MarketEvent {
         let res_temp: Reservation = DBReservation {
             id: id_temp,
             id_event: id_event.to_owned(),
-            made_at: now(),
+            made_at: self.clock.now(),
             reserver: String::from("+18002000000"),
             passenger_count: form.passenger_count,
             is_cancelled: false,
@@ -340,6 +474,8 @@ impl MarketEvent {
             is_dropoff: form.is_dropoff,
             is_driver_arrived: false,
             driver_arrived_at: None,
+            driver_arrived_auto: false,
+            driver_accepted_at: None,
             est_pickup: 0,
             est_dropoff: 0,
             rating: None,
@@ -367,7 +503,7 @@ impl MarketEvent {
         let res_temp: Reservation = DBReservation {
             id: *id_reservation,
             id_event: id_event.to_owned(),
-            made_at: now(),
+            made_at: self.clock.now(),
             reserver: String::from("+18002000000"),
             passenger_count: form_geocoded.passenger_count,
             is_cancelled: false,
@@ -379,6 +515,8 @@ impl MarketEvent {
             is_dropoff: form_geocoded.is_dropoff,
             is_driver_arrived: false,
             driver_arrived_at: None,
+            driver_arrived_auto: false,
+            driver_accepted_at: None,
             est_pickup: 0,
             est_dropoff: 0,
             rating: None,
@@ -420,7 +558,7 @@ impl MarketEvent {
         let res_temp: Reservation = DBReservation {
             id,
             id_event: id_event.to_owned(),
-            made_at: now(),
+            made_at: self.clock.now(),
             reserver: String::from("+18002000000"),
             passenger_count: form.passenger_count,
             is_cancelled: false,
@@ -432,6 +570,8 @@ impl MarketEvent {
             is_dropoff: form.is_dropoff,
             is_driver_arrived: false,
             driver_arrived_at: None,
+            driver_arrived_auto: false,
+            driver_accepted_at: None,
             est_pickup: 0,
             est_dropoff: 0,
             rating: None,
@@ -504,13 +644,13 @@ impl MarketEvent {
     }
 
     #[doc = "Get a cached event location's cordinates, if no cache, it will get the location from the db and set it in cache."]
-    async fn get_property_location_cached(&self, id_event: &Uuid) -> MarketResult<LatLng> {
-        if let Some(location) = self.cache.get_property_location(id_event)? {
+    pub async fn get_property_location_cached(&self, id_event: &Uuid) -> MarketResult<LatLng> {
+        if let Some(location) = self.cache.get_property_location(id_event).await? {
             Ok(location)
         } else {
             if let Some(property) = &self.get_property(id_event).await? {
                 let location = property.latlng();
-                self.cache.set_property_location(id_event, location.clone())?;
+                self.cache.set_property_location(id_event, location.clone()).await?;
                 Ok(location)
             } else {
                 Err(ErrorMarket::NoEventProperty)
@@ -518,8 +658,23 @@ impl MarketEvent {
         }
     }
 
+    #[doc = "Get if an event confirms arrivals from where its drivers are, from cache if it is there"]
+    pub async fn is_auto_arrival_cached(&self, id_event: &Uuid) -> MarketResult<bool> {
+        if let Some(is_auto) = self.cache.get_auto_arrival(id_event).await? {
+            return Ok(is_auto)
+        }
+        let event: Event = self.db.send(EventGet { id: *id_event }).await??.into();
+        self.cache.set_auto_arrival(id_event, event.auto_arrival).await?;
+        Ok(event.auto_arrival)
+    }
+
+    #[doc = "Forget the event's location and settings kept in cache, they are read again the next time they are needed"]
+    pub async fn evict_settings(&self, id_event: &Uuid) -> MarketResult<()> {
+        self.cache.evict_settings(id_event).await
+    }
+
     #[doc = "Update a driver strategy for an event"]
-    pub async fn update_driver_strategy(&self, id_event: &Uuid, id_driver: &IdEventDriver, update_fn: Box<dyn FnOnce(DriverStrategy) -> MarketResult<DriverStrategy> + Send>) -> MarketResult<DriverStrategyEstimations> {
+    pub async fn update_driver_strategy(&self, id_event: &Uuid, id_driver: &IdEventDriver, update_fn: Box<dyn Fn(DriverStrategy) -> MarketResult<DriverStrategy> + Send>) -> MarketResult<DriverStrategyEstimations> {
         let driver_id_cloned = id_driver.clone();
         let strategy = self.update_strategy(id_event, Box::new(move |mut strategy| {
             let driver = strategy.drivers.get(&driver_id_cloned).ok_or(ErrorMarket::DriverNotFound)?;
@@ -528,14 +683,48 @@ impl MarketEvent {
             Ok(strategy)
         })).await?;
         let driver = strategy.driver(id_driver)?;
+        self.messanger.send_driver_strategy(driver.clone()).await?;
         Ok(driver)
     }
 
-    async fn update_strategy(&self, id_event: &Uuid, update_fn: Box<dyn FnOnce(Strategy) -> MarketResult<Strategy> + Send>) -> MarketResult<StrategyEstimations> {
-        let strategy = self.get_strategy_cached(id_event).await?;
-        let new_strategy = update_fn(strategy)?;
-        self.cache.set_strategy(id_event, new_strategy)?;
-        self.get_estimates(id_event).await
+    #[doc = "Change the strategy of an event, the update is run again if another server changed it in the meantime"]
+    async fn update_strategy(&self, id_event: &Uuid, update_fn: Box<dyn Fn(Strategy) -> MarketResult<Strategy> + Send>) -> MarketResult<StrategyEstimations> {
+        let mut changed = None;
+        for _ in 0..STRATEGY_UPDATE_ATTEMPTS {
+            let (strategy, version) = match self.cache.get_strategy_versioned(id_event).await? {
+                Some((strategy, version)) => (strategy, Some(version)),
+                None => (self.create_new_strategy(id_event).await?, None),
+            };
+            let old_drivers = strategy.drivers.clone();
+            let new_strategy = update_fn(strategy)?;
+            if !self.cache.replace_strategy(id_event, version.as_deref(), &new_strategy).await? { continue }
+            changed = Some(new_strategy.drivers.iter()
+                .filter(|(id, driver)| old_drivers.get(id).map_or(true, |old| old != driver))
+                .map(|(id, _)| *id)
+                .collect::<Vec<IdEventDriver>>());
+            break;
+        }
+        let changed = changed.ok_or(ErrorMarket::StrategyConflict)?;
+
+        let estimates = self.get_estimates(id_event).await?;
+        for id_driver in changed {
+            self.messanger.send_strategy_changed(estimates.driver(&id_driver)?).await?;
+        }
+        Ok(estimates)
+    }
+
+    #[doc = "Tell admins watching the event how many reservations are waiting for a driver. It's only logged if this fails, the change it follows is already saved"]
+    pub async fn publish_pool(&self, id_event: &Uuid) {
+        if let Err(e) = self.send_pool(id_event).await {
+            warn!("Could not publish the pool of event {id_event}, {e:?}");
+        }
+    }
+
+    async fn send_pool(&self, id_event: &Uuid) -> MarketResult<()> {
+        let pool = self.get_pool(id_event).await?;
+        let now = self.clock.now();
+        let oldest_wait = pool.iter().map(|reservation| now - reservation.made_at).max();
+        self.messanger.send_pool_changed(id_event, pool.len() as i32, oldest_wait).await
     }
 
     pub async fn update_driver_location(&self, id_event: &Uuid, id_driver: &IdEventDriver, location: &LatLng) -> MarketResult<()> {
@@ -543,11 +732,66 @@ impl MarketEvent {
         let driver = est.driver(id_driver)?;
         let id_reservations = driver.get_sharing_location_with();
         
-        self.cache.set_driver_location(id_driver, location)?;
+        self.cache.set_driver_location(id_driver, location).await?;
         self.messanger.send_driver_location(id_event, id_driver, id_reservations, location).await?;
         Ok(())
     }
 
+    #[doc = "What an admin subscribing to the event is sent before any live messages"]
+    pub async fn get_snapshot(&self, id_event: &Uuid) -> MarketResult<Vec<MessageMarket>> {
+        let strategy = self.get_estimates(id_event).await?;
+        Ok(vec![MessageMarket::new_event_estimations(strategy)])
+    }
+
+    #[doc = "Get how busy an event is"]
+    pub async fn get_summary(&self, id_event: &Uuid) -> MarketResult<MessageEventSummary> {
+        let pool = self.get_pool(id_event).await?;
+        let strategy = self.get_estimates(id_event).await?;
+
+        let drivers_busy = strategy.drivers.values().filter(|driver| !driver.is_empty()).count();
+        let passengers_picked_up = strategy.drivers.values().flat_map(|driver| driver.picked_up.values()).sum();
+        let longest_pickup = strategy.drivers.values()
+            .flat_map(|driver| driver.dest.iter().chain(driver.queue.iter()))
+            .filter_map(|stop| match stop {
+                DriverStopEstimation::Reservation(res) if !res.is_dropoff => Some(res.pickup.num_seconds() as i32),
+                _ => None,
+            })
+            .max();
+
+        Ok(MessageEventSummary {
+            id_event: *id_event,
+            pool_size: pool.len() as i32,
+            drivers: strategy.drivers.len() as i32,
+            drivers_busy: drivers_busy as i32,
+            passengers_picked_up,
+            longest_pickup,
+            cursor: None,
+        })
+    }
+
+    #[doc = "Send admins watching the org how busy the event is now, at most once every few seconds. The estimator sends one every tick, so a skipped change goes out with the next one. It's only logged if this fails, the next summary replaces it"]
+    pub async fn publish_summary(&self, id_event: &Uuid) {
+        if let Err(e) = self.send_summary(id_event).await {
+            warn!("Could not publish the summary of event {id_event}, {e:?}");
+        }
+    }
+
+    async fn send_summary(&self, id_event: &Uuid) -> MarketResult<()> {
+        if !self.cache.claim_summary(id_event, self.clock.now(), SUMMARY_INTERVAL_SECONDS).await? { return Ok(()) }
+        let event: Event = self.db.send(EventGet { id: *id_event }).await??.into();
+        let summary = self.get_summary(id_event).await?;
+        self.messanger.send_event_summary(&event.id_org, summary).await
+    }
+
+    #[doc = "Get the events of an org that are happening now"]
+    pub async fn list_active_for_org(&self, id_org: &Uuid) -> MarketResult<Vec<Event>> {
+        let events = self.list_active().await?
+            .into_iter()
+            .filter(|event| event.id_org.eq(id_org))
+            .collect();
+        Ok(events)
+    }
+
     #[doc = "Get whether or not the requested driver has reservation in their queue."]
     pub async fn is_driver_empty(&self, id_event: &Uuid, id_driver: &IdEventDriver) -> MarketResult<bool> {
         let est = self.get_estimates(id_event).await?;
@@ -564,18 +808,20 @@ impl MarketEvent {
             strategy.drivers.insert(id_driver, DriverStrategy::new(id_driver, &id_event_cloned, max_capacity));
             Ok(strategy)
         })).await?;
+        self.messanger.send_driver_online(id_event, &id_driver).await?;
         Ok(())
     }
 
     #[doc = "Remove a driver from the event"]
     pub async fn remove_driver(&self, id_event: &Uuid, driver: &Driver) -> MarketResult<()> {
         let id_driver = driver.id;
-        self.cache.delete_driver_location(&id_driver)?;
+        self.cache.delete_driver_location(&id_driver).await?;
+        self.cache.delete_geofence(&id_driver).await?;
         self.update_strategy(id_event, Box::new(move |mut strategy: Strategy| {
             strategy.drivers.remove(&id_driver);
             Ok(strategy)
         })).await?;
+        self.messanger.send_driver_offline(id_event, &id_driver).await?;
         Ok(())
     }
 }
-
@@ -1,18 +1,22 @@
-use std::collections::HashMap;
+use std::{collections::{HashMap, HashSet}, sync::{Arc, atomic::{AtomicU64, Ordering}}};
 
 use chrono::Duration;
-use kv::{Store, Integer};
 use log::debug;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
-use crate::{graphql::geo::model::LatLng, market::{types::MarketResult, util::now, strategy::{model::{Strategy, IdEventDriver}, driver::{model::DriverStrategy, stop::model::DriverStop}}}};
+use crate::{graphql::geo::model::LatLng, market::{types::MarketResult, clock::Clock, store::CacheStore, geocoder::{EstimateConfidence, RouteEstimate}, strategy::{model::{Strategy, IdEventDriver}, driver::{model::DriverStrategy, stop::model::DriverStop}}}};
 
 const BUCKET_LOCATIONS: &str = "location_events";
 const BUCKET_STRATEGIES: &str = "strategies";
 const BUCKET_EST_DRIVERS: &str = "estimations_drivers";
 const BUCKET_REAL_TIME: &str = "location_real_time";
+const BUCKET_REAL_TIME_AT: &str = "location_real_time_at";
 const BUCKET_EST_STOPS: &str = "estimations_stops";
+const BUCKET_APPROACHING: &str = "reservations_approaching";
+const BUCKET_GEOFENCE: &str = "geofence_drivers";
+const BUCKET_SUMMARY_AT: &str = "summaries_at";
+const BUCKET_AUTO_ARRIVAL: &str = "auto_arrival_events";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TimeEstimatesDrivers {
@@ -55,188 +59,449 @@ pub struct CachedEstimate {
     #[serde_as(as = "serde_with::DurationSeconds<i64>")]
     pub duration: Duration,
     pub made_at: i32,
+    #[serde(default)]
+    pub confidence: EstimateConfidence,
 }
 
 const EST_REFRESH_THRESHOLD_SECONDS: i32 = 60;
+const EST_LOW_CONFIDENCE_REFRESH_THRESHOLD_SECONDS: i32 = 15;
+#[doc = "Driver estimates are refreshed after a minute, entries older than this are for a destination the driver no longer has"]
+const EST_DRIVER_TTL_SECONDS: i32 = 10 * 60;
+#[doc = "Traffic between two stops changes slowly, but it does change over a night"]
+const EST_STOP_TTL_SECONDS: i32 = 30 * 60;
 
 impl CachedEstimate {
-    pub fn new(duration: Duration) -> Self {
+    pub fn new(estimate: RouteEstimate, made_at: i32) -> Self {
         Self {
-            duration,
-            made_at: now(),
+            duration: estimate.duration,
+            made_at,
+            confidence: estimate.confidence,
         }
     }
 
-    pub fn should_update(&self) -> bool {
-        let now = now();
+    pub fn is_low_confidence(&self) -> bool {
+        self.confidence == EstimateConfidence::Low
+    }
+
+    pub fn should_update(&self, now: i32) -> bool {
         let diff = now - self.made_at;
-        diff > EST_REFRESH_THRESHOLD_SECONDS
+        let threshold = if self.is_low_confidence() { EST_LOW_CONFIDENCE_REFRESH_THRESHOLD_SECONDS } else { EST_REFRESH_THRESHOLD_SECONDS };
+        diff > threshold
+    }
+
+    pub fn is_expired(&self, now: i32, ttl: i32) -> bool {
+        now - self.made_at > ttl
     }
 }
 
+#[doc = "Get the reservations a cache key refers to, reservation stops are keyed as `{id_reservation}:{order}`"]
+fn key_reservations(key: &str) -> Vec<Uuid> {
+    key.match_indices(':')
+        .filter_map(|(idx, _)| key.get(idx.checked_sub(36)?..idx))
+        .filter_map(|id| Uuid::parse_str(id).ok())
+        .collect()
+}
 
+#[doc = "Counts how often estimates are found in cache, shared between clones"]
+#[derive(Debug, Clone, Default)]
+pub struct CacheStats {
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
 
-#[derive(Debug, Clone)]
-pub struct MarketEventCache {
-    kv: Store
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheStatsSnapshot {
+    pub hits: u64,
+    pub misses: u64,
 }
 
-impl MarketEventCache {
-    pub fn new(kv: Store) -> Self {
-        Self {
-            kv
-        }
+impl CacheStats {
+    fn record(&self, is_hit: bool) {
+        let counter = if is_hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
     }
 
-    #[doc = "Get the bucket for property locations"]
-    fn bucket_property_locations(&self) -> kv::Bucket<&str, kv::Json<LatLng>> {
-        self.kv.bucket(Some(BUCKET_LOCATIONS)).unwrap()
+    pub fn snapshot(&self) -> CacheStatsSnapshot {
+        CacheStatsSnapshot {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
     }
+}
 
-    #[doc = "Get the bucket for strategies"]
-    fn bucket_strategies(&self) -> kv::Bucket<&str, kv::Json<Strategy>> {
-        self.kv.bucket(Some(BUCKET_STRATEGIES)).unwrap()
+impl CacheStatsSnapshot {
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        if total == 0 { None } else { Some(self.hits as f64 / total as f64) }
     }
+}
+
+#[doc = "Everything in cache for one event"]
+#[derive(Debug, Clone)]
+pub struct EventCacheSnapshot {
+    pub id_event: Uuid,
+    pub property_location: Option<LatLng>,
+    pub strategy: Option<Strategy>,
+    pub driver_locations: HashMap<IdEventDriver, LatLng>,
+    pub estimates_drivers: Option<TimeEstimatesDrivers>,
+    pub estimates_stops: Option<TimeEstimatesStops>,
+}
+
+#[doc = "When a driver got to the stop they are going to"]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GeofenceEntry {
+    #[doc = "The key of the stop"]
+    pub stop: String,
+    pub entered_at: i32,
+    #[doc = "If the arrival has been confirmed for the driver"]
+    pub is_confirmed: bool,
+}
+
+#[doc = "What was removed by a garbage collection of the cache"]
+#[derive(Debug, Clone, Default)]
+pub struct CacheGcReport {
+    pub events_evicted: usize,
+    pub estimates_removed: usize,
+}
 
-    #[doc = "Get the bucket for driver estimations"]
-    fn bucket_estimations_drivers(&self) -> kv::Bucket<&str, kv::Json<TimeEstimatesDrivers>> {
-        self.kv.bucket(Some(BUCKET_EST_DRIVERS)).unwrap()
-    }
 
-    #[doc = "Get the bucket for stop estimations"]
-    fn bucket_estimations_stops(&self) -> kv::Bucket<&str, kv::Json<TimeEstimatesStops>> {
-        self.kv.bucket(Some(BUCKET_EST_STOPS)).unwrap()
+
+#[derive(Debug)]
+pub struct MarketEventCache {
+    store: Box<dyn CacheStore>,
+    clock: Box<dyn Clock>,
+    stats: CacheStats,
+}
+
+impl Clone for MarketEventCache {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.box_clone(),
+            clock: self.clock.box_clone(),
+            stats: self.stats.clone(),
+        }
     }
+}
 
-    #[doc = "Get the bucket for driver real time locations"]
-    fn bucket_driver_locations(&self) -> kv::Bucket<Integer, kv::Json<LatLng>> {
-        self.kv.bucket(Some(BUCKET_REAL_TIME)).unwrap()
+impl MarketEventCache {
+    pub fn new(store: Box<dyn CacheStore>, clock: Box<dyn Clock>) -> Self {
+        Self {
+            store,
+            clock,
+            stats: CacheStats::default(),
+        }
     }
 
     #[doc = "Clear the cache for events"]
-    pub fn clear(&self) -> MarketResult<()> {
-        self.kv.drop_bucket(BUCKET_LOCATIONS)?;
-        self.kv.drop_bucket(BUCKET_STRATEGIES)?;
-        self.kv.drop_bucket(BUCKET_EST_DRIVERS)?;
-        self.kv.drop_bucket(BUCKET_EST_STOPS)?;
-        self.kv.drop_bucket(BUCKET_REAL_TIME)?;
+    pub async fn clear(&self) -> MarketResult<()> {
+        self.store.clear(BUCKET_LOCATIONS).await?;
+        self.store.clear(BUCKET_STRATEGIES).await?;
+        self.store.clear(BUCKET_EST_DRIVERS).await?;
+        self.store.clear(BUCKET_EST_STOPS).await?;
+        self.store.clear(BUCKET_REAL_TIME).await?;
+        self.store.clear(BUCKET_REAL_TIME_AT).await?;
+        self.store.clear(BUCKET_APPROACHING).await?;
+        self.store.clear(BUCKET_GEOFENCE).await?;
+        self.store.clear(BUCKET_SUMMARY_AT).await?;
+        self.store.clear(BUCKET_AUTO_ARRIVAL).await?;
         Ok(())
     }
 
     #[doc = "Get a property location from an event id"]
-    pub fn get_property_location(&self, id_event: &Uuid) -> MarketResult<Option<LatLng>> {
+    pub async fn get_property_location(&self, id_event: &Uuid) -> MarketResult<Option<LatLng>> {
         let key = id_event.to_string();
-        let result = self.bucket_property_locations().get(&&*key)?
-            .map(|result| result.0);
+        let result = self.store.get_json(BUCKET_LOCATIONS, &key).await?;
         Ok(result)
     }
 
     #[doc = "Set a property location for an event id"]
-    pub fn set_property_location(&self, id_event: &Uuid, location: LatLng) -> MarketResult<()> {
+    pub async fn set_property_location(&self, id_event: &Uuid, location: LatLng) -> MarketResult<()> {
+        let key = id_event.to_string();
+        self.store.set_json(BUCKET_LOCATIONS, &key, &location).await?;
+        Ok(())
+    }
+
+    #[doc = "Get if an event confirms arrivals from where its drivers are"]
+    pub async fn get_auto_arrival(&self, id_event: &Uuid) -> MarketResult<Option<bool>> {
+        self.store.get_json(BUCKET_AUTO_ARRIVAL, &id_event.to_string()).await
+    }
+
+    #[doc = "Set if an event confirms arrivals from where its drivers are"]
+    pub async fn set_auto_arrival(&self, id_event: &Uuid, is_auto: bool) -> MarketResult<()> {
+        self.store.set_json(BUCKET_AUTO_ARRIVAL, &id_event.to_string(), &is_auto).await
+    }
+
+    #[doc = "Forget what is cached from an event's settings, for when the event is changed"]
+    pub async fn evict_settings(&self, id_event: &Uuid) -> MarketResult<()> {
         let key = id_event.to_string();
-        self.bucket_property_locations().set(&&*key, &kv::Json(location))?;
+        self.store.remove(BUCKET_LOCATIONS, &key).await?;
+        self.store.remove(BUCKET_AUTO_ARRIVAL, &key).await?;
         Ok(())
     }
 
     #[doc = "Get a strategy from an event id"]
-    pub fn get_strategy(&self, id_event: &Uuid) -> MarketResult<Option<Strategy>> {
+    pub async fn get_strategy(&self, id_event: &Uuid) -> MarketResult<Option<Strategy>> {
         let key = id_event.to_string();
-        let result = self.bucket_strategies().get(&&*key)?
-            .map(|result| result.0);
+        let result = self.store.get_json(BUCKET_STRATEGIES, &key).await?;
         Ok(result)
     }
 
-    #[doc = "Set a strategy for an event"]
-    pub fn set_strategy(&self, id_event: &Uuid, strategy: Strategy) -> MarketResult<()> {
+    #[doc = "Get a strategy along with the version of it that was read"]
+    pub async fn get_strategy_versioned(&self, id_event: &Uuid) -> MarketResult<Option<(Strategy, String)>> {
         let key = id_event.to_string();
-        self.bucket_strategies().set(&&*key, &kv::Json(strategy))?;
-        Ok(())
+        let result = self.store.get_json_versioned(BUCKET_STRATEGIES, &key).await?;
+        Ok(result)
+    }
+
+    #[doc = "Replace the version of a strategy that was read, None if there was none. Returns false if it changed since"]
+    pub async fn replace_strategy(&self, id_event: &Uuid, version: Option<&str>, strategy: &Strategy) -> MarketResult<bool> {
+        let key = id_event.to_string();
+        let is_set = self.store.compare_and_set_json(BUCKET_STRATEGIES, &key, version, strategy).await?;
+        Ok(is_set)
     }
 
     #[doc = "Get driver estimations for an event"]
-    pub fn get_estimates_drivers(&self, id_event: &Uuid) -> MarketResult<Option<TimeEstimatesDrivers>> {
+    pub async fn get_estimates_drivers(&self, id_event: &Uuid) -> MarketResult<Option<TimeEstimatesDrivers>> {
         let key = id_event.to_string();
-        let result = self.bucket_estimations_drivers().get(&&*key)?
-            .map(|result| result.0);
+        let result = self.store.get_json(BUCKET_EST_DRIVERS, &key).await?;
         Ok(result)
     }
 
     #[doc = "Get an updated estimate for a driver, this will be None if it is out of date"]
-    pub fn get_estimate_driver(&self, id_event: &Uuid, driver_strategy: &DriverStrategy) -> MarketResult<Option<Duration>> {
+    pub async fn get_estimate_driver(&self, id_event: &Uuid, driver_strategy: &DriverStrategy) -> MarketResult<Option<Duration>> {
         let key = format!("{}-{}", driver_strategy.id, driver_strategy.clone().dest.unwrap().key());
-        let ests = self.get_estimates_drivers(id_event)?;
+        let ests = self.get_estimates_drivers(id_event).await?;
         let est = ests
             .and_then(|ests| ests.drivers.get(&key).cloned())
-            .and_then(|est| if est.should_update() { None } else { Some(est) })
+            .and_then(|est| if est.should_update(self.clock.now()) { None } else { Some(est) })
             .map(|est| est.duration);
+        self.stats.record(est.is_some());
         Ok(est)
     }
 
     #[doc = "Set driver estimates for an event"]
-    fn set_estimates_drivers(&self, id_event: &Uuid, estimates: TimeEstimatesDrivers) -> MarketResult<()> {
+    async fn set_estimates_drivers(&self, id_event: &Uuid, estimates: TimeEstimatesDrivers) -> MarketResult<()> {
         let key = id_event.to_string();
-        self.bucket_estimations_drivers().set(&&*key, &kv::Json(estimates))?;
+        self.store.set_json(BUCKET_EST_DRIVERS, &key, &estimates).await?;
         Ok(())
     }
 
     #[doc = "Update a driver estimation"]
-    pub fn update_estimate_driver(&self, id_event: &Uuid, driver_strategy: &DriverStrategy, estimate: Duration) -> MarketResult<()> {
+    pub async fn update_estimate_driver(&self, id_event: &Uuid, driver_strategy: &DriverStrategy, estimate: RouteEstimate) -> MarketResult<()> {
         let key = format!("{}-{}", driver_strategy.id, driver_strategy.clone().dest.unwrap().key());
-        let mut ests = self.get_estimates_drivers(id_event)?.unwrap_or(TimeEstimatesDrivers::new());
-        ests.drivers.insert(key, CachedEstimate::new(estimate));
-        self.set_estimates_drivers(id_event, ests)?;
+        let mut ests = self.get_estimates_drivers(id_event).await?.unwrap_or(TimeEstimatesDrivers::new());
+        let now = self.clock.now();
+        ests.drivers.retain(|_, est| !est.is_expired(now, EST_DRIVER_TTL_SECONDS));
+        ests.drivers.insert(key, CachedEstimate::new(estimate, now));
+        self.set_estimates_drivers(id_event, ests).await?;
         Ok(())
     }
 
     #[doc = "Get driver location by their id"]
-    pub fn get_driver_location(&self, id_driver: &IdEventDriver) -> MarketResult<Option<LatLng>> {
-        let result = self.bucket_driver_locations().get(&Integer::from(*id_driver))?
-            .map(|result| result.0);
+    pub async fn get_driver_location(&self, id_driver: &IdEventDriver) -> MarketResult<Option<LatLng>> {
+        let result = self.store.get_json(BUCKET_REAL_TIME, &id_driver.to_string()).await?;
         Ok(result)
     }
 
     #[doc = "Set driver location from their id"]
-    pub fn delete_driver_location(&self, id_driver: &IdEventDriver) -> MarketResult<()> {
-        self.bucket_driver_locations().remove(&Integer::from(*id_driver))?;
+    pub async fn delete_driver_location(&self, id_driver: &IdEventDriver) -> MarketResult<()> {
+        self.store.remove(BUCKET_REAL_TIME, &id_driver.to_string()).await?;
+        self.store.remove(BUCKET_REAL_TIME_AT, &id_driver.to_string()).await?;
         Ok(())
     }
 
+    #[doc = "Get when the driver location was taken"]
+    pub async fn get_driver_location_at(&self, id_driver: &IdEventDriver) -> MarketResult<Option<i32>> {
+        self.store.get_json(BUCKET_REAL_TIME_AT, &id_driver.to_string()).await
+    }
+
+    #[doc = "Set when the driver location was taken"]
+    pub async fn set_driver_location_at(&self, id_driver: &IdEventDriver, at: i32) -> MarketResult<()> {
+        self.store.set_json(BUCKET_REAL_TIME_AT, &id_driver.to_string(), &at).await
+    }
+
     #[doc = "Set driver location from their id"]
-    pub fn set_driver_location(&self, id_driver: &IdEventDriver, location: &LatLng) -> MarketResult<()> {
-        self.bucket_driver_locations().set(&Integer::from(*id_driver), &kv::Json(location.clone()))?;
+    pub async fn set_driver_location(&self, id_driver: &IdEventDriver, location: &LatLng) -> MarketResult<()> {
+        self.store.set_json(BUCKET_REAL_TIME, &id_driver.to_string(), location).await?;
         Ok(())
     }
 
     #[doc = "Get stops estimations for an event"]
-    pub fn get_estimates_stops(&self, id_event: &Uuid) -> MarketResult<Option<TimeEstimatesStops>> {
+    pub async fn get_estimates_stops(&self, id_event: &Uuid) -> MarketResult<Option<TimeEstimatesStops>> {
         let key = id_event.to_string();
-        let result = self.bucket_estimations_stops().get(&&*key)?
-            .map(|result| result.0);
+        let result = self.store.get_json(BUCKET_EST_STOPS, &key).await?;
         Ok(result)
     }
 
     #[doc = "Set the estimates stops for an event"]
-    fn set_estimates_stops(&self, id_event: &Uuid, estimates: TimeEstimatesStops) -> MarketResult<()> {
+    async fn set_estimates_stops(&self, id_event: &Uuid, estimates: TimeEstimatesStops) -> MarketResult<()> {
         let key = id_event.to_string();
-        self.bucket_estimations_stops().set(&&*key, &kv::Json(estimates))?;
+        self.store.set_json(BUCKET_EST_STOPS, &key, &estimates).await?;
         Ok(())
     }
 
     #[doc = "Get an estimate between two stops"]
-    pub fn get_estimate_between_stops(&self, id_event: &Uuid, from: &DriverStop, to: &DriverStop) -> MarketResult<Option<Duration>> {
+    pub async fn get_estimate_between_stops(&self, id_event: &Uuid, from: &DriverStop, to: &DriverStop) -> MarketResult<Option<Duration>> {
         let key = from.key_with(to);
-        let stops = self.get_estimates_stops(id_event)?;
-        let est = stops.and_then(|stops| stops.connections.get(&key).cloned());
+        let stops = self.get_estimates_stops(id_event).await?;
+        let est = stops
+            .and_then(|stops| stops.connections.get(&key).cloned())
+            .and_then(|est| if est.is_low_confidence() && est.should_update(self.clock.now()) { None } else { Some(est) })
+            .and_then(|est| if est.is_expired(self.clock.now(), EST_STOP_TTL_SECONDS) { None } else { Some(est) });
         let duration = est.map(|est| est.duration);
+        self.stats.record(duration.is_some());
         Ok(duration)
     }
 
     #[doc = "Update a stop estimate"]
-    pub fn update_estimate_stop(&self, id_event: &Uuid, from: &DriverStop, to: &DriverStop, est: Duration) -> MarketResult<()> {
+    pub async fn update_estimate_stop(&self, id_event: &Uuid, from: &DriverStop, to: &DriverStop, est: RouteEstimate) -> MarketResult<()> {
         let key = from.key_with(to);
-        let mut ests = self.get_estimates_stops(id_event)?.unwrap_or(TimeEstimatesStops::new());
-        ests.connections.insert(key, CachedEstimate::new(est));
-        self.set_estimates_stops(id_event, ests)?;
+        let mut ests = self.get_estimates_stops(id_event).await?.unwrap_or(TimeEstimatesStops::new());
+        let now = self.clock.now();
+        ests.connections.retain(|_, est| !est.is_expired(now, EST_STOP_TTL_SECONDS));
+        ests.connections.insert(key, CachedEstimate::new(est, now));
+        self.set_estimates_stops(id_event, ests).await?;
+        Ok(())
+    }
+
+    #[doc = "Whether the rider was told their driver is almost here"]
+    pub async fn is_approaching(&self, id_event: &Uuid, id_reservation: &Uuid) -> MarketResult<bool> {
+        let notified: HashSet<Uuid> = self.store.get_json(BUCKET_APPROACHING, &id_event.to_string()).await?.unwrap_or_default();
+        Ok(notified.contains(id_reservation))
+    }
+
+    #[doc = "Remember that a rider was told their driver is almost here, this is false if they were already told"]
+    pub async fn mark_approaching(&self, id_event: &Uuid, id_reservation: &Uuid) -> MarketResult<bool> {
+        let key = id_event.to_string();
+        let mut notified: HashSet<Uuid> = self.store.get_json(BUCKET_APPROACHING, &key).await?.unwrap_or_default();
+        if !notified.insert(*id_reservation) { return Ok(false) }
+        self.store.set_json(BUCKET_APPROACHING, &key, &notified).await?;
+        Ok(true)
+    }
+
+    #[doc = "Claim sending the next summary of an event, false if one was sent less than `interval` seconds ago"]
+    pub async fn claim_summary(&self, id_event: &Uuid, now: i32, interval: i32) -> MarketResult<bool> {
+        let key = id_event.to_string();
+        let sent: Option<(i32, String)> = self.store.get_json_versioned(BUCKET_SUMMARY_AT, &key).await?;
+        if sent.as_ref().is_some_and(|(at, _)| now - at < interval) { return Ok(false) }
+        self.store.compare_and_set_json(BUCKET_SUMMARY_AT, &key, sent.as_ref().map(|(_, version)| version.as_str()), &now).await
+    }
+
+    #[doc = "Get when a driver got to their stop"]
+    pub async fn get_geofence(&self, id_driver: &IdEventDriver) -> MarketResult<Option<GeofenceEntry>> {
+        self.store.get_json(BUCKET_GEOFENCE, &id_driver.to_string()).await
+    }
+
+    #[doc = "Set when a driver got to their stop"]
+    pub async fn set_geofence(&self, id_driver: &IdEventDriver, entry: &GeofenceEntry) -> MarketResult<()> {
+        self.store.set_json(BUCKET_GEOFENCE, &id_driver.to_string(), entry).await
+    }
+
+    #[doc = "Forget that a driver is at their stop"]
+    pub async fn delete_geofence(&self, id_driver: &IdEventDriver) -> MarketResult<()> {
+        self.store.remove(BUCKET_GEOFENCE, &id_driver.to_string()).await
+    }
+
+    #[doc = "Get how often estimates have been found in cache"]
+    pub fn stats(&self) -> CacheStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    #[doc = "Remove estimates that have expired or are for reservations that are no longer active, returns how many were removed"]
+    pub async fn prune_event(&self, id_event: &Uuid, active: &HashSet<Uuid>) -> MarketResult<usize> {
+        let now = self.clock.now();
+        let is_stale = |key: &str, est: &CachedEstimate, ttl: i32| {
+            est.is_expired(now, ttl) || key_reservations(key).iter().any(|id| !active.contains(id))
+        };
+        let mut removed = 0;
+
+        if let Some(mut ests) = self.get_estimates_drivers(id_event).await? {
+            let before = ests.drivers.len();
+            ests.drivers.retain(|key, est| !is_stale(key, est, EST_DRIVER_TTL_SECONDS));
+            if ests.drivers.len() != before {
+                removed += before - ests.drivers.len();
+                self.set_estimates_drivers(id_event, ests).await?;
+            }
+        }
+
+        if let Some(mut ests) = self.get_estimates_stops(id_event).await? {
+            let before = ests.connections.len();
+            ests.connections.retain(|key, est| !is_stale(key, est, EST_STOP_TTL_SECONDS));
+            if ests.connections.len() != before {
+                removed += before - ests.connections.len();
+                self.set_estimates_stops(id_event, ests).await?;
+            }
+        }
+
+        if let Some(mut notified) = self.store.get_json::<HashSet<Uuid>>(BUCKET_APPROACHING, &id_event.to_string()).await? {
+            let before = notified.len();
+            notified.retain(|id| active.contains(id));
+            if notified.len() != before {
+                self.store.set_json(BUCKET_APPROACHING, &id_event.to_string(), &notified).await?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    #[doc = "Get the ids of all the events with something in cache"]
+    pub async fn list_events(&self) -> MarketResult<Vec<Uuid>> {
+        let mut ids = HashSet::new();
+        for name in [BUCKET_LOCATIONS, BUCKET_STRATEGIES, BUCKET_EST_DRIVERS, BUCKET_EST_STOPS, BUCKET_APPROACHING, BUCKET_AUTO_ARRIVAL] {
+            for key in self.store.keys(name).await? {
+                if let Ok(id) = Uuid::parse_str(&key) { ids.insert(id); }
+            }
+        }
+        Ok(ids.into_iter().collect())
+    }
+
+    #[doc = "Get everything cached for an event"]
+    pub async fn snapshot(&self, id_event: &Uuid) -> MarketResult<EventCacheSnapshot> {
+        let strategy = self.get_strategy(id_event).await?;
+        let mut driver_locations = HashMap::new();
+        if let Some(strategy) = &strategy {
+            for id_driver in strategy.drivers.keys() {
+                if let Some(location) = self.get_driver_location(id_driver).await? {
+                    driver_locations.insert(*id_driver, location);
+                }
+            }
+        }
+        Ok(EventCacheSnapshot {
+            id_event: *id_event,
+            property_location: self.get_property_location(id_event).await?,
+            strategy,
+            driver_locations,
+            estimates_drivers: self.get_estimates_drivers(id_event).await?,
+            estimates_stops: self.get_estimates_stops(id_event).await?,
+        })
+    }
+
+    #[doc = "Remove what is calculated for an event, keeping the strategy and driver locations"]
+    pub async fn evict_estimates(&self, id_event: &Uuid) -> MarketResult<()> {
+        let key = id_event.to_string();
+        self.store.remove(BUCKET_LOCATIONS, &key).await?;
+        self.store.remove(BUCKET_EST_DRIVERS, &key).await?;
+        self.store.remove(BUCKET_EST_STOPS, &key).await?;
+        Ok(())
+    }
+
+    #[doc = "Remove everything cached for an event"]
+    pub async fn evict_event(&self, id_event: &Uuid) -> MarketResult<()> {
+        let key = id_event.to_string();
+        if let Some(strategy) = self.get_strategy(id_event).await? {
+            for id_driver in strategy.drivers.keys() {
+                self.delete_driver_location(id_driver).await?;
+                self.delete_geofence(id_driver).await?;
+            }
+        }
+        self.store.remove(BUCKET_LOCATIONS, &key).await?;
+        self.store.remove(BUCKET_STRATEGIES, &key).await?;
+        self.store.remove(BUCKET_EST_DRIVERS, &key).await?;
+        self.store.remove(BUCKET_EST_STOPS, &key).await?;
+        self.store.remove(BUCKET_APPROACHING, &key).await?;
+        self.store.remove(BUCKET_SUMMARY_AT, &key).await?;
+        self.store.remove(BUCKET_AUTO_ARRIVAL, &key).await?;
+        debug!("Evicted event {} from cache", id_event);
         Ok(())
     }
 }
@@ -1,6 +1,8 @@
 use chrono::Duration;
 
 use async_trait::async_trait;
+use juniper::GraphQLEnum;
+use serde::{Serialize, Deserialize};
 
 use crate::graphql::{reservations::{FormReservation, FormReservationGeocoded}, geo::model::LatLng};
 
@@ -8,8 +10,45 @@ use super::types::MarketResult;
 pub mod google;
 pub mod mock;
 pub mod mock_location;
+pub mod scenario;
+pub mod resilient;
 
 
+#[doc = "How much an estimate can be trusted, low confidence estimates are not from a routing provider"]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum EstimateConfidence {
+    High,
+    Low,
+}
+
+impl Default for EstimateConfidence {
+    fn default() -> Self {
+        EstimateConfidence::High
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RouteEstimate {
+    pub duration: Duration,
+    pub confidence: EstimateConfidence,
+}
+
+impl RouteEstimate {
+    pub fn exact(duration: Duration) -> Self {
+        Self {
+            duration,
+            confidence: EstimateConfidence::High,
+        }
+    }
+
+    pub fn approximate(duration: Duration) -> Self {
+        Self {
+            duration,
+            confidence: EstimateConfidence::Low,
+        }
+    }
+}
+
 
 #[async_trait]
 pub trait Geocoder: Send + Sync + std::fmt::Debug {
@@ -18,5 +57,10 @@ pub trait Geocoder: Send + Sync + std::fmt::Debug {
     async fn geocode_form(&self, form: &FormReservation) -> MarketResult<FormReservationGeocoded>;
 
     async fn estimate(&self, from: LatLng, to: LatLng) -> MarketResult<Duration>;
-}
 
+    #[doc = "Estimate a route along with how confident the geocoder is in it"]
+    async fn estimate_route(&self, from: LatLng, to: LatLng) -> MarketResult<RouteEstimate> {
+        let duration = self.estimate(from, to).await?;
+        Ok(RouteEstimate::exact(duration))
+    }
+}
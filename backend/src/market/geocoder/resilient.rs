@@ -0,0 +1,190 @@
+use std::{sync::{Arc, Mutex}, time::Instant};
+
+use chrono::Duration;
+use log::warn;
+
+use crate::{graphql::{reservations::{FormReservation, FormReservationGeocoded}, geo::model::LatLng}, market::{types::MarketResult, error::ErrorMarket}};
+
+use super::{Geocoder, RouteEstimate};
+use async_trait::async_trait;
+
+#[derive(Debug, Clone)]
+pub struct ResilienceConfig {
+    #[doc = "How many times a failed request is retried before falling back"]
+    pub retries: u32,
+    #[doc = "The wait before the first retry, doubled for every retry after"]
+    pub backoff: std::time::Duration,
+    #[doc = "How many failures in a row open the circuit"]
+    pub failure_threshold: u32,
+    #[doc = "How long the circuit stays open before a request is let through again"]
+    pub cooldown: std::time::Duration,
+    #[doc = "The average driving speed used for straight line estimates"]
+    pub fallback_speed_kmh: f64,
+    #[doc = "Roads are not straight, this is multiplied onto the straight line distance"]
+    pub fallback_detour_factor: f64,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: std::time::Duration::from_millis(200),
+            failure_threshold: 5,
+            cooldown: std::time::Duration::from_secs(30),
+            fallback_speed_kmh: 40.0,
+            fallback_detour_factor: 1.3,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CircuitState {
+    failures: u32,
+    opened_at: Option<Instant>,
+    #[doc = "If a request was let through after the cooldown and has not come back yet"]
+    is_probing: bool,
+}
+
+#[doc = "Stops calling a failing service for a while so every request does not have to wait on it"]
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    state: Arc<Mutex<CircuitState>>,
+    failure_threshold: u32,
+    cooldown: std::time::Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: std::time::Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CircuitState { failures: 0, opened_at: None, is_probing: false })),
+            failure_threshold,
+            cooldown,
+        }
+    }
+
+    #[doc = "Whether a request should be sent, once the cooldown is over a single request is let through to test the service"]
+    pub fn allows_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.opened_at {
+            None => true,
+            Some(_) if state.is_probing => false,
+            Some(opened_at) if opened_at.elapsed() >= self.cooldown => {
+                state.is_probing = true;
+                true
+            },
+            Some(_) => false,
+        }
+    }
+
+    #[doc = "If requests are being held back, this does not use up the trial request"]
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().opened_at.is_some()
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = 0;
+        state.opened_at = None;
+        state.is_probing = false;
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures += 1;
+        // A failed trial request opens the circuit for another cooldown
+        if state.failures >= self.failure_threshold || state.is_probing {
+            if state.opened_at.is_none() { warn!("Geocoder circuit opened after {} failures", state.failures) }
+            state.opened_at = Some(Instant::now());
+            state.is_probing = false;
+        }
+    }
+}
+
+#[doc = "Wraps a geocoder with retries and a circuit breaker, estimates fall back to the straight line distance"]
+#[derive(Debug)]
+pub struct GeocoderResilient {
+    inner: Box<dyn Geocoder>,
+    breaker: CircuitBreaker,
+    config: ResilienceConfig,
+}
+
+impl Clone for GeocoderResilient {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.box_clone(),
+            breaker: self.breaker.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl GeocoderResilient {
+    pub fn new(inner: Box<dyn Geocoder>, config: ResilienceConfig) -> Self {
+        Self {
+            inner,
+            breaker: CircuitBreaker::new(config.failure_threshold, config.cooldown),
+            config,
+        }
+    }
+
+    #[doc = "Estimate the drive from the straight line distance between two points"]
+    pub fn estimate_straight_line(&self, from: &LatLng, to: &LatLng) -> Duration {
+        let meters = from.distance_meters(to) * self.config.fallback_detour_factor;
+        let meters_per_second = self.config.fallback_speed_kmh * 1000.0 / 3600.0;
+        Duration::seconds((meters / meters_per_second).round() as i64)
+    }
+}
+
+#[doc = "Whether an error is worth retrying, the route not existing will not change by asking again"]
+fn is_transient(err: &ErrorMarket) -> bool {
+    !matches!(err, ErrorMarket::NoRoutes | ErrorMarket::NoRouteLegs)
+}
+
+#[async_trait]
+impl Geocoder for GeocoderResilient {
+    fn box_clone(&self) -> Box<dyn Geocoder> {
+        Box::new(self.clone())
+    }
+
+    async fn geocode_form(&self, form: &FormReservation) -> MarketResult<FormReservationGeocoded> {
+        self.inner.geocode_form(form).await
+    }
+
+    async fn estimate(&self, from: LatLng, to: LatLng) -> MarketResult<Duration> {
+        let est = self.estimate_route(from, to).await?;
+        Ok(est.duration)
+    }
+
+    async fn estimate_route(&self, from: LatLng, to: LatLng) -> MarketResult<RouteEstimate> {
+        if !self.breaker.allows_request() {
+            return Ok(RouteEstimate::approximate(self.estimate_straight_line(&from, &to)));
+        }
+
+        let mut backoff = self.config.backoff;
+        for attempt in 0..=self.config.retries {
+            match self.inner.estimate(from, to).await {
+                Ok(duration) => {
+                    self.breaker.record_success();
+                    return Ok(RouteEstimate::exact(duration));
+                }
+                Err(err) if !is_transient(&err) => {
+                    // The service answered, there just is no route
+                    self.breaker.record_success();
+                    warn!("No route from {from:?} to {to:?}, using straight line estimate");
+                    break;
+                }
+                Err(err) => {
+                    warn!("Geocoder estimate failed (attempt {}), got error: {}", attempt + 1, err);
+                    if attempt == self.config.retries {
+                        self.breaker.record_failure();
+                    } else {
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                }
+            }
+        }
+
+        Ok(RouteEstimate::approximate(self.estimate_straight_line(&from, &to)))
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use chrono::Duration;
+use serde::{Serialize, Deserialize};
+
+use crate::{graphql::{geo::model::LatLng, reservations::{FormReservationStopGeocoded, stops::model::{FormReservationStop, FormLatLng}}}, market::{types::MarketResult, error::ErrorMarket, strategy::driver::stop::reservation::location::model::Address}};
+
+use super::mock_location;
+
+const DEFAULT_FALLBACK_SPEED_KMH: f64 = 30.0;
+
+fn default_fallback_speed_kmh() -> f64 {
+    DEFAULT_FALLBACK_SPEED_KMH
+}
+
+#[doc = "A place the mock geocoder knows about"]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScenarioLocation {
+    pub id: String,
+    pub address: String,
+    pub lat: f64,
+    pub lng: f64,
+}
+
+#[doc = "The drive time between two scenario locations, legs work in both directions"]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScenarioLeg {
+    pub from: String,
+    pub to: String,
+    pub seconds: i64,
+}
+
+#[doc = "The geography used by the mock geocoder, it can be loaded from a file or declared inline in a test"]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MockScenario {
+    pub locations: Vec<ScenarioLocation>,
+    #[serde(default)]
+    pub legs: Vec<ScenarioLeg>,
+    #[doc = "The speed used for the distance based estimate of a pair with no leg"]
+    #[serde(default = "default_fallback_speed_kmh")]
+    pub fallback_speed_kmh: f64,
+}
+
+impl ScenarioLocation {
+    pub fn new(id: &str, address: &str, lat: f64, lng: f64) -> Self {
+        Self {
+            id: id.to_owned(),
+            address: address.to_owned(),
+            lat,
+            lng,
+        }
+    }
+
+    pub fn latlng(&self) -> LatLng {
+        LatLng {
+            lat: self.lat,
+            lng: self.lng,
+        }
+    }
+
+    pub fn stop(&self) -> FormReservationStop {
+        FormReservationStop {
+            place_id: self.id.clone(),
+            address: self.address.clone(),
+            location: FormLatLng::new(self.lat, self.lng),
+        }
+    }
+
+    pub fn geocoded(&self) -> FormReservationStopGeocoded {
+        FormReservationStopGeocoded {
+            address: Address::new(self.address.clone(), "MOCK".to_owned()),
+            location: self.latlng(),
+            place_id: self.id.clone(),
+        }
+    }
+}
+
+impl ScenarioLeg {
+    pub fn new(from: &str, to: &str, duration: Duration) -> Self {
+        Self {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            seconds: duration.num_seconds(),
+        }
+    }
+}
+
+impl Default for MockScenario {
+    fn default() -> Self {
+        let locations = mock_location::ALL_LOCATIONS.iter()
+            .map(|location| {
+                let (lat, lng) = location.lat_lng;
+                ScenarioLocation::new(location.id, location.address, lat, lng)
+            })
+            .collect();
+
+        let legs = vec![
+            ScenarioLeg::new(mock_location::TIGER_BLVD.id, mock_location::BENET_HALL.id, Duration::minutes(10)),
+            ScenarioLeg::new(mock_location::TIGER_BLVD.id, mock_location::CSP.id, Duration::minutes(3)),
+            ScenarioLeg::new(mock_location::CSP.id, mock_location::BENET_HALL.id, Duration::minutes(5)),
+            ScenarioLeg::new(mock_location::BENET_HALL.id, mock_location::DOUTHIT.id, Duration::minutes(5)),
+            ScenarioLeg::new(mock_location::CSP.id, mock_location::DOUTHIT.id, Duration::minutes(4)),
+            ScenarioLeg::new(mock_location::TIGER_BLVD.id, mock_location::DOUTHIT.id, Duration::minutes(8)),
+        ];
+
+        Self {
+            locations,
+            legs,
+            fallback_speed_kmh: DEFAULT_FALLBACK_SPEED_KMH,
+        }
+    }
+}
+
+impl MockScenario {
+    pub fn from_json(json: &str) -> MarketResult<Self> {
+        serde_json::from_str(json)
+            .map_err(|err| ErrorMarket::BadValue(format!("Invalid mock scenario: {err}")))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> MarketResult<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| ErrorMarket::BadValue(format!("Could not read mock scenario '{}': {err}", path.display())))?;
+        Self::from_json(&json)
+    }
+
+    #[doc = "The scenario in the file at `MOCK_SCENARIO`, or the default one when it is not set"]
+    pub fn from_env() -> MarketResult<Self> {
+        match std::env::var("MOCK_SCENARIO") {
+            Ok(path) => Self::from_file(path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn location(&self, id: &str) -> Option<&ScenarioLocation> {
+        self.locations.iter().find(|location| location.id.eq(id))
+    }
+
+    pub fn location_at(&self, latlng: &LatLng) -> Option<&ScenarioLocation> {
+        self.locations.iter().find(|location| location.latlng().is_close_to(latlng))
+    }
+
+    #[doc = "The declared leg between two points, in either direction"]
+    pub fn leg(&self, from: &LatLng, to: &LatLng) -> Option<Duration> {
+        let from = self.location_at(from)?;
+        let to = self.location_at(to)?;
+        self.legs.iter()
+            .find(|leg| (leg.from.eq(&from.id) && leg.to.eq(&to.id)) || (leg.from.eq(&to.id) && leg.to.eq(&from.id)))
+            .map(|leg| Duration::seconds(leg.seconds))
+    }
+
+    #[doc = "A deterministic estimate from the straight line distance, used when no leg is declared"]
+    pub fn estimate_by_distance(&self, from: &LatLng, to: &LatLng) -> Duration {
+        let meters_per_second = self.fallback_speed_kmh * 1000.0 / 3600.0;
+        Duration::seconds((from.distance_meters(to) / meters_per_second).round() as i64)
+    }
+}
@@ -2,10 +2,11 @@ use chrono::Duration;
 use juniper::{GraphQLObject, GraphQLUnion};
 use redis::{FromRedisValue, Value, RedisResult, RedisError, ErrorKind};
 use serde::{Serialize, Deserialize};
+use uuid::Uuid;
 
-use crate::graphql::{geo::model::LatLng, context::Context, reservations::Reservation};
+use crate::graphql::{geo::model::LatLng, context::Context, reservations::{Reservation, stops::model::ReservationStop}};
 
-use super::{types::ReservationEstimate, strategy::model::IdEventDriver, estimate::model::StrategyEstimations};
+use super::{types::ReservationEstimate, strategy::model::IdEventDriver, estimate::{model::StrategyEstimations, driver::model::DriverStrategyEstimations}};
 
 #[derive(Debug, Serialize, Deserialize, Clone, GraphQLUnion)]
 #[graphql(Context = Context)]
@@ -14,29 +15,59 @@ pub enum MessageMarket {
     ReservationEstimation(MessageReservationEstimation),
     ReservationUpdate(MessageReservationUpdate),
     EventEstimations(MessageEventEstimations),
+    DriverStrategy(MessageDriverStrategy),
+    DriverOffer(MessageDriverOffer),
+    ReservationCancelled(MessageReservationCancelled),
+    RiderMessage(MessageRiderMessage),
+    EventSummary(MessageEventSummary),
+    DriverOnline(MessageDriverOnline),
+    DriverOffline(MessageDriverOffline),
+    PoolChanged(MessagePoolChanged),
+    StrategyChanged(MessageStrategyChanged),
+    ReplayTruncated(MessageReplayTruncated),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
 pub struct MessageDriverLocation {
     pub id: IdEventDriver,
     pub location: LatLng,
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
 pub struct MessageReservationEstimation {
     pub estimate: ReservationEstimate,
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 impl MessageMarket {
     pub fn new_reservation_estimate(pickup: Duration, arrival: Duration, queue_position: i32) -> Self {
         Self::ReservationEstimation(MessageReservationEstimation {
-            estimate: ReservationEstimate::new(pickup, arrival, queue_position)
+            estimate: ReservationEstimate::new(pickup, arrival, queue_position),
+            cursor: None,
+        })
+    }
+
+    pub fn from_reservation_estimate(estimate: ReservationEstimate) -> Self {
+        Self::ReservationEstimation(MessageReservationEstimation {
+            estimate,
+            cursor: None,
+        })
+    }
+
+    pub fn new_event_estimations(strategy: StrategyEstimations) -> Self {
+        Self::EventEstimations(MessageEventEstimations {
+            strategy,
+            cursor: None,
         })
     }
 
     pub fn new_reservation_update(reservation: Reservation) -> Self {
         Self::ReservationUpdate(MessageReservationUpdate {
-            reservation
+            reservation,
+            cursor: None,
         })
     }
 
@@ -44,6 +75,122 @@ impl MessageMarket {
         Self::DriverLocation(MessageDriverLocation {
             id,
             location,
+            cursor: None,
+        })
+    }
+
+    pub fn new_driver_strategy(strategy: DriverStrategyEstimations) -> Self {
+        Self::DriverStrategy(MessageDriverStrategy {
+            strategy,
+            cursor: None,
+        })
+    }
+
+    pub fn new_driver_offer(reservation: Reservation) -> Self {
+        Self::DriverOffer(MessageDriverOffer {
+            id_reservation: reservation.id,
+            id_event: reservation.id_event,
+            made_at: reservation.made_at,
+            passenger_count: reservation.passenger_count,
+            is_dropoff: reservation.is_dropoff,
+            stops: reservation.stops.0,
+            cursor: None,
+        })
+    }
+
+    pub fn new_reservation_cancelled(reservation: Reservation) -> Self {
+        Self::ReservationCancelled(MessageReservationCancelled {
+            reservation,
+            cursor: None,
+        })
+    }
+
+    pub fn new_rider_message(id_reservation: Uuid, message: String, sent_at: i32) -> Self {
+        Self::RiderMessage(MessageRiderMessage {
+            id_reservation,
+            message,
+            sent_at,
+            cursor: None,
+        })
+    }
+
+    #[doc = "Where the message is in its topic, set when it is published"]
+    pub fn cursor(&self) -> Option<&str> {
+        match self {
+            Self::DriverLocation(msg) => msg.cursor.as_deref(),
+            Self::ReservationEstimation(msg) => msg.cursor.as_deref(),
+            Self::ReservationUpdate(msg) => msg.cursor.as_deref(),
+            Self::EventEstimations(msg) => msg.cursor.as_deref(),
+            Self::DriverStrategy(msg) => msg.cursor.as_deref(),
+            Self::DriverOffer(msg) => msg.cursor.as_deref(),
+            Self::ReservationCancelled(msg) => msg.cursor.as_deref(),
+            Self::RiderMessage(msg) => msg.cursor.as_deref(),
+            Self::EventSummary(msg) => msg.cursor.as_deref(),
+            Self::DriverOnline(msg) => msg.cursor.as_deref(),
+            Self::DriverOffline(msg) => msg.cursor.as_deref(),
+            Self::PoolChanged(msg) => msg.cursor.as_deref(),
+            Self::StrategyChanged(msg) => msg.cursor.as_deref(),
+            Self::ReplayTruncated(msg) => msg.cursor.as_deref(),
+        }
+    }
+
+    pub fn with_cursor(mut self, cursor: String) -> Self {
+        let slot = match &mut self {
+            Self::DriverLocation(msg) => &mut msg.cursor,
+            Self::ReservationEstimation(msg) => &mut msg.cursor,
+            Self::ReservationUpdate(msg) => &mut msg.cursor,
+            Self::EventEstimations(msg) => &mut msg.cursor,
+            Self::DriverStrategy(msg) => &mut msg.cursor,
+            Self::DriverOffer(msg) => &mut msg.cursor,
+            Self::ReservationCancelled(msg) => &mut msg.cursor,
+            Self::RiderMessage(msg) => &mut msg.cursor,
+            Self::EventSummary(msg) => &mut msg.cursor,
+            Self::DriverOnline(msg) => &mut msg.cursor,
+            Self::DriverOffline(msg) => &mut msg.cursor,
+            Self::PoolChanged(msg) => &mut msg.cursor,
+            Self::StrategyChanged(msg) => &mut msg.cursor,
+            Self::ReplayTruncated(msg) => &mut msg.cursor,
+        };
+        *slot = Some(cursor);
+        self
+    }
+
+    pub fn new_driver_online(id_event: Uuid, id_driver: IdEventDriver) -> Self {
+        Self::DriverOnline(MessageDriverOnline {
+            id_event,
+            id_driver,
+            cursor: None,
+        })
+    }
+
+    pub fn new_driver_offline(id_event: Uuid, id_driver: IdEventDriver) -> Self {
+        Self::DriverOffline(MessageDriverOffline {
+            id_event,
+            id_driver,
+            cursor: None,
+        })
+    }
+
+    pub fn new_pool_changed(id_event: Uuid, pool_size: i32, oldest_wait: Option<i32>) -> Self {
+        Self::PoolChanged(MessagePoolChanged {
+            id_event,
+            pool_size,
+            oldest_wait,
+            cursor: None,
+        })
+    }
+
+    pub fn new_strategy_changed(strategy: DriverStrategyEstimations) -> Self {
+        Self::StrategyChanged(MessageStrategyChanged {
+            strategy,
+            cursor: None,
+        })
+    }
+
+    pub fn new_replay_truncated(since: String) -> Self {
+        Self::ReplayTruncated(MessageReplayTruncated {
+            since,
+            cursor: None,
         })
     }
 
@@ -55,14 +202,123 @@ impl MessageMarket {
 #[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
 #[graphql(Context = Context)]
 pub struct MessageEventEstimations {
-    pub strategy: StrategyEstimations
+    pub strategy: StrategyEstimations,
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 
 #[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
 #[graphql(Context = Context)]
 pub struct MessageReservationUpdate {
-    pub reservation: Reservation
+    pub reservation: Reservation,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A driver's queue changed"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+#[graphql(Context = Context)]
+pub struct MessageDriverStrategy {
+    pub strategy: DriverStrategyEstimations,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A reservation is waiting in the pool for a driver, every driver at the event gets this so it leaves out who the rider is"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessageDriverOffer {
+    pub id_reservation: Uuid,
+    pub id_event: Uuid,
+    pub made_at: i32,
+    pub passenger_count: i32,
+    pub is_dropoff: bool,
+    pub stops: Vec<ReservationStop>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A rider cancelled a reservation the driver was assigned"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+#[graphql(Context = Context)]
+pub struct MessageReservationCancelled {
+    pub reservation: Reservation,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A rider sent their driver a message"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessageRiderMessage {
+    pub id_reservation: Uuid,
+    pub message: String,
+    pub sent_at: i32,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "How busy an event is, for dashboards watching many events at once"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessageEventSummary {
+    pub id_event: Uuid,
+    #[graphql(description = "Reservations waiting for a driver")]
+    pub pool_size: i32,
+    pub drivers: i32,
+    #[graphql(description = "Drivers with a reservation in their queue or riders in their car")]
+    pub drivers_busy: i32,
+    #[graphql(description = "Riders in a car")]
+    pub passengers_picked_up: i32,
+    #[graphql(description = "The longest a queued rider has to wait to be picked up, in seconds")]
+    pub longest_pickup: Option<i32>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A driver joined the event"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessageDriverOnline {
+    pub id_event: Uuid,
+    pub id_driver: IdEventDriver,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A driver left the event"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessageDriverOffline {
+    pub id_event: Uuid,
+    pub id_driver: IdEventDriver,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A reservation went into or out of the pool"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessagePoolChanged {
+    pub id_event: Uuid,
+    pub pool_size: i32,
+    #[graphql(description = "How long the reservation that has been in the pool longest has waited, in seconds")]
+    pub oldest_wait: Option<i32>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "A driver's queue changed, sent to admins"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+#[graphql(Context = Context)]
+pub struct MessageStrategyChanged {
+    pub strategy: DriverStrategyEstimations,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[doc = "Some messages after the cursor were dropped from the log before they could be replayed, the state should be loaded again"]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+pub struct MessageReplayTruncated {
+    #[graphql(description = "The cursor that was asked for")]
+    pub since: String,
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 impl FromRedisValue for MessageMarket {
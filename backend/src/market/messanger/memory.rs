@@ -0,0 +1,179 @@
+use std::{collections::{HashMap, VecDeque}, sync::{Arc, Mutex, atomic::Ordering}, time::{Duration, Instant}};
+
+use crate::market::{types::{MarketResult, StreamMessageMarket}, messages::MessageMarket, error::ErrorMarket};
+
+use super::{Messanger, MessangerStats, MessangerStatsSnapshot};
+use async_trait::async_trait;
+use log::warn;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const DEFAULT_CAPACITY: usize = 256;
+const DEFAULT_LOG_LEN: usize = 100;
+const DEFAULT_LOG_TTL: Duration = Duration::from_secs(60 * 60);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[doc = "A topic's live channel and the messages kept for subscribers that come back"]
+#[derive(Debug)]
+struct Topic {
+    sender: broadcast::Sender<MessageMarket>,
+    log: VecDeque<(u64, Instant, MessageMarket)>,
+    next_seq: u64,
+}
+
+impl Topic {
+    fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+            log: VecDeque::new(),
+            next_seq: 1,
+        }
+    }
+
+    fn prune(&mut self, max_len: usize, ttl: Duration) {
+        while self.log.len() > max_len { self.log.pop_front(); }
+        while self.log.front().map_or(false, |(_, at, _)| at.elapsed() > ttl) { self.log.pop_front(); }
+    }
+
+    fn is_unused(&self) -> bool {
+        self.log.is_empty() && self.sender.receiver_count() == 0
+    }
+}
+
+#[derive(Debug)]
+struct Topics {
+    by_key: HashMap<String, Topic>,
+    swept_at: Instant,
+}
+
+#[doc = "Sends messages between subscribers in this process, for running without redis"]
+#[derive(Debug, Clone)]
+pub struct MessangerMemory {
+    topics: Arc<Mutex<Topics>>,
+    capacity: usize,
+    log_len: usize,
+    log_ttl: Duration,
+    stats: MessangerStats,
+}
+
+impl MessangerMemory {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    #[doc = "How many messages a topic holds for a slow subscriber before it starts skipping them"]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            topics: Arc::new(Mutex::new(Topics { by_key: HashMap::new(), swept_at: Instant::now() })),
+            capacity,
+            log_len: DEFAULT_LOG_LEN,
+            log_ttl: DEFAULT_LOG_TTL,
+            stats: MessangerStats::default(),
+        }
+    }
+
+    #[doc = "How many messages, and for how long, each topic keeps to replay"]
+    pub fn with_log(mut self, log_len: usize, log_ttl: Duration) -> Self {
+        self.log_len = log_len;
+        self.log_ttl = log_ttl;
+        self
+    }
+
+    #[doc = "The number of topics with at least one subscriber"]
+    pub fn topic_count(&self) -> usize {
+        let topics = self.topics.lock().unwrap();
+        topics.by_key.values().filter(|topic| topic.sender.receiver_count() > 0).count()
+    }
+}
+
+fn parse_cursor(cursor: &str) -> MarketResult<u64> {
+    cursor.parse().map_err(|_| ErrorMarket::BadValue(format!("Invalid cursor '{cursor}'")))
+}
+
+#[async_trait]
+impl Messanger for MessangerMemory {
+    fn box_clone(&self) -> Box<dyn Messanger> {
+        Box::new(self.clone())
+    }
+
+    async fn publish(&self, key: String, message: MessageMarket) -> MarketResult<()> {
+        MessangerStats::record(&self.stats.published);
+        let mut topics = self.topics.lock().unwrap();
+        let topic = topics.by_key.entry(key).or_insert_with(|| Topic::new(self.capacity));
+
+        let seq = topic.next_seq;
+        topic.next_seq += 1;
+        let message = message.with_cursor(seq.to_string());
+        topic.log.push_back((seq, Instant::now(), message.clone()));
+        topic.prune(self.log_len, self.log_ttl);
+
+        // It's fine if nobody is listening, the message is in the log for when they come back
+        let _ = topic.sender.send(message);
+
+        // Drop the logs of topics nobody has published to in a while
+        if topics.swept_at.elapsed() > SWEEP_INTERVAL {
+            topics.swept_at = Instant::now();
+            topics.by_key.retain(|_, topic| {
+                topic.prune(self.log_len, self.log_ttl);
+                !topic.is_unused()
+            });
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self, key: String, since: Option<String>) -> MarketResult<StreamMessageMarket> {
+        let since = since.as_deref().map(parse_cursor).transpose()?;
+        MessangerStats::record(&self.stats.subscriptions);
+
+        // Take the missed messages and join the channel under one lock so nothing is missed or sent twice
+        let (missed, mut receiver) = {
+            let mut topics = self.topics.lock().unwrap();
+            let topic = topics.by_key.entry(key.clone()).or_insert_with(|| Topic::new(self.capacity));
+            let mut missed: Vec<MessageMarket> = Vec::new();
+            if let Some(since) = since {
+                // Sequences have no gaps, so anything between the cursor and the oldest kept message was pruned.
+                // A cursor that was never given out is from before a restart
+                let oldest = topic.log.front().map_or(topic.next_seq, |(seq, _, _)| *seq);
+                if oldest > since + 1 || since >= topic.next_seq {
+                    missed.push(MessageMarket::new_replay_truncated(since.to_string()));
+                }
+                missed.extend(topic.log.iter()
+                    .filter(|(seq, _, _)| *seq > since)
+                    .map(|(_, _, message)| message.clone()));
+            }
+            (missed, topic.sender.subscribe())
+        };
+
+        let stats = self.stats.clone();
+        let stream = async_stream::stream! {
+            for message in missed {
+                yield Ok(message);
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => yield Ok(message),
+                    Err(RecvError::Lagged(skipped)) => {
+                        stats.dropped.fetch_add(skipped, Ordering::Relaxed);
+                        warn!("MEMORY MESSENGER: {key}: subscriber skipped {skipped} messages");
+                    },
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+        Ok(Box::pin(stream))
+    }
+
+    async fn subscribe_from_end(&self, key: String) -> MarketResult<(Option<String>, StreamMessageMarket)> {
+        // Everything published after joining comes after the end of the log, so there is nothing to skip
+        let end = {
+            let mut topics = self.topics.lock().unwrap();
+            let topic = topics.by_key.entry(key.clone()).or_insert_with(|| Topic::new(self.capacity));
+            topic.next_seq - 1
+        };
+        let stream = self.subscribe(key, Some(end.to_string())).await?;
+        Ok((Some(end.to_string()), stream))
+    }
+
+    fn stats(&self) -> MessangerStatsSnapshot {
+        self.stats.snapshot()
+    }
+}
@@ -25,7 +25,7 @@ impl Messanger for MessangerMock {
         Ok(())
     }
 
-    async fn subscribe(&self, _key: String) -> MarketResult<StreamMessageMarket> {
+    async fn subscribe(&self, _key: String, _since: Option<String>) -> MarketResult<StreamMessageMarket> {
         todo!()
     }
 }
@@ -1,23 +1,76 @@
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+
 use async_trait::async_trait;
 use chrono::Duration;
 use uuid::Uuid;
 
 use crate::graphql::{reservations::Reservation, geo::model::LatLng};
 
-use super::{types::{MarketResult, StreamMessageMarket}, messages::MessageMarket, strategy::model::IdEventDriver};
+use super::{types::{MarketResult, StreamMessageMarket}, messages::{MessageMarket, MessageEventSummary}, strategy::model::IdEventDriver, estimate::driver::model::DriverStrategyEstimations};
 pub mod redis;
 pub mod mock;
+pub mod memory;
 
 
 
 
 #[async_trait]
-pub trait Messanger: Send + Sync {
+pub trait Messanger: Send + Sync + std::fmt::Debug {
     fn box_clone(&self) -> Box<dyn Messanger>;
 
     async fn publish(&self, key: String, message: MessageMarket) -> MarketResult<()>;
 
-    async fn subscribe(&self, key: String) -> MarketResult<StreamMessageMarket>;
+    #[doc = "Get the messages sent to a topic, starting with the ones after `since` if the messanger still has them"]
+    async fn subscribe(&self, key: String, since: Option<String>) -> MarketResult<StreamMessageMarket>;
+
+    #[doc = "Join a topic to follow a snapshot taken right after, gets the cursor the snapshot is at. Only messages after that cursor are sent, the snapshot has the rest"]
+    async fn subscribe_from_end(&self, key: String) -> MarketResult<(Option<String>, StreamMessageMarket)> {
+        Ok((None, self.subscribe(key, None).await?))
+    }
+
+    #[doc = "What the messanger has done since the server started"]
+    fn stats(&self) -> MessangerStatsSnapshot {
+        MessangerStatsSnapshot::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MessangerStatsSnapshot {
+    pub published: u64,
+    pub publish_errors: u64,
+    pub subscriptions: u64,
+    pub reconnects: u64,
+    pub decode_errors: u64,
+    pub dropped: u64,
+}
+
+#[doc = "Counts what the messanger has done, shared between clones"]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MessangerStats {
+    pub(crate) published: Arc<AtomicU64>,
+    pub(crate) publish_errors: Arc<AtomicU64>,
+    pub(crate) subscriptions: Arc<AtomicU64>,
+    pub(crate) reconnects: Arc<AtomicU64>,
+    pub(crate) decode_errors: Arc<AtomicU64>,
+    #[doc = "Messages subscribers fell too far behind to get"]
+    pub(crate) dropped: Arc<AtomicU64>,
+}
+
+impl MessangerStats {
+    pub(crate) fn record(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> MessangerStatsSnapshot {
+        MessangerStatsSnapshot {
+            published: self.published.load(Ordering::Relaxed),
+            publish_errors: self.publish_errors.load(Ordering::Relaxed),
+            subscriptions: self.subscriptions.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
 }
 
 impl dyn Messanger {
@@ -42,6 +95,50 @@ impl dyn Messanger {
         self.send_event(id_event, message).await
     }
 
+    #[doc = "Summaries go to the org so dashboards find out about events that start after they subscribed"]
+    pub async fn send_event_summary(&self, id_org: &Uuid, summary: MessageEventSummary) -> MarketResult<()> {
+        self.publish(format!("org:{}", id_org), MessageMarket::EventSummary(summary)).await
+    }
+
+    pub async fn send_driver_online(&self, id_event: &Uuid, id_driver: &IdEventDriver) -> MarketResult<()> {
+        self.send_event(id_event, MessageMarket::new_driver_online(*id_event, *id_driver)).await
+    }
+
+    pub async fn send_driver_offline(&self, id_event: &Uuid, id_driver: &IdEventDriver) -> MarketResult<()> {
+        self.send_event(id_event, MessageMarket::new_driver_offline(*id_event, *id_driver)).await
+    }
+
+    pub async fn send_pool_changed(&self, id_event: &Uuid, pool_size: i32, oldest_wait: Option<i32>) -> MarketResult<()> {
+        self.send_event(id_event, MessageMarket::new_pool_changed(*id_event, pool_size, oldest_wait)).await
+    }
+
+    pub async fn send_strategy_changed(&self, strategy: DriverStrategyEstimations) -> MarketResult<()> {
+        let id_event = strategy.id_event;
+        self.send_event(&id_event, MessageMarket::new_strategy_changed(strategy)).await
+    }
+
+    pub async fn send_driver_strategy(&self, strategy: DriverStrategyEstimations) -> MarketResult<()> {
+        let id_driver = strategy.id;
+        let message = MessageMarket::new_driver_strategy(strategy);
+        self.send_driver(&id_driver, message).await
+    }
+
+    pub async fn send_driver_offer(&self, reservation: Reservation) -> MarketResult<()> {
+        let id_event = reservation.id_event;
+        let message = MessageMarket::new_driver_offer(reservation);
+        self.send_drivers(&id_event, message).await
+    }
+
+    pub async fn send_reservation_cancelled(&self, id_driver: &IdEventDriver, reservation: Reservation) -> MarketResult<()> {
+        let message = MessageMarket::new_reservation_cancelled(reservation);
+        self.send_driver(id_driver, message).await
+    }
+
+    pub async fn send_rider_message(&self, id_driver: &IdEventDriver, id_reservation: &Uuid, message: String, sent_at: i32) -> MarketResult<()> {
+        let message = MessageMarket::new_rider_message(*id_reservation, message, sent_at);
+        self.send_driver(id_driver, message).await
+    }
+
     async fn send_reservation(&self, id_reservation: &Uuid, message: MessageMarket) -> MarketResult<()> {
         self.publish(format!("res:{}", id_reservation), message).await
     }
@@ -49,6 +146,15 @@ impl dyn Messanger {
     async fn send_event(&self, id_event: &Uuid, message: MessageMarket) -> MarketResult<()> {
         self.publish(format!("event:{}", id_event), message).await
     }
+
+    async fn send_driver(&self, id_driver: &IdEventDriver, message: MessageMarket) -> MarketResult<()> {
+        self.publish(format!("driver:{}", id_driver), message).await
+    }
+
+    #[doc = "Sent to every driver at the event"]
+    async fn send_drivers(&self, id_event: &Uuid, message: MessageMarket) -> MarketResult<()> {
+        self.publish(format!("drivers:{}", id_event), message).await
+    }
 }
 
 
@@ -1,18 +1,57 @@
-use crate::market::{types::{MarketResult, StreamMessageMarket}, messages::MessageMarket};
+use std::{sync::Arc, time::Duration};
 
-use super::Messanger;
+use crate::market::{types::{MarketResult, StreamMessageMarket}, messages::MessageMarket, error::ErrorMarket};
+
+use super::{Messanger, MessangerStats, MessangerStatsSnapshot};
 use async_trait::async_trait;
-use juniper::FieldError;
-use redis::{AsyncCommands, aio::Connection};
+use log::{warn, error};
+use redis::{Script, aio::{ConnectionManager, PubSub}};
 use futures::StreamExt;
+use tokio::sync::OnceCell;
+
+const BACKOFF_MIN: Duration = Duration::from_millis(250);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+#[doc = "About how many messages each topic keeps to replay"]
+const LOG_LEN: usize = 100;
+#[doc = "How long a topic's messages are kept after the last one was sent"]
+const LOG_TTL_SECONDS: usize = 60 * 60;
+#[doc = "The cursor of an empty log, before any message"]
+const CURSOR_START: (u64, u64) = (0, 0);
 
+#[doc = "Log the message and send it in one round trip, it is sent as `{cursor} {message}` since the cursor is only known once it is logged"]
+const SCRIPT_PUBLISH: &str = r"
+local id = redis.call('XADD', KEYS[1], 'MAXLEN', '~', ARGV[1], '*', 'message', ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+redis.call('PUBLISH', ARGV[4], id .. ' ' .. ARGV[2])
+return id
+";
 
-#[derive(Debug, Clone)]
-pub struct MessangerRedis;
+#[derive(Clone)]
+pub struct MessangerRedis {
+    client: redis::Client,
+    #[doc = "One multiplexed connection shared by every publish, made on first use"]
+    publisher: Arc<OnceCell<ConnectionManager>>,
+    stats: MessangerStats,
+}
 
 impl MessangerRedis {
-    pub fn new() -> Self {
-        Self
+    pub fn new(url: &str) -> MarketResult<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            client,
+            publisher: Arc::new(OnceCell::new()),
+            stats: MessangerStats::default(),
+        })
+    }
+}
+
+impl std::fmt::Debug for MessangerRedis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessangerRedis")
+            .field("client", &self.client)
+            .field("is_connected", &self.publisher.initialized())
+            .field("stats", &self.stats)
+            .finish()
     }
 }
 
@@ -23,33 +62,195 @@ impl Messanger for MessangerRedis {
     }
 
     async fn publish(&self, key: String, message: MessageMarket) -> MarketResult<()> {
-        let mut pubsub = self.connection().await?;
-        pubsub.publish(key, message.serialize()).await?;
-        Ok(())
+        let result = self.publish_inner(key, message).await;
+        match &result {
+            Ok(_) => MessangerStats::record(&self.stats.published),
+            Err(e) => {
+                MessangerStats::record(&self.stats.publish_errors);
+                error!("Error publishing to redis, {e:?}");
+            },
+        }
+        result
+    }
+
+    async fn subscribe(&self, key: String, since: Option<String>) -> MarketResult<StreamMessageMarket> {
+        let last: Option<(u64, u64)> = since.as_deref().map(parse_cursor).transpose()?;
+        MessangerStats::record(&self.stats.subscriptions);
+
+        // Without a cursor start from the end of the log, so what is sent while reconnecting can still be caught up on
+        let mut conn = connection(&self.publisher, &self.client).await?;
+        let since = match last {
+            Some(since) => since,
+            None => read_log_end(&mut conn, &key).await?,
+        };
+
+        // Connect once up front so a bad redis fails the subscription instead of hanging it.
+        // Join the topic before reading the log so nothing sent in between is missed
+        let mut pubsub = self.client.get_async_connection().await?.into_pubsub();
+        pubsub.subscribe(&key).await?;
+        let missed = read_log(&mut conn, &key, &since, &self.stats).await?;
+        Ok(self.stream(key, pubsub, since, missed))
     }
 
-    async fn subscribe(&self, key: String) -> MarketResult<StreamMessageMarket> {
-        let mut pubsub = self.connection().await?.into_pubsub();
-        pubsub.subscribe(key).await?;
+    async fn subscribe_from_end(&self, key: String) -> MarketResult<(Option<String>, StreamMessageMarket)> {
+        MessangerStats::record(&self.stats.subscriptions);
+
+        // Join before finding where the log ends, what was sent before then is skipped as the snapshot taken after has it
+        let mut pubsub = self.client.get_async_connection().await?.into_pubsub();
+        pubsub.subscribe(&key).await?;
+        let mut conn = connection(&self.publisher, &self.client).await?;
+        let end = read_log_end(&mut conn, &key).await?;
+        Ok((Some(format!("{}-{}", end.0, end.1)), self.stream(key, pubsub, end, Vec::new())))
+    }
 
-        let stream = pubsub
-            .into_on_message()
-            .map(|m| {
-                m.get_payload::<MessageMarket>()
-                    .map_err(|e| FieldError::from(e))
-            })
-            .boxed();
-        Ok(stream)
+    fn stats(&self) -> MessangerStatsSnapshot {
+        self.stats.snapshot()
     }
 }
 
 impl MessangerRedis {
-    async fn connection(&self) -> MarketResult<Connection> {
-        let url = "redis://127.0.0.1:6379";
-        let client = redis::Client::open(url).unwrap();
-        let conn = client.get_async_connection().await?;
-        Ok(conn)
+    #[doc = "Send what was missed then what is published to the topic, skipping anything at or before `last`. Reconnects when the connection drops"]
+    fn stream(&self, key: String, mut pubsub: PubSub, last: (u64, u64), mut missed: Vec<((u64, u64), MessageMarket)>) -> StreamMessageMarket {
+        let mut last = Some(last);
+        let client = self.client.clone();
+        let publisher = self.publisher.clone();
+        let stats = self.stats.clone();
+        let stream = async_stream::stream! {
+            let mut backoff = BACKOFF_MIN;
+            loop {
+                for (cursor, message) in missed.drain(..) {
+                    last = Some(cursor);
+                    yield Ok(message);
+                }
+
+                let mut messages = pubsub.into_on_message();
+                while let Some(msg) = messages.next().await {
+                    backoff = BACKOFF_MIN;
+                    let Some((cursor, message)) = msg.get_payload::<String>().ok().as_deref().and_then(read_payload) else {
+                        MessangerStats::record(&stats.decode_errors);
+                        warn!("REDIS MESSENGER: {key}: skipped a message that could not be read");
+                        continue
+                    };
+                    // Already sent from the log
+                    if Some(cursor) <= last { continue }
+                    last = Some(cursor);
+                    yield Ok(message);
+                }
+                drop(messages);
+
+                // The connection dropped, keep trying until it comes back rather than ending the subscription
+                pubsub = loop {
+                    MessangerStats::record(&stats.reconnects);
+                    warn!("REDIS MESSENGER: {key}: reconnecting in {}ms", backoff.as_millis());
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(BACKOFF_MAX);
+
+                    let mut pubsub = match client.get_async_connection().await {
+                        Ok(conn) => conn.into_pubsub(),
+                        Err(e) => {
+                            warn!("REDIS MESSENGER: {key}: could not reconnect, {e:?}");
+                            continue
+                        },
+                    };
+                    match pubsub.subscribe(&key).await {
+                        Ok(_) => break pubsub,
+                        Err(e) => warn!("REDIS MESSENGER: {key}: could not resubscribe, {e:?}"),
+                    }
+                };
+
+                // Catch up on what was sent while the connection was down
+                if let Some(since) = &last {
+                    let read = match connection(&publisher, &client).await {
+                        Ok(mut conn) => read_log(&mut conn, &key, since, &stats).await,
+                        Err(e) => Err(e),
+                    };
+                    match read {
+                        Ok(read) => missed = read,
+                        Err(e) => warn!("REDIS MESSENGER: {key}: could not read the messages sent while disconnected, {e:?}"),
+                    }
+                }
+            }
+        };
+        Box::pin(stream)
+    }
+
+    async fn publish_inner(&self, key: String, message: MessageMarket) -> MarketResult<()> {
+        let mut conn = connection(&self.publisher, &self.client).await?;
+
+        // Keep the message in the topic's log so subscribers can catch up on it, its id there is the cursor
+        let _: String = Script::new(SCRIPT_PUBLISH)
+            .key(format!("log:{key}"))
+            .arg(LOG_LEN)
+            .arg(message.serialize())
+            .arg(LOG_TTL_SECONDS)
+            .arg(key)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(())
     }
 }
 
+async fn connection(publisher: &OnceCell<ConnectionManager>, client: &redis::Client) -> MarketResult<ConnectionManager> {
+    let conn = publisher
+        .get_or_try_init(|| ConnectionManager::new(client.clone()))
+        .await?
+        .clone();
+    Ok(conn)
+}
+
+#[doc = "Cursors are redis stream ids, `{milliseconds}-{sequence}`"]
+fn parse_cursor(cursor: &str) -> MarketResult<(u64, u64)> {
+    let invalid = || ErrorMarket::BadValue(format!("Invalid cursor '{cursor}'"));
+    let (ms, seq) = cursor.split_once('-').ok_or_else(invalid)?;
+    Ok((ms.parse().map_err(|_| invalid())?, seq.parse().map_err(|_| invalid())?))
+}
+
+#[doc = "Read a published `{cursor} {message}`"]
+fn read_payload(payload: &str) -> Option<((u64, u64), MessageMarket)> {
+    let (cursor, message) = payload.split_once(' ')?;
+    let message = serde_json::from_str::<MessageMarket>(message).ok()?;
+    Some((parse_cursor(cursor).ok()?, message.with_cursor(cursor.to_string())))
+}
+
+#[doc = "The cursor of the last message in a topic's log"]
+async fn read_log_end(conn: &mut ConnectionManager, key: &str) -> MarketResult<(u64, u64)> {
+    let entries: Vec<(String, Vec<String>)> = redis::cmd("XREVRANGE")
+        .arg(format!("log:{key}"))
+        .arg("+")
+        .arg("-")
+        .arg("COUNT").arg(1)
+        .query_async(conn)
+        .await?;
+    Ok(entries.first().and_then(|(id, _)| parse_cursor(id).ok()).unwrap_or(CURSOR_START))
+}
+
+#[doc = "Get the messages in a topic's log after the cursor, with their cursors. Starts with a `ReplayTruncated` if the log no longer has the cursor"]
+async fn read_log(conn: &mut ConnectionManager, key: &str, since: &(u64, u64), stats: &MessangerStats) -> MarketResult<Vec<((u64, u64), MessageMarket)>> {
+    let entries: Vec<(String, Vec<String>)> = redis::cmd("XRANGE")
+        .arg(format!("log:{key}"))
+        .arg(format!("{}-{}", since.0, since.1))
+        .arg("+")
+        .query_async(conn)
+        .await?;
 
+    let mut messages = Vec::new();
+    // The range includes the cursor itself, if it is gone what came after it may be too
+    let has_since = entries.first().and_then(|(id, _)| parse_cursor(id).ok()) == Some(*since);
+    if *since != CURSOR_START && !has_since {
+        warn!("REDIS MESSENGER: {key}: the log no longer has {}-{}, some messages can not be replayed", since.0, since.1);
+        messages.push((*since, MessageMarket::new_replay_truncated(format!("{}-{}", since.0, since.1))));
+    }
+    for (id, fields) in entries {
+        let Ok(cursor) = parse_cursor(&id) else { continue };
+        if cursor <= *since { continue }
+        let payload = fields.chunks(2).find(|field| field[0] == "message").and_then(|field| field.get(1));
+        match payload.map(|payload| serde_json::from_str::<MessageMarket>(payload)) {
+            Some(Ok(message)) => messages.push((cursor, message.with_cursor(id))),
+            _ => {
+                MessangerStats::record(&stats.decode_errors);
+                warn!("REDIS MESSENGER: {key}: skipped a logged message that could not be read");
+            },
+        }
+    }
+    Ok(messages)
+}
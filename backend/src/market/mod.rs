@@ -5,93 +5,154 @@ pub mod util;
 pub mod geocoder;
 pub mod messanger;
 pub mod pusher;
+pub mod notification;
+pub mod template;
 pub mod driver;
 pub mod event;
 pub mod vehicle;
 pub mod reservation;
 pub mod strategy;
 pub mod estimate;
+pub mod clock;
+pub mod store;
+pub mod config;
 
 use actix::Addr;
-use kv::Store;
 use crate::{db_util::DBActor, sms::ClientTwilio};
 use google_maps::prelude::GoogleMapsClient;
 
-use self::{error::ErrorMarket, driver::MarketDriver, event::MarketEvent, vehicle::MarketVehicle, reservation::MarketReservation, geocoder::{Geocoder, google::GeocoderGoogle, mock::GeocoderMock}, messanger::{Messanger, redis::MessangerRedis, mock::MessangerMock}, pusher::{Pushers, mock::PusherMock, twilio::PusherTwilio}};
+use self::{error::ErrorMarket, types::MarketResult, driver::MarketDriver, event::MarketEvent, notification::MarketNotification, vehicle::MarketVehicle, reservation::MarketReservation, geocoder::{Geocoder, google::GeocoderGoogle, mock::GeocoderMock, scenario::MockScenario, resilient::{GeocoderResilient, ResilienceConfig}}, messanger::{Messanger, redis::MessangerRedis, mock::MessangerMock, memory::MessangerMemory}, pusher::{Pusher, Pushers, mock::{PusherMock, PushServiceMock}, twilio::PusherTwilio, webpush::{PusherWebPush, PushService, PushServiceVapid}}, clock::{Clock, ClockSystem}, store::{CacheStore, memory::CacheStoreMemory}, config::{MarketConfig, MessangerBackend}};
 
 
 pub struct Market {
-    pub kv: Store,
+    pub store: Box<dyn CacheStore>,
     pub is_mock: bool,
     pub sms: ClientTwilio,
     pub messanger: Box<dyn Messanger>,
     pub db: Addr<DBActor>,
+    pub clock: Box<dyn Clock>,
 
     pub driver: MarketDriver,
     pub event: MarketEvent,
     pub vehicle: MarketVehicle,
     pub reservation: MarketReservation,
+    pub notification: MarketNotification,
 }
 
+#[doc = "What a mock market is made with"]
+#[derive(Debug)]
+pub struct MarketMockConfig {
+    pub scenario: MockScenario,
+    pub clock: Box<dyn Clock>,
+    pub store: Box<dyn CacheStore>,
+    pub messanger: Box<dyn Messanger>,
+    #[doc = "Where web pushes go, a `PushServiceMock` to see what riders were sent"]
+    pub push_service: Box<dyn PushService>,
+    pub market: MarketConfig,
+}
+
+impl Default for MarketMockConfig {
+    fn default() -> Self {
+        Self {
+            scenario: MockScenario::default(),
+            clock: Box::new(ClockSystem::new()),
+            store: Box::new(CacheStoreMemory::new()),
+            messanger: Box::new(MessangerMock::new()),
+            push_service: Box::new(PushServiceMock::new()),
+            market: MarketConfig::default(),
+        }
+    }
+}
 
 impl Clone for Market {
     fn clone(&self) -> Self {
         Market {
-            kv: self.kv.clone(),
+            store: self.store.box_clone(),
             is_mock: self.is_mock,
             db: self.db.clone(),
             sms: self.sms.clone(),
             messanger: self.messanger.box_clone(),
+            clock: self.clock.box_clone(),
             driver: self.driver.clone(),
             event: self.event.clone(),
             vehicle: self.vehicle.clone(),
             reservation: self.reservation.clone(),
+            notification: self.notification.clone(),
         }
     }
 }
 
 impl Market {
-    pub fn new(db: Addr<DBActor>, kv: Store, maps: GoogleMapsClient, sms: ClientTwilio) -> Self {
-        let geocoder: Box<dyn Geocoder> = Box::new(GeocoderGoogle::new(maps.clone()));
-        let messanger: Box<dyn Messanger> = Box::new(MessangerRedis::new());
+    #[doc = "The market for the server, fails if the messanger or web push can not be set up from the config"]
+    pub fn new(db: Addr<DBActor>, store: Box<dyn CacheStore>, maps: GoogleMapsClient, sms: ClientTwilio, config: MarketConfig) -> MarketResult<Self> {
+        let google: Box<dyn Geocoder> = Box::new(GeocoderGoogle::new(maps.clone()));
+        let geocoder: Box<dyn Geocoder> = Box::new(GeocoderResilient::new(google, ResilienceConfig::default()));
+        let messanger: Box<dyn Messanger> = match config.messanger {
+            MessangerBackend::Redis => Box::new(MessangerRedis::new(&config.redis_url)?),
+            MessangerBackend::Memory => Box::new(MessangerMemory::new()),
+        };
+        let web_push: Option<Box<dyn Pusher>> = match config.web_push_vapid_key.as_ref() {
+            Some(key) => {
+                let service = PushServiceVapid::new(key, &config.web_push_subject)?;
+                Some(Box::new(PusherWebPush::new(db.clone(), Box::new(service))))
+            },
+            None => None,
+        };
         let pushers = Pushers {
-            web: Box::new(PusherTwilio::new(sms.clone())),
+            db: db.clone(),
+            sms: Box::new(PusherTwilio::new(sms.clone())),
+            web_push,
             app: Box::new(PusherMock::new()),
             mock: Box::new(PusherMock::new()),
         };
-        Market::make(geocoder, messanger, db, kv, sms, false, pushers)
+        Ok(Market::make(geocoder, messanger, db, store, sms, false, pushers, Box::new(ClockSystem::new()), config))
+    }
+
+    #[doc = "A mock market where the geocoder uses the scenario from `MOCK_SCENARIO`, if there is one"]
+    pub fn mock(db: Addr<DBActor>) -> MarketResult<Self> {
+        Ok(Market::mock_with_scenario(db, MockScenario::from_env()?))
+    }
+
+    #[doc = "A mock market where the geocoder uses the locations and legs of the scenario"]
+    pub fn mock_with_scenario(db: Addr<DBActor>, scenario: MockScenario) -> Self {
+        Market::mock_with(db, MarketMockConfig { scenario, ..Default::default() })
     }
 
-    pub fn mock(db: Addr<DBActor>, kv: Store) -> Self {
-        let geocoder: Box<dyn Geocoder> = Box::new(GeocoderMock::new());
+    pub fn mock_with(db: Addr<DBActor>, config: MarketMockConfig) -> Self {
+        let geocoder: Box<dyn Geocoder> = Box::new(GeocoderMock::with_scenario(config.scenario));
         let sms = ClientTwilio::new("", "");
-        let messanger: Box<dyn Messanger> = Box::new(MessangerMock::new());
         let pushers = Pushers {
-            web: Box::new(PusherMock::new()),
+            db: db.clone(),
+            sms: Box::new(PusherMock::new()),
+            web_push: Some(Box::new(PusherWebPush::new(db.clone(), config.push_service))),
             app: Box::new(PusherMock::new()),
             mock: Box::new(PusherMock::new()),
         };
-        Market::make(geocoder, messanger, db, kv, sms, true, pushers)
+        Market::make(geocoder, config.messanger, db, config.store, sms, true, pushers, config.clock, config.market)
     }
 
-    fn make(geocoder: Box<dyn Geocoder>, messanger: Box<dyn Messanger>, db: Addr<DBActor>, kv: Store, sms: ClientTwilio, is_mock: bool, pushers: Pushers) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn make(geocoder: Box<dyn Geocoder>, messanger: Box<dyn Messanger>, db: Addr<DBActor>, store: Box<dyn CacheStore>, sms: ClientTwilio, is_mock: bool, pushers: Pushers, clock: Box<dyn Clock>, config: MarketConfig) -> Self {
         let vehicle = MarketVehicle::new(db.clone());
-        let event = MarketEvent::new(db.clone(), geocoder.box_clone(), messanger.box_clone(), kv.clone(), vehicle.clone());
+        let event = MarketEvent::new(db.clone(), geocoder.box_clone(), messanger.box_clone(), store.box_clone(), vehicle.clone(), clock.box_clone());
+        let notification = MarketNotification::new(db.clone(), pushers, vehicle.clone(), clock.box_clone());
         Self {
-            driver: MarketDriver::new(db.clone(), kv.clone(), messanger.box_clone(), event.clone(), pushers),
+            driver: MarketDriver::new(db.clone(), messanger.box_clone(), event.clone(), notification.clone(), clock.box_clone(), config),
             event: event.clone(),
             vehicle,
-            reservation: MarketReservation::new(db.clone(), geocoder, messanger.box_clone(), event),
+            reservation: MarketReservation::new(db.clone(), geocoder, messanger.box_clone(), event, notification.clone(), clock.box_clone()),
+            notification,
+            clock,
             messanger,
-            kv,
+            store,
             db,
             is_mock,
             sms,
         }
     }
 
-    pub fn clear_cache(&self) -> Result<(), ErrorMarket> {
-        self.event.clear_cache()?;
+    pub async fn clear_cache(&self) -> Result<(), ErrorMarket> {
+        self.event.clear_cache().await?;
         Ok(())
     }
 }
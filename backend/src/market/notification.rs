@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use actix::Addr;
+use chrono::Duration;
+use log::warn;
+use uuid::Uuid;
+
+use crate::{db_util::DBActor, graphql::{reservations::{Reservation, messages::{ReservationGet, ReservationsList}}, users::{User, messages::UserGet}, events::{Event, messages::EventGet}, orgs::{model::Organization, messages::OrganizationGet}, drivers::{Driver, messages::EventDriverGet}, notifications::{messages::{NotificationInsert, NotificationsClaimDue, NotificationMarkDone, NotificationMarkFailed, NotificationsForReservation, NotificationTemplateGet}, model::{Notification, NotificationKind, NotificationStatus, DBNotificationInsertable}}}, market::strategy::model::IdEventDriver};
+
+use super::{types::MarketResult, error::ErrorMarket, pusher::{Pushers, Pusher, PushMessage}, clock::Clock, vehicle::MarketVehicle, template};
+
+#[doc = "A notification is given up on after this many failed attempts"]
+const MAX_ATTEMPTS: i32 = 5;
+#[doc = "How long to wait before the first retry, it doubles with every failure"]
+const RETRY_BASE_SECONDS: i32 = 30;
+const RETRY_MAX_SECONDS: i32 = 30 * 60;
+#[doc = "How long a worker has to deliver the notifications it took before another worker can take them"]
+const CLAIM_LEASE_SECONDS: i32 = 2 * 60;
+const DELIVER_BATCH: i64 = 50;
+#[doc = "Announcements are kept short enough for a text"]
+const MAX_ANNOUNCEMENT_LENGTH: usize = 200;
+
+#[doc = "What happened to the notifications in one run of the outbox"]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OutboxReport {
+    pub sent: usize,
+    pub skipped: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+#[doc = "Notifications for riders go through an outbox so a failed push is tried again instead of lost"]
+pub struct MarketNotification {
+    db: Addr<DBActor>,
+    push: Pushers,
+    vehicle: MarketVehicle,
+    clock: Box<dyn Clock>,
+}
+
+impl Clone for MarketNotification {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            push: self.push.clone(),
+            vehicle: self.vehicle.clone(),
+            clock: self.clock.box_clone(),
+        }
+    }
+}
+
+impl MarketNotification {
+    pub fn new(db: Addr<DBActor>, push: Pushers, vehicle: MarketVehicle, clock: Box<dyn Clock>) -> Self {
+        Self { db, push, vehicle, clock }
+    }
+
+    pub async fn send_driver_accepted(&self, reservation: &Reservation, id_driver: &IdEventDriver) -> MarketResult<()> {
+        let dedupe_key = format!("driver_accepted:{}:{id_driver}", reservation.id);
+        self.enqueue(reservation, NotificationKind::DriverAccepted, dedupe_key, Some(id_driver), None, None).await
+    }
+
+    pub async fn send_driver_approaching(&self, reservation: &Reservation, id_driver: &IdEventDriver, eta: Duration) -> MarketResult<()> {
+        let dedupe_key = format!("driver_approaching:{}:{id_driver}", reservation.id);
+        self.enqueue(reservation, NotificationKind::DriverApproaching, dedupe_key, Some(id_driver), Some(eta), None).await
+    }
+
+    #[doc = "`is_dropoff` is if the driver arrived to drop the rider off instead of to pick them up"]
+    pub async fn send_driver_arrival(&self, reservation: &Reservation, id_driver: &IdEventDriver, is_dropoff: bool) -> MarketResult<()> {
+        let stage = if is_dropoff { "dropoff" } else { "pickup" };
+        let dedupe_key = format!("driver_arrived:{}:{id_driver}:{stage}", reservation.id);
+        self.enqueue(reservation, NotificationKind::DriverArrived, dedupe_key, Some(id_driver), None, None).await
+    }
+
+    pub async fn send_reservation_cancelled(&self, reservation: &Reservation) -> MarketResult<()> {
+        let dedupe_key = format!("reservation_cancelled:{}", reservation.id);
+        self.enqueue(reservation, NotificationKind::ReservationCancelled, dedupe_key, None, None, None).await
+    }
+
+    #[doc = "Tell every rider with a ride still to come at an event something from the org, returns how many were told"]
+    pub async fn send_event_announcement(&self, id_event: &Uuid, announcement: &str) -> MarketResult<usize> {
+        let announcement = announcement.trim();
+        if announcement.is_empty() || announcement.chars().count() > MAX_ANNOUNCEMENT_LENGTH {
+            return Err(ErrorMarket::BadValue(format!("Announcements must be between 1 and {MAX_ANNOUNCEMENT_LENGTH} characters")))
+        }
+        let id_announcement = Uuid::new_v4();
+        let reservations = self.db.send(ReservationsList { id_event: *id_event }).await??.into_iter()
+            .map(Reservation::from)
+            .filter(|reservation| !reservation.is_cancelled && !reservation.is_complete);
+
+        let mut count = 0;
+        for reservation in reservations {
+            let dedupe_key = format!("event_announcement:{}:{id_announcement}", reservation.id);
+            self.enqueue(&reservation, NotificationKind::EventAnnouncement, dedupe_key, None, None, Some(announcement)).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    #[doc = "Write the notification from the org's template for the kind, or ours, in the rider's language. It's done when it is delivered so it has the driver's vehicle and the org's wording as they are then"]
+    async fn render(&self, notification: &Notification, reservation: &Reservation, rider: &User) -> MarketResult<PushMessage> {
+        let locale = rider.locale;
+        let event: Event = self.db.send(EventGet { id: reservation.id_event }).await??.into();
+        let org: Organization = self.db.send(OrganizationGet { id: event.id_org }).await??.into();
+
+        let mut values: HashMap<&str, String> = HashMap::new();
+        values.insert("rider_name", rider.name.clone());
+        values.insert("passenger_count", reservation.passenger_count.to_string());
+        values.insert("event_name", event.name);
+        values.insert("org_name", org.label);
+        let mut has_vehicle = false;
+        if let Some(id_driver) = notification.id_driver {
+            let driver: Driver = self.db.send(EventDriverGet { id: id_driver }).await??.into();
+            let driver_name = self.db.send(UserGet { phone: driver.phone.clone() }).await?.ok().map(|user| user.name);
+            values.insert("driver_name", driver_name.unwrap_or_default());
+            if let Some(id_vehicle) = driver.id_vehicle {
+                let vehicle = self.vehicle.get(&id_vehicle).await?;
+                has_vehicle = true;
+                values.insert("vehicle", template::vehicle(locale, &vehicle));
+                values.insert("vehicle_color", vehicle.color);
+                values.insert("vehicle_make", vehicle.make);
+                values.insert("vehicle_model", vehicle.model);
+                values.insert("vehicle_license", vehicle.license);
+            }
+        }
+        if let Some(eta) = notification.eta {
+            values.insert("eta", template::eta(locale, Duration::seconds(eta as i64)));
+        }
+        if let Some(announcement) = &notification.announcement {
+            values.insert("announcement", announcement.clone());
+        }
+
+        let custom = self.db.send(NotificationTemplateGet { id_org: event.id_org, kind: notification.kind, locale }).await??;
+        // The org's wording would have gaps where the vehicle goes for a driver without one, so ours is used instead
+        let body = match &custom {
+            Some(custom) if has_vehicle || !template::uses_vehicle(&custom.body) => custom.body.clone(),
+            _ if has_vehicle => template::default_template(notification.kind, locale).to_owned(),
+            _ => template::default_template_without_vehicle(notification.kind, locale).to_owned(),
+        };
+        let title = custom.and_then(|custom| custom.title).unwrap_or_else(|| template::DEFAULT_TITLE.to_owned());
+        Ok(PushMessage { title: template::render(&title, &values), body: template::render(&body, &values) })
+    }
+
+    #[doc = "Add a notification for the rider, nothing happens if one with the same dedupe key was already added"]
+    async fn enqueue(&self, reservation: &Reservation, kind: NotificationKind, dedupe_key: String, id_driver: Option<&IdEventDriver>, eta: Option<Duration>, announcement: Option<&str>) -> MarketResult<()> {
+        let now = self.clock.now();
+        self.db.send(NotificationInsert {
+            notification: DBNotificationInsertable {
+                id_reservation: reservation.id,
+                phone: reservation.reserver.clone(),
+                kind: kind.as_str().to_owned(),
+                dedupe_key,
+                created_at: now,
+                next_attempt_at: now,
+                id_driver: id_driver.copied(),
+                eta: eta.map(|eta| eta.num_seconds() as i32),
+                announcement: announcement.map(str::to_owned),
+            },
+        }).await??;
+        Ok(())
+    }
+
+    #[doc = "Send the notifications that are due, failures are tried again later with a backoff"]
+    pub async fn deliver_due(&self) -> MarketResult<OutboxReport> {
+        self.deliver_claimed(None).await
+    }
+
+    #[doc = "Send the notifications of a reservation that are due, the others are left for the outbox"]
+    pub async fn deliver_due_for_reservation(&self, id_reservation: &Uuid) -> MarketResult<OutboxReport> {
+        self.deliver_claimed(Some(*id_reservation)).await
+    }
+
+    async fn deliver_claimed(&self, id_reservation: Option<Uuid>) -> MarketResult<OutboxReport> {
+        let now = self.clock.now();
+        let rows = self.db.send(NotificationsClaimDue {
+            now,
+            lease_until: now + CLAIM_LEASE_SECONDS,
+            limit: DELIVER_BATCH,
+            id_reservation,
+        }).await??;
+
+        let mut report = OutboxReport::default();
+        let mut due = Vec::new();
+        for row in rows {
+            let id = row.id;
+            match Notification::try_from(row) {
+                Ok(notification) => due.push(notification),
+                // It can't be sent, so it's given up on instead of claimed again on every run
+                Err(err) => {
+                    warn!("Could not read notification {id}, got error: {err}");
+                    report.failed += 1;
+                    self.mark_failed(id, err.to_string(), None).await;
+                },
+            }
+        }
+        for notification in due {
+            match self.deliver(&notification).await {
+                Ok((status, message)) => {
+                    self.mark_done(notification.id, status, message).await;
+                    if status == NotificationStatus::Sent { report.sent += 1 } else { report.skipped += 1 }
+                },
+                Err(err) => {
+                    let attempts = notification.attempts + 1;
+                    let retry_at = (attempts < MAX_ATTEMPTS).then(|| self.clock.now() + retry_delay(attempts));
+                    warn!("Could not send notification {} (attempt {attempts}), got error: {err}", notification.id);
+                    if retry_at.is_some() { report.retrying += 1 } else { report.failed += 1 }
+                    self.mark_failed(notification.id, err.to_string(), retry_at).await;
+                },
+            }
+        }
+        Ok(report)
+    }
+
+    #[doc = "Record how sending went. A failure is only logged so the rest of the batch still goes out, the notification is claimed again once its lease runs out"]
+    async fn mark_done(&self, id: i32, status: NotificationStatus, message: String) {
+        match self.db.send(NotificationMarkDone { id, status, message, at: self.clock.now() }).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => warn!("Could not record that notification {id} went through, got error: {e}"),
+            Err(e) => warn!("Could not record that notification {id} went through, got error: {e}"),
+        }
+    }
+
+    #[doc = "Record a failed attempt, like `mark_done` a failure to record it is only logged"]
+    async fn mark_failed(&self, id: i32, error: String, retry_at: Option<i32>) {
+        match self.db.send(NotificationMarkFailed { id, error, retry_at }).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => warn!("Could not record the failed attempt of notification {id}, got error: {e}"),
+            Err(e) => warn!("Could not record the failed attempt of notification {id}, got error: {e}"),
+        }
+    }
+
+    #[doc = "Send a notification on every channel the rider can be reached on, it is sent if any of them worked. Returns what it said"]
+    async fn deliver(&self, notification: &Notification) -> MarketResult<(NotificationStatus, String)> {
+        let reservation: Reservation = self.db.send(ReservationGet { id: notification.id_reservation }).await??.into();
+        let user: User = self.db.send(UserGet { phone: notification.phone.clone() }).await??.into();
+        let message = self.render(notification, &reservation, &user).await?;
+        let pushers = self.push.get(&reservation, &user, notification.kind).await?;
+        if pushers.is_empty() { return Ok((NotificationStatus::Skipped, message.body)) }
+
+        match self.push_all(pushers, &reservation, &message, &user).await {
+            // The expired browsers were removed, so the channels are picked again and the rider can get a text instead
+            Err(ErrorMarket::WebPushGone) => {
+                let pushers = self.push.get(&reservation, &user, notification.kind).await?;
+                if pushers.is_empty() { return Ok((NotificationStatus::Skipped, message.body)) }
+                self.push_all(pushers, &reservation, &message, &user).await?;
+            },
+            result => result?,
+        }
+        Ok((NotificationStatus::Sent, message.body))
+    }
+
+    async fn push_all(&self, pushers: Vec<Box<dyn Pusher>>, reservation: &Reservation, message: &PushMessage, user: &User) -> MarketResult<()> {
+        let mut error = None;
+        let mut is_sent = false;
+        for pusher in pushers {
+            match pusher.push(reservation, message, user).await {
+                Ok(()) => is_sent = true,
+                Err(err) => error = Some(err),
+            }
+        }
+        match error {
+            Some(err) if !is_sent => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    #[doc = "Every notification for a reservation, oldest first"]
+    pub async fn list_for_reservation(&self, id_reservation: &Uuid) -> MarketResult<Vec<Notification>> {
+        let rows = self.db.send(NotificationsForReservation { id_reservation: *id_reservation }).await??;
+        rows.into_iter().map(Notification::try_from).collect()
+    }
+}
+
+#[doc = "How many seconds to wait after the given number of failed attempts"]
+fn retry_delay(attempts: i32) -> i32 {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    (RETRY_BASE_SECONDS * 2_i32.pow(exponent)).min(RETRY_MAX_SECONDS)
+}
@@ -1,6 +1,8 @@
-use crate::{market::types::MarketResult, graphql::{reservations::Reservation, users::User}};
+use std::{sync::{Arc, Mutex}, collections::HashSet};
 
-use super::Pusher;
+use crate::{market::types::MarketResult, graphql::{reservations::Reservation, users::User, push_subscriptions::model::DBPushSubscription}};
+
+use super::{Pusher, PushMessage, webpush::{PushService, PushOutcome}};
 use async_trait::async_trait;
 use log::debug;
 
@@ -20,11 +22,45 @@ impl Pusher for PusherMock {
         Box::new(self.clone())
     }
 
-    async fn push(&self, reservation: &Reservation, message: &str, user: &User) -> MarketResult<()> {
+    async fn push(&self, reservation: &Reservation, message: &PushMessage, user: &User) -> MarketResult<()> {
         debug!("MOCK PUSHER: {}: {:?}", reservation.reserver, message);
         Ok(())
     }
 }
 
+#[doc = "A push service that keeps what it was sent instead of sending it"]
+#[derive(Debug, Clone, Default)]
+pub struct PushServiceMock {
+    sent: Arc<Mutex<Vec<(String, String)>>>,
+    gone: Arc<Mutex<HashSet<String>>>,
+}
+
+impl PushServiceMock {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
+    #[doc = "Act like the subscription for this endpoint has expired"]
+    pub fn expire(&self, endpoint: &str) {
+        self.gone.lock().unwrap().insert(endpoint.to_owned());
+    }
 
+    #[doc = "The endpoints and payloads that were sent, oldest first"]
+    pub fn sent(&self) -> Vec<(String, String)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl PushService for PushServiceMock {
+    fn box_clone(&self) -> Box<dyn PushService> {
+        Box::new(self.clone())
+    }
+
+    async fn send(&self, subscription: &DBPushSubscription, payload: &str) -> MarketResult<PushOutcome> {
+        if self.gone.lock().unwrap().contains(&subscription.endpoint) { return Ok(PushOutcome::Gone) }
+        debug!("MOCK PUSH SERVICE: {}: {payload}", subscription.endpoint);
+        self.sent.lock().unwrap().push((subscription.endpoint.clone(), payload.to_owned()));
+        Ok(PushOutcome::Delivered)
+    }
+}
@@ -1,51 +1,72 @@
+use actix::Addr;
 use async_trait::async_trait;
 
-use crate::graphql::{reservations::Reservation, users::User};
+use crate::{db_util::DBActor, graphql::{reservations::Reservation, users::User, events::{Event, messages::EventGet}, push_subscriptions::messages::PushSubscriptionsForUser, notifications::{messages::{NotificationPreferencesForUser, NotificationMuteGet}, model::{NotificationKind, NotificationChannel, NotificationPreferences}}}};
 use super::types::MarketResult;
 
 pub mod twilio;
 pub mod mock;
+pub mod webpush;
 
 
 
 
+#[doc = "A notification written out for the rider, `title` is who it is from"]
+#[derive(Debug, Clone)]
+pub struct PushMessage {
+    pub title: String,
+    pub body: String,
+}
+
 #[async_trait]
 pub trait Pusher: Send + Sync {
     fn box_clone(&self) -> Box<dyn Pusher>;
 
-    async fn push(&self, reservation: &Reservation, message: &str, user: &User) -> MarketResult<()>;
-}
-
-impl dyn Pusher {
-    pub async fn send_driver_arrival(&self, reservation: &Reservation, user: &User) -> MarketResult<()> {
-        self.push(reservation, "Your driver has arrived!", user).await
-    }
-
-    pub async fn send_driver_accepted(&self, reservation: &Reservation, user: &User) -> MarketResult<()> {
-        self.push(reservation, "Your driver is on the way!", user).await
-    }
+    async fn push(&self, reservation: &Reservation, message: &PushMessage, user: &User) -> MarketResult<()>;
 }
 
 pub struct Pushers {
+    pub db: Addr<DBActor>,
     pub app: Box<dyn Pusher>,
-    pub web: Box<dyn Pusher>,
+    pub sms: Box<dyn Pusher>,
+    #[doc = "None when the server has no VAPID key to send web pushes with"]
+    pub web_push: Option<Box<dyn Pusher>>,
     pub mock: Box<dyn Pusher>,
 }
 
 impl Clone for Pushers {
     fn clone(&self) -> Self {
         Self {
+            db: self.db.clone(),
             app: self.app.box_clone(),
-            web: self.web.box_clone(),
+            sms: self.sms.box_clone(),
+            web_push: self.web_push.as_ref().map(|pusher| pusher.box_clone()),
             mock: self.mock.box_clone(),
         }
     }
 }
 
 impl Pushers {
-    pub fn get(&self, reservation: &Reservation) -> Box<dyn Pusher> {
-        if reservation.reserver.is_mock() { return self.mock.box_clone() }
-        self.web.box_clone()
+    #[doc = "The channels to send a kind of notification to a rider on, from their preferences, browsers and SMS opt in. None when the org muted the kind"]
+    pub async fn get(&self, reservation: &Reservation, user: &User, kind: NotificationKind) -> MarketResult<Vec<Box<dyn Pusher>>> {
+        let event: Event = self.db.send(EventGet { id: reservation.id_event }).await??.into();
+        if self.db.send(NotificationMuteGet { id_org: event.id_org, kind }).await??.is_some() { return Ok(Vec::new()) }
+
+        let preferences = NotificationPreferences::new(self.db.send(NotificationPreferencesForUser { phone: user.phone.clone() }).await??);
+        let mut pushers = Vec::new();
+
+        if let Some(web_push) = &self.web_push {
+            if preferences.chosen(NotificationChannel::WebPush, kind).unwrap_or(true) {
+                let subscriptions = self.db.send(PushSubscriptionsForUser { phone: user.phone.clone() }).await??;
+                if !subscriptions.is_empty() { pushers.push(web_push.box_clone()) }
+            }
+        }
+
+        // Texts always need the opt in, and unless the user chose otherwise they are only sent when there is no browser
+        let is_sms = user.is_opted_in_sms == Some(true) && preferences.chosen(NotificationChannel::Sms, kind).unwrap_or(pushers.is_empty());
+        if is_sms {
+            pushers.push(if reservation.reserver.is_mock() { self.mock.box_clone() } else { self.sms.box_clone() });
+        }
+        Ok(pushers)
     }
 }
-
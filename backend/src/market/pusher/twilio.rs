@@ -2,7 +2,7 @@ use async_trait::async_trait;
 
 use crate::{market::types::MarketResult, graphql::{reservations::Reservation, users::User}, sms::ClientTwilio};
 
-use super::Pusher;
+use super::{Pusher, PushMessage};
 
 #[derive(Debug, Clone)]
 pub struct PusherTwilio {
@@ -21,14 +21,9 @@ impl Pusher for PusherTwilio {
         Box::new(self.clone())
     }
 
-    async fn push(&self, reservation: &Reservation, message: &str, user: &User) -> MarketResult<()> {
-        match user.is_opted_in_sms {
-            Some(true) => {
-                self.client.post_message(&reservation.reserver, &format!("Elytra Rides: {message}")).await?;
-                Ok(())
-            }
-            _ => Ok(())
-        }
+    async fn push(&self, reservation: &Reservation, message: &PushMessage, _user: &User) -> MarketResult<()> {
+        self.client.post_message(&reservation.reserver, &format!("{}: {}", message.title, message.body)).await?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use actix::Addr;
+use async_trait::async_trait;
+use log::{warn, debug};
+use serde_json::json;
+use web_push::{WebPushClient, IsahcWebPushClient, SubscriptionInfo, VapidSignatureBuilder, PartialVapidSignatureBuilder, WebPushMessageBuilder, ContentEncoding, WebPushError};
+
+use crate::{db_util::DBActor, market::{types::MarketResult, error::ErrorMarket}, graphql::{reservations::Reservation, users::User, push_subscriptions::{messages::{PushSubscriptionsForUser, PushSubscriptionDeleteEndpoint}, model::DBPushSubscription}}};
+
+use super::{Pusher, PushMessage};
+
+#[doc = "How long the push service holds a notification for a device that is offline, in seconds"]
+const PUSH_TTL: u32 = 60 * 60;
+
+#[doc = "What the push service did with a notification"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Delivered,
+    #[doc = "The subscription expired or was revoked, it will never work again"]
+    Gone,
+}
+
+#[doc = "Sends an encrypted payload to a browser's push service"]
+#[async_trait]
+pub trait PushService: Send + Sync + std::fmt::Debug {
+    fn box_clone(&self) -> Box<dyn PushService>;
+
+    async fn send(&self, subscription: &DBPushSubscription, payload: &str) -> MarketResult<PushOutcome>;
+}
+
+#[doc = "The real push services, messages are signed with the server's VAPID key"]
+#[derive(Clone)]
+pub struct PushServiceVapid {
+    client: Arc<IsahcWebPushClient>,
+    signer: PartialVapidSignatureBuilder,
+    subject: String,
+}
+
+impl std::fmt::Debug for PushServiceVapid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PushServiceVapid").field("subject", &self.subject).finish_non_exhaustive()
+    }
+}
+
+impl PushServiceVapid {
+    #[doc = "`private_key` is the url safe base64 VAPID private key, `subject` a mailto: or https: contact for the push services"]
+    pub fn new(private_key: &str, subject: &str) -> MarketResult<Self> {
+        let signer = VapidSignatureBuilder::from_base64_no_sub(private_key)
+            .map_err(|err| ErrorMarket::BadValue(format!("Invalid VAPID private key, {err}")))?;
+        let client = IsahcWebPushClient::new()
+            .map_err(|err| ErrorMarket::BadValue(format!("Could not create the web push client, {err}")))?;
+        Ok(Self { client: Arc::new(client), signer, subject: subject.to_owned() })
+    }
+}
+
+#[async_trait]
+impl PushService for PushServiceVapid {
+    fn box_clone(&self) -> Box<dyn PushService> {
+        Box::new(self.clone())
+    }
+
+    async fn send(&self, subscription: &DBPushSubscription, payload: &str) -> MarketResult<PushOutcome> {
+        let info = SubscriptionInfo::new(&subscription.endpoint, &subscription.p256dh, &subscription.auth);
+
+        let mut signer = self.signer.clone().add_sub_info(&info);
+        signer.add_claim("sub", self.subject.as_str());
+        let signature = signer.build().map_err(|err| {
+            warn!("Could not sign web push for subscription {}, got error: {err}", subscription.id);
+            ErrorMarket::WebPushError
+        })?;
+
+        let mut builder = WebPushMessageBuilder::new(&info);
+        builder.set_payload(ContentEncoding::Aes128Gcm, payload.as_bytes());
+        builder.set_vapid_signature(signature);
+        builder.set_ttl(PUSH_TTL);
+        let message = builder.build().map_err(|err| {
+            warn!("Could not build web push for subscription {}, got error: {err}", subscription.id);
+            ErrorMarket::WebPushError
+        })?;
+
+        match self.client.send(message).await {
+            Ok(()) => Ok(PushOutcome::Delivered),
+            Err(WebPushError::EndpointNotValid { .. } | WebPushError::EndpointNotFound { .. }) => Ok(PushOutcome::Gone),
+            Err(err) => {
+                warn!("Web push to subscription {} failed, got error: {err}", subscription.id);
+                Err(ErrorMarket::WebPushError)
+            },
+        }
+    }
+}
+
+#[doc = "Notifies riders on every browser they subscribed from"]
+pub struct PusherWebPush {
+    db: Addr<DBActor>,
+    service: Box<dyn PushService>,
+}
+
+impl Clone for PusherWebPush {
+    fn clone(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            service: self.service.box_clone(),
+        }
+    }
+}
+
+impl PusherWebPush {
+    pub fn new(db: Addr<DBActor>, service: Box<dyn PushService>) -> Self {
+        Self { db, service }
+    }
+}
+
+#[async_trait]
+impl Pusher for PusherWebPush {
+    fn box_clone(&self) -> Box<dyn Pusher> {
+        Box::new(self.clone())
+    }
+
+    async fn push(&self, reservation: &Reservation, message: &PushMessage, user: &User) -> MarketResult<()> {
+        let subscriptions = self.db.send(PushSubscriptionsForUser { phone: user.phone.clone() }).await??;
+        let payload = json!({
+            "title": message.title,
+            "body": message.body,
+            "id_reservation": reservation.id,
+        }).to_string();
+
+        let mut delivered = 0;
+        let mut error = None;
+        for subscription in &subscriptions {
+            match self.service.send(subscription, &payload).await {
+                Ok(PushOutcome::Delivered) => delivered += 1,
+                Ok(PushOutcome::Gone) => {
+                    debug!("Removing expired web push subscription {}", subscription.id);
+                    self.db.send(PushSubscriptionDeleteEndpoint { endpoint: subscription.endpoint.clone() }).await??;
+                },
+                Err(err) => error = Some(err),
+            }
+        }
+
+        // One browser getting it is enough, retrying would send it again to the ones that did
+        match error {
+            _ if delivered > 0 => Ok(()),
+            Some(err) => Err(err),
+            None => Err(ErrorMarket::WebPushGone),
+        }
+    }
+}
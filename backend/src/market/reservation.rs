@@ -1,15 +1,20 @@
 use actix::Addr;
+use log::warn;
 use uuid::Uuid;
 
 use crate::{db_util::DBActor, graphql::reservations::{Reservation, messages::{ReservationReserve, ReservationCancel, ReservationGet}, FormReservation}, types::phone::Phone};
 
-use super::{types::{MarketResult, ReservationEstimate}, event::MarketEvent, geocoder::Geocoder, messanger::Messanger, strategy::driver::{model::DriverStrategy, stop::model::DriverStop}, error::ErrorMarket};
+use super::{types::{MarketResult, ReservationEstimate}, event::MarketEvent, geocoder::Geocoder, messanger::Messanger, strategy::driver::{model::DriverStrategy, stop::model::DriverStop}, error::ErrorMarket, clock::Clock, messages::MessageMarket, notification::MarketNotification};
+
+const MAX_RIDER_MESSAGE_LENGTH: usize = 500;
 
 pub struct MarketReservation {
     db: Addr<DBActor>,
     geocoder: Box<dyn Geocoder>,
     messanger: Box<dyn Messanger>,
     event: MarketEvent,
+    notification: MarketNotification,
+    clock: Box<dyn Clock>,
 }
 
 impl Clone for MarketReservation {
@@ -19,17 +24,21 @@ impl Clone for MarketReservation {
             geocoder: self.geocoder.box_clone(),
             messanger: self.messanger.box_clone(),
             event: self.event.clone(),
+            notification: self.notification.clone(),
+            clock: self.clock.box_clone(),
         }
     }
 }
 
 impl MarketReservation {
-    pub fn new(db: Addr<DBActor>, geocoder: Box<dyn Geocoder>, messanger: Box<dyn Messanger>, event: MarketEvent) -> Self {
+    pub fn new(db: Addr<DBActor>, geocoder: Box<dyn Geocoder>, messanger: Box<dyn Messanger>, event: MarketEvent, notification: MarketNotification, clock: Box<dyn Clock>) -> Self {
         Self {
             db,
             geocoder,
             messanger,
             event,
+            notification,
+            clock,
         }
     }
 
@@ -50,15 +59,20 @@ impl MarketReservation {
             form: form_geocoded,
             est_pickup,
             est_dropoff,
+            made_at: self.clock.now(),
         }).await??.into();
         self.messanger.send_reservation_update(result.clone()).await?;
+        self.messanger.send_driver_offer(result.clone()).await?;
+        self.event.publish_pool(id_event).await;
+        self.event.publish_summary(id_event).await;
         Ok(result)
     }
 
     pub async fn cancel(&self, id: &Uuid) -> MarketResult<Reservation> {
-        let reservation: Reservation = self.db.send(ReservationCancel { id: id.to_owned() }).await??.into();
+        let reservation: Reservation = self.db.send(ReservationCancel { id: id.to_owned(), cancelled_at: self.clock.now() }).await??.into();
         self.messanger.send_reservation_update(reservation.clone()).await?;
         if let Some(id_driver) = reservation.id_driver {
+            self.messanger.send_reservation_cancelled(&id_driver, reservation.clone()).await?;
             let id_reservation = id.clone();
             self.event.update_driver_strategy(&reservation.id_event, &id_driver, Box::new(move |mut driver: DriverStrategy| {
                 if driver.picked_up.contains_key(&id_reservation) { return Err(ErrorMarket::ReservationIsPickedUp)}
@@ -86,9 +100,50 @@ impl MarketReservation {
                 Ok(driver)
             })).await?;
         }
+        if let Err(e) = self.notification.send_reservation_cancelled(&reservation).await {
+            warn!("Could not tell rider of reservation {id} that it was cancelled, {e:?}");
+        }
+        self.event.publish_pool(&reservation.id_event).await;
+        self.event.publish_summary(&reservation.id_event).await;
         Ok(reservation)
     }
 
+    #[doc = "Send a message from the rider to the driver assigned to their reservation"]
+    pub async fn message_driver(&self, id: &Uuid, message: String) -> MarketResult<()> {
+        let message = message.trim().to_string();
+        if message.is_empty() || message.chars().count() > MAX_RIDER_MESSAGE_LENGTH {
+            return Err(ErrorMarket::BadValue(format!("Messages must be between 1 and {MAX_RIDER_MESSAGE_LENGTH} characters")));
+        }
+        let reservation = self.get(id).await?;
+        let id_driver = reservation.id_driver.ok_or(ErrorMarket::NotAccepted)?;
+        self.messanger.send_rider_message(&id_driver, id, message, self.clock.now()).await
+    }
+
+    #[doc = "What a rider subscribing to their reservation is sent before any live messages, the reservation, its estimate and where their driver is"]
+    pub async fn get_snapshot(&self, id: &Uuid) -> MarketResult<Vec<MessageMarket>> {
+        let reservation = self.get(id).await?;
+        let mut messages = vec![MessageMarket::new_reservation_update(reservation.clone())];
+        if reservation.is_cancelled || reservation.is_complete { return Ok(messages) }
+
+        // There may be no estimate yet, like when the event has no drivers
+        if let Ok(estimate) = self.estimate(&reservation).await {
+            messages.push(MessageMarket::from_reservation_estimate(estimate));
+        }
+
+        if let Some(id_driver) = reservation.id_driver {
+            let strategy = self.event.get_estimates(&reservation.id_event).await?;
+            let is_sharing = strategy.driver(&id_driver)
+                .map(|driver| driver.get_sharing_location_with().contains(id))
+                .unwrap_or(false);
+            if is_sharing {
+                if let Some(location) = self.event.get_driver_location(&id_driver).await? {
+                    messages.push(MessageMarket::new_driver_location(id_driver, location));
+                }
+            }
+        }
+        Ok(messages)
+    }
+
     pub async fn estimate(&self, reservation: &Reservation) -> MarketResult<ReservationEstimate> {
         self.event.get_estimate_reservation(reservation).await
     }
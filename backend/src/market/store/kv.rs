@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use kv::{Store, Bucket};
+
+use crate::market::types::MarketResult;
+
+use super::CacheStore;
+
+const BUCKET_META: &str = "cache_meta";
+const KEY_FORMAT: &str = "format";
+#[doc = "Bumped when how values are stored changes. Driver locations used to be keyed by binary integers, now every key is a string"]
+const FORMAT_VERSION: &str = "2";
+
+
+#[doc = "Keeps the cache in a sled database on the local disk, only one process can open it"]
+#[derive(Debug, Clone)]
+pub struct CacheStoreKv {
+    kv: Store,
+    #[doc = "Held while comparing and setting, every user of the database is in this process"]
+    lock: Arc<Mutex<()>>,
+}
+
+impl CacheStoreKv {
+    pub fn new(kv: Store) -> Self {
+        Self {
+            kv,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn open(path: &str) -> MarketResult<Self> {
+        let cfg = kv::Config::new(path);
+        let kv = Store::new(cfg)?;
+        let store = Self::new(kv);
+        store.migrate()?;
+        Ok(store)
+    }
+
+    #[doc = "Drop a cache written in an older format, it is rebuilt from the database as it is used"]
+    fn migrate(&self) -> MarketResult<()> {
+        let meta = self.bucket(BUCKET_META)?;
+        if meta.get(&KEY_FORMAT.to_owned())?.as_deref() == Some(FORMAT_VERSION) { return Ok(()) }
+        for name in self.kv.buckets() {
+            if name == BUCKET_META || name.starts_with("__sled__") { continue }
+            self.kv.drop_bucket(&name)?;
+        }
+        meta.set(&KEY_FORMAT.to_owned(), &FORMAT_VERSION.to_owned())?;
+        Ok(())
+    }
+
+    fn bucket(&self, bucket: &str) -> MarketResult<Bucket<String, String>> {
+        let bucket = self.kv.bucket(Some(bucket))?;
+        Ok(bucket)
+    }
+}
+
+#[async_trait]
+impl CacheStore for CacheStoreKv {
+    fn box_clone(&self) -> Box<dyn CacheStore> {
+        Box::new(self.clone())
+    }
+
+    async fn get(&self, bucket: &str, key: &str) -> MarketResult<Option<String>> {
+        let value = self.bucket(bucket)?.get(&key.to_owned())?;
+        Ok(value)
+    }
+
+    async fn set(&self, bucket: &str, key: &str, value: String) -> MarketResult<()> {
+        self.bucket(bucket)?.set(&key.to_owned(), &value)?;
+        Ok(())
+    }
+
+    async fn remove(&self, bucket: &str, key: &str) -> MarketResult<()> {
+        self.bucket(bucket)?.remove(&key.to_owned())?;
+        Ok(())
+    }
+
+    async fn keys(&self, bucket: &str) -> MarketResult<Vec<String>> {
+        let mut keys = Vec::new();
+        for item in self.bucket(bucket)?.iter() {
+            keys.push(item?.key()?);
+        }
+        Ok(keys)
+    }
+
+    async fn clear(&self, bucket: &str) -> MarketResult<()> {
+        self.kv.drop_bucket(bucket)?;
+        Ok(())
+    }
+
+    async fn compare_and_set(&self, bucket: &str, key: &str, expected: Option<&str>, value: String) -> MarketResult<bool> {
+        let _lock = self.lock.lock().unwrap();
+        let bucket = self.bucket(bucket)?;
+        if bucket.get(&key.to_owned())?.as_deref() != expected { return Ok(false) }
+        bucket.set(&key.to_owned(), &value)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use async_trait::async_trait;
+
+use crate::market::types::MarketResult;
+
+use super::CacheStore;
+
+
+#[doc = "Keeps the cache in memory, clones share the same data"]
+#[derive(Debug, Clone, Default)]
+pub struct CacheStoreMemory {
+    buckets: Arc<Mutex<HashMap<String, HashMap<String, String>>>>,
+}
+
+impl CacheStoreMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CacheStore for CacheStoreMemory {
+    fn box_clone(&self) -> Box<dyn CacheStore> {
+        Box::new(self.clone())
+    }
+
+    async fn get(&self, bucket: &str, key: &str) -> MarketResult<Option<String>> {
+        let buckets = self.buckets.lock().unwrap();
+        let value = buckets.get(bucket).and_then(|values| values.get(key).cloned());
+        Ok(value)
+    }
+
+    async fn set(&self, bucket: &str, key: &str, value: String) -> MarketResult<()> {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.entry(bucket.to_owned()).or_default().insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    async fn remove(&self, bucket: &str, key: &str) -> MarketResult<()> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(values) = buckets.get_mut(bucket) {
+            values.remove(key);
+        }
+        Ok(())
+    }
+
+    async fn keys(&self, bucket: &str) -> MarketResult<Vec<String>> {
+        let buckets = self.buckets.lock().unwrap();
+        let keys = buckets.get(bucket)
+            .map(|values| values.keys().cloned().collect())
+            .unwrap_or_default();
+        Ok(keys)
+    }
+
+    async fn clear(&self, bucket: &str) -> MarketResult<()> {
+        self.buckets.lock().unwrap().remove(bucket);
+        Ok(())
+    }
+
+    async fn compare_and_set(&self, bucket: &str, key: &str, expected: Option<&str>, value: String) -> MarketResult<bool> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let values = buckets.entry(bucket.to_owned()).or_default();
+        if values.get(key).map(String::as_str) != expected { return Ok(false) }
+        values.insert(key.to_owned(), value);
+        Ok(true)
+    }
+}
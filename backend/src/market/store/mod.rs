@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+
+use super::{types::MarketResult, error::ErrorMarket, config::MarketConfig};
+pub mod kv;
+pub mod memory;
+pub mod redis;
+
+use self::{kv::CacheStoreKv, memory::CacheStoreMemory, redis::CacheStoreRedis};
+
+
+#[doc = "Where the market keeps its cache, values are stored as strings in named buckets"]
+#[async_trait]
+pub trait CacheStore: Send + Sync + std::fmt::Debug {
+    fn box_clone(&self) -> Box<dyn CacheStore>;
+
+    async fn get(&self, bucket: &str, key: &str) -> MarketResult<Option<String>>;
+
+    async fn set(&self, bucket: &str, key: &str, value: String) -> MarketResult<()>;
+
+    async fn remove(&self, bucket: &str, key: &str) -> MarketResult<()>;
+
+    async fn keys(&self, bucket: &str) -> MarketResult<Vec<String>>;
+
+    #[doc = "Remove everything in a bucket"]
+    async fn clear(&self, bucket: &str) -> MarketResult<()>;
+
+    #[doc = "Set a value only if it is still `expected`, None meaning it is not set. Returns if it was set"]
+    async fn compare_and_set(&self, bucket: &str, key: &str, expected: Option<&str>, value: String) -> MarketResult<bool>;
+}
+
+impl dyn CacheStore {
+    #[doc = "Create the store named by `CACHE_STORE`, one of `kv` (the default), `redis` or `memory`. Redis is the market's `redis_url`"]
+    pub fn from_env(config: &MarketConfig) -> MarketResult<Box<dyn CacheStore>> {
+        let kind = std::env::var("CACHE_STORE").unwrap_or(String::from("kv"));
+        let store: Box<dyn CacheStore> = match kind.as_str() {
+            "kv" => {
+                let path = std::env::var("CACHE_KV_PATH").unwrap_or(String::from("./kv"));
+                Box::new(CacheStoreKv::open(&path)?)
+            },
+            "redis" => Box::new(CacheStoreRedis::new(&config.redis_url)?),
+            "memory" => Box::new(CacheStoreMemory::new()),
+            _ => return Err(ErrorMarket::BadValue(format!("Unknown cache store '{kind}'"))),
+        };
+        Ok(store)
+    }
+
+    pub async fn get_json<T: serde::de::DeserializeOwned>(&self, bucket: &str, key: &str) -> MarketResult<Option<T>> {
+        match self.get(bucket, key).await? {
+            Some(value) => {
+                let value = serde_json::from_str(&value)
+                    .map_err(|err| ErrorMarket::BadValue(format!("Invalid cache value for '{bucket}/{key}': {err}")))?;
+                Ok(Some(value))
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_json<T: serde::Serialize>(&self, bucket: &str, key: &str, value: &T) -> MarketResult<()> {
+        let value = to_json(bucket, key, value)?;
+        self.set(bucket, key, value).await
+    }
+
+    #[doc = "Get a value along with what is stored, to give to `compare_and_set_json` when writing it back"]
+    pub async fn get_json_versioned<T: serde::de::DeserializeOwned>(&self, bucket: &str, key: &str) -> MarketResult<Option<(T, String)>> {
+        match self.get(bucket, key).await? {
+            Some(raw) => {
+                let value = serde_json::from_str(&raw)
+                    .map_err(|err| ErrorMarket::BadValue(format!("Invalid cache value for '{bucket}/{key}': {err}")))?;
+                Ok(Some((value, raw)))
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub async fn compare_and_set_json<T: serde::Serialize>(&self, bucket: &str, key: &str, expected: Option<&str>, value: &T) -> MarketResult<bool> {
+        let value = to_json(bucket, key, value)?;
+        self.compare_and_set(bucket, key, expected, value).await
+    }
+}
+
+fn to_json<T: serde::Serialize>(bucket: &str, key: &str, value: &T) -> MarketResult<String> {
+    serde_json::to_string(value)
+        .map_err(|err| ErrorMarket::BadValue(format!("Could not serialize cache value for '{bucket}/{key}': {err}")))
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use redis::{AsyncCommands, Script, aio::ConnectionManager};
+use tokio::sync::OnceCell;
+
+use crate::market::types::MarketResult;
+
+use super::CacheStore;
+
+const KEY_PREFIX: &str = "cache";
+
+#[doc = "Sets a field of a hash only if it still has the value the caller read, a missing field is expected when ARGV[2] is 0"]
+const SCRIPT_COMPARE_AND_SET: &str = r"
+local current = redis.call('HGET', KEYS[1], ARGV[1])
+if ARGV[2] == '1' then
+    if current ~= ARGV[3] then return 0 end
+elseif current then
+    return 0
+end
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[4])
+return 1
+";
+
+
+#[doc = "Keeps the cache in Redis so it can be shared between instances, each bucket is a hash"]
+#[derive(Clone)]
+pub struct CacheStoreRedis {
+    client: redis::Client,
+    #[doc = "One multiplexed connection shared by every clone of the store, made on first use"]
+    conn: Arc<OnceCell<ConnectionManager>>,
+}
+
+impl CacheStoreRedis {
+    pub fn new(url: &str) -> MarketResult<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            client,
+            conn: Arc::new(OnceCell::new()),
+        })
+    }
+
+    async fn connection(&self) -> MarketResult<ConnectionManager> {
+        let conn = self.conn
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await?
+            .clone();
+        Ok(conn)
+    }
+
+    fn hash(&self, bucket: &str) -> String {
+        format!("{KEY_PREFIX}:{bucket}")
+    }
+}
+
+impl std::fmt::Debug for CacheStoreRedis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheStoreRedis")
+            .field("client", &self.client)
+            .field("is_connected", &self.conn.initialized())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CacheStore for CacheStoreRedis {
+    fn box_clone(&self) -> Box<dyn CacheStore> {
+        Box::new(self.clone())
+    }
+
+    async fn get(&self, bucket: &str, key: &str) -> MarketResult<Option<String>> {
+        let value: Option<String> = self.connection().await?.hget(self.hash(bucket), key).await?;
+        Ok(value)
+    }
+
+    async fn set(&self, bucket: &str, key: &str, value: String) -> MarketResult<()> {
+        let _: () = self.connection().await?.hset(self.hash(bucket), key, value).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, bucket: &str, key: &str) -> MarketResult<()> {
+        let _: () = self.connection().await?.hdel(self.hash(bucket), key).await?;
+        Ok(())
+    }
+
+    async fn keys(&self, bucket: &str) -> MarketResult<Vec<String>> {
+        let keys: Vec<String> = self.connection().await?.hkeys(self.hash(bucket)).await?;
+        Ok(keys)
+    }
+
+    async fn clear(&self, bucket: &str) -> MarketResult<()> {
+        let _: () = self.connection().await?.del(self.hash(bucket)).await?;
+        Ok(())
+    }
+
+    async fn compare_and_set(&self, bucket: &str, key: &str, expected: Option<&str>, value: String) -> MarketResult<bool> {
+        let mut conn = self.connection().await?;
+        let is_set: i32 = Script::new(SCRIPT_COMPARE_AND_SET)
+            .key(self.hash(bucket))
+            .arg(key)
+            .arg(if expected.is_some() { "1" } else { "0" })
+            .arg(expected.unwrap_or_default())
+            .arg(value)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(is_set == 1)
+    }
+}
@@ -26,6 +26,31 @@ impl DriverStrategy {
         self.passengers() + passengers <= self.max_capacity
     }
 
+    #[doc = "Get the ids of every reservation the driver has, picked up or not"]
+    pub fn get_reservations(&self) -> Vec<Uuid> {
+        let stops = self.dest.iter().chain(self.queue.iter())
+            .filter_map(|stop| if let DriverStop::Reservation(res) = stop { Some(res.id_reservation) } else { None });
+        self.picked_up.keys().cloned().chain(stops).collect()
+    }
+
+    #[doc = "Drop every reservation that was not picked up, the driver still takes the riders in their car where they are going"]
+    pub fn keep_picked_up(&self) -> DriverStrategy {
+        let mut stops: Vec<DriverStop> = Vec::new();
+        for stop in self.dest.iter().chain(self.queue.iter()) {
+            match stop {
+                DriverStop::Reservation(res) if !self.picked_up.contains_key(&res.id_reservation) => (),
+                // Only riders picked up for the event need to get to it, and they get off at the first one
+                DriverStop::Event(_) if !stops.is_empty() => (),
+                _ => stops.push(stop.clone()),
+            }
+        }
+
+        let mut new_driver = self.clone();
+        new_driver.dest = if stops.is_empty() { None } else { Some(stops.remove(0)) };
+        new_driver.queue = stops;
+        new_driver
+    }
+
     #[doc = "Add a reservation to the strategy"]
     pub fn add_reservation(&self, reservation: Reservation) -> DriverStrategy {
         let mut new_driver = self.clone();
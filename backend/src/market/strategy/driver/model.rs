@@ -9,7 +9,7 @@ use super::stop::model::DriverStop;
 
 pub type PassengerCount = i32;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct DriverStrategy {
     pub id: IdEventDriver,
     pub id_event: Uuid,
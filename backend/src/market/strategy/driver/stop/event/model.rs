@@ -1,7 +1,7 @@
 use juniper::GraphQLObject;
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject, PartialEq)]
 pub struct DriverStopEvent {
     a: bool, // GraphQL needs some field to be present
 }
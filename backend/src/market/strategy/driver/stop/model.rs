@@ -6,7 +6,7 @@ use crate::graphql::context::Context;
 use super::event::model::DriverStopEvent;
 use super::reservation::model::DriverStopReservation;
 
-#[derive(Debug, Serialize, Deserialize, Clone, GraphQLUnion)]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLUnion, PartialEq)]
 #[graphql(Context = Context)]
 pub enum DriverStop {
     Event(DriverStopEvent),
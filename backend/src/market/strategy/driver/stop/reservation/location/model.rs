@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 
 use crate::graphql::geo::model::LatLng;
 
-#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject)]
+#[derive(Debug, Serialize, Deserialize, Clone, GraphQLObject, PartialEq)]
 pub struct DriverStopLocation {
     pub coords: LatLng,
     pub address: Address,
@@ -3,7 +3,7 @@ use uuid::Uuid;
 
 use super::location::model::DriverStopLocation;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct DriverStopReservation {
     pub location: DriverStopLocation,
     pub id_reservation: Uuid,
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+
+use crate::{graphql::{notifications::model::NotificationKind, vehicles::Vehicle}, types::locale::Locale};
+
+use super::{types::MarketResult, error::ErrorMarket};
+
+#[doc = "Orgs can not write notifications longer than this, it keeps texts to a couple of segments"]
+pub const MAX_TEMPLATE_LENGTH: usize = 300;
+
+#[doc = "What can go in braces in a template"]
+pub const PLACEHOLDERS: [&str; 12] = [
+    "rider_name",
+    "passenger_count",
+    "event_name",
+    "org_name",
+    "driver_name",
+    "vehicle",
+    "vehicle_color",
+    "vehicle_make",
+    "vehicle_model",
+    "vehicle_license",
+    "eta",
+    "announcement",
+];
+
+#[doc = "The wording used when the org did not write their own"]
+pub fn default_template(kind: NotificationKind, locale: Locale) -> &'static str {
+    match (locale, kind) {
+        (Locale::En, NotificationKind::DriverAccepted) => "Your driver is on the way! Look for a {vehicle}, plate {vehicle_license}.",
+        (Locale::En, NotificationKind::DriverApproaching) => "Your driver is {eta} away!",
+        (Locale::En, NotificationKind::DriverArrived) => "Your driver has arrived! Look for a {vehicle}, plate {vehicle_license}.",
+        (Locale::En, NotificationKind::ReservationCancelled) => "Your ride to {event_name} was cancelled.",
+        (Locale::En, NotificationKind::EventAnnouncement) => "Update about {event_name}: {announcement}",
+        (Locale::Es, NotificationKind::DriverAccepted) => "¡Tu conductor va en camino! Busca un {vehicle}, placa {vehicle_license}.",
+        (Locale::Es, NotificationKind::DriverApproaching) => "¡Tu conductor está a {eta}!",
+        (Locale::Es, NotificationKind::DriverArrived) => "¡Tu conductor ha llegado! Busca un {vehicle}, placa {vehicle_license}.",
+        (Locale::Es, NotificationKind::ReservationCancelled) => "Tu viaje a {event_name} fue cancelado.",
+        (Locale::Es, NotificationKind::EventAnnouncement) => "Novedades sobre {event_name}: {announcement}",
+    }
+}
+
+#[doc = "Who notifications are from when the org did not choose"]
+pub const DEFAULT_TITLE: &str = "{org_name}";
+
+#[doc = "The default wording when the driver has no vehicle to look for"]
+pub fn default_template_without_vehicle(kind: NotificationKind, locale: Locale) -> &'static str {
+    match (locale, kind) {
+        (Locale::En, NotificationKind::DriverAccepted) => "Your driver is on the way!",
+        (Locale::En, NotificationKind::DriverArrived) => "Your driver has arrived!",
+        (Locale::Es, NotificationKind::DriverAccepted) => "¡Tu conductor va en camino!",
+        (Locale::Es, NotificationKind::DriverArrived) => "¡Tu conductor ha llegado!",
+        _ => default_template(kind, locale),
+    }
+}
+
+#[doc = "Check a template an org wrote only uses placeholders we can fill"]
+pub fn validate(body: &str) -> MarketResult<()> {
+    if body.trim().is_empty() { return Err(ErrorMarket::BadValue(String::from("The template can not be empty"))) }
+    if body.chars().count() > MAX_TEMPLATE_LENGTH {
+        return Err(ErrorMarket::BadValue(format!("The template can not be longer than {MAX_TEMPLATE_LENGTH} characters")))
+    }
+    for name in placeholders(body) {
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(ErrorMarket::BadValue(format!("Unknown placeholder {{{name}}}, it can be one of {}", PLACEHOLDERS.join(", "))))
+        }
+    }
+    Ok(())
+}
+
+#[doc = "Fill in the placeholders, ones without a value are left empty"]
+pub fn render(body: &str, values: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else { break };
+        out.push_str(&rest[..start]);
+        let name = &rest[start + 1..end];
+        match values.get(name) {
+            Some(value) => out.push_str(value),
+            None if PLACEHOLDERS.contains(&name) => (),
+            None => out.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[doc = "If a template needs the driver's vehicle to make sense"]
+pub fn uses_vehicle(body: &str) -> bool {
+    placeholders(body).into_iter().any(|name| name.starts_with("vehicle"))
+}
+
+fn placeholders(body: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else { break };
+        names.push(&rest[start + 1..end]);
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+#[doc = "How long until the driver is there, rounded up to the minute"]
+pub fn eta(locale: Locale, eta: Duration) -> String {
+    let minutes = ((eta.num_seconds() + 59) / 60).max(1);
+    match (locale, minutes) {
+        (Locale::En, 1) => String::from("1 minute"),
+        (Locale::En, _) => format!("{minutes} minutes"),
+        (Locale::Es, 1) => String::from("1 minuto"),
+        (Locale::Es, _) => format!("{minutes} minutos"),
+    }
+}
+
+#[doc = "The vehicle the way riders look for it, \"Blue Honda Civic\""]
+pub fn vehicle(locale: Locale, vehicle: &Vehicle) -> String {
+    match locale {
+        Locale::En => format!("{} {} {}", vehicle.color, vehicle.make, vehicle.model),
+        Locale::Es => format!("{} {} {}", vehicle.make, vehicle.model, vehicle.color.to_lowercase()),
+    }
+}
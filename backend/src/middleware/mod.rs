@@ -14,6 +14,8 @@ pub struct AuthToken {
 #[derive(Serialize, Deserialize)]
 pub struct UserId {
     pub phone: Option<Phone>,
+    #[doc = "When the token stops being valid, as a unix timestamp"]
+    pub expires_at: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -59,10 +61,10 @@ impl FromRequest for UserId {
 
     fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
         let auth = req.headers().get(http::header::AUTHORIZATION);
-        if auth.is_none() { return ready(Ok(UserId { phone: None })) }
+        if auth.is_none() { return ready(Ok(UserId { phone: None, expires_at: None })) }
 
         let token = auth.unwrap().to_str().unwrap_or("");
-        if token.is_empty() { return ready(Ok(UserId { phone: None })) }
+        if token.is_empty() { return ready(Ok(UserId { phone: None, expires_at: None })) }
 
         let state = req.app_data::<web::Data<AppState>>().unwrap();
         let secret = &state.jwt_secret;
@@ -74,8 +76,9 @@ impl FromRequest for UserId {
         match decode {
             Ok(token) => ready(Ok(UserId {
                 phone: Some(token.claims.phone),
+                expires_at: Some(token.claims.exp as i64),
             })),
-            Err(_err) => ready(Ok(UserId { phone: None })),
+            Err(_err) => ready(Ok(UserId { phone: None, expires_at: None })),
         }
     }
 }
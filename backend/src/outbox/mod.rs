@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use actix::prelude::*;
+use tokio::{spawn, sync::Mutex};
+use log::{info, error};
+
+use crate::market::Market;
+
+#[doc = "How often the outbox is checked for notifications that are due"]
+const TICK: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[doc = "Delivers the notifications in the outbox, and tries the failed ones again"]
+pub struct OutboxWorker {
+    market: Arc<Market>,
+    running: Arc<Mutex<()>>,
+}
+
+impl OutboxWorker {
+    pub fn new(market: Arc<Market>) -> Addr<Self> {
+        let actor = OutboxWorker { market, running: Arc::new(Mutex::new(())) };
+        actor.start()
+    }
+
+    fn start_interval(&self, ctx: &mut Context<Self>) {
+        let market = self.market.clone();
+        let running = self.running.clone();
+
+        ctx.run_interval(TICK, move |_act, _ctx| {
+            // Skip this tick if the last one is still delivering
+            let Ok(guard) = running.clone().try_lock_owned() else { return };
+            let market = market.clone();
+            spawn(async move {
+                match market.notification.deliver_due().await {
+                    Ok(report) if report.sent + report.skipped + report.retrying + report.failed > 0 => {
+                        info!("Outbox: sent {}, skipped {}, retrying {}, failed {}", report.sent, report.skipped, report.retrying, report.failed);
+                    },
+                    Ok(_) => (),
+                    Err(e) => error!("Error delivering notifications, {e:?}"),
+                }
+                drop(guard);
+            });
+        });
+    }
+}
+
+impl Actor for OutboxWorker {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.start_interval(ctx);
+    }
+}
@@ -0,0 +1,35 @@
+use actix_web::{web::{Data, Path, Query}, HttpResponse, http::header::{ContentDisposition, DispositionParam, DispositionType}};
+use uuid::Uuid;
+
+use crate::{db_util::AppState, middleware::AuthToken, graphql::{context::Context, reports::model::{DriverReport, FormSemester}}};
+
+#[doc = "Download the driver report of an org for a semester, as a CSV file"]
+pub async fn download_driver_report(state: Data<AppState>, auth: AuthToken, id_org: Path<Uuid>, semester: Query<FormSemester>) -> Result<HttpResponse, actix_web::Error> {
+    let id_org = id_org.into_inner();
+    let ctx = Context::new(
+        state.db.clone(),
+        state.twilio.clone(),
+        state.jwt_secret.clone(),
+        state.google_maps_client.clone(),
+        state.market.clone(),
+        Some(auth.phone),
+        state.is_mock,
+    );
+    if !ctx.validate_is_admin(id_org).await {
+        return Err(actix_web::error::ErrorForbidden("Not an admin"))
+    }
+
+    let Some(range) = semester.range() else {
+        return Err(actix_web::error::ErrorBadRequest("The year is out of range"))
+    };
+    let report = DriverReport::load_between(&state.db, id_org, range).await
+        .map_err(|err| actix_web::error::ErrorInternalServerError(format!("Could not load the report: {:?}", err)))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(semester.filename())],
+        })
+        .body(report.to_csv()))
+}
@@ -0,0 +1,9 @@
+mod controller;
+use actix_web::web;
+
+pub fn register_urls(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        web::scope("/reports")
+            .service(web::resource("{id_org}/drivers.csv").route(web::get().to(controller::download_driver_report)))
+    );
+}
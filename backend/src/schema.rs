@@ -12,6 +12,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    driver_pings (id) {
+        id -> Int4,
+        id_event -> Uuid,
+        id_driver -> Int4,
+        lat -> Float8,
+        lng -> Float8,
+        at -> Int4,
+    }
+}
+
 diesel::table! {
     event_drivers (id) {
         id -> Int4,
@@ -36,6 +47,7 @@ diesel::table! {
         obsolete_at -> Nullable<Int4>,
         published_at -> Nullable<Int4>,
         id -> Uuid,
+        auto_arrival -> Bool,
     }
 }
 
@@ -84,6 +96,55 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    notification_mutes (id_org, kind) {
+        id_org -> Uuid,
+        kind -> Text,
+        updated_at -> Int4,
+    }
+}
+
+diesel::table! {
+    notification_preferences (phone, channel, kind) {
+        phone -> Text,
+        channel -> Text,
+        kind -> Text,
+        is_enabled -> Bool,
+        updated_at -> Int4,
+    }
+}
+
+diesel::table! {
+    notification_templates (id_org, kind, locale) {
+        id_org -> Uuid,
+        kind -> Text,
+        locale -> Text,
+        body -> Text,
+        updated_at -> Int4,
+        title -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Int4,
+        id_reservation -> Uuid,
+        phone -> Text,
+        kind -> Text,
+        message -> Nullable<Text>,
+        dedupe_key -> Text,
+        status -> Text,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Int4,
+        next_attempt_at -> Int4,
+        sent_at -> Nullable<Int4>,
+        id_driver -> Nullable<Int4>,
+        eta -> Nullable<Int4>,
+        announcement -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     orgs (id) {
         label -> Text,
@@ -135,6 +196,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    push_subscriptions (id) {
+        id -> Int4,
+        phone -> Text,
+        endpoint -> Text,
+        p256dh -> Text,
+        auth -> Text,
+        created_at -> Int4,
+    }
+}
+
 diesel::table! {
     reservations (id) {
         made_at -> Int4,
@@ -158,6 +230,8 @@ diesel::table! {
         driver_arrived_at -> Nullable<Int4>,
         est_pickup -> Int4,
         est_dropoff -> Int4,
+        driver_arrived_auto -> Bool,
+        driver_accepted_at -> Nullable<Int4>,
     }
 }
 
@@ -196,6 +270,7 @@ diesel::table! {
         created_at -> Int4,
         updated_at -> Int4,
         is_opted_in_sms -> Nullable<Bool>,
+        locale -> Nullable<Text>,
     }
 }
 
@@ -217,16 +292,22 @@ diesel::table! {
 
 diesel::allow_tables_to_appear_in_same_query!(
     colleges,
+    driver_pings,
     event_drivers,
     events,
     invites,
     locations,
     media,
     members,
+    notification_mutes,
+    notification_preferences,
+    notification_templates,
+    notifications,
     orgs,
     points,
     points_assignment,
     points_request,
+    push_subscriptions,
     reservations,
     user_group_memberships,
     user_groups,
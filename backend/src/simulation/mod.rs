@@ -0,0 +1,214 @@
+pub mod model;
+
+use std::collections::HashMap;
+
+use chrono::Duration;
+use log::{debug, warn};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use uuid::Uuid;
+
+use crate::{graphql::{geo::model::LatLng, reservations::FormReservation}, market::{Market, clock::{Clock, ClockManual}, types::MarketResult, strategy::model::IdEventDriver, estimate::driver::{model::DriverStrategyEstimations, stop::model::DriverStopEstimation}}, types::phone::Phone};
+
+use self::model::{SimulationConfig, SimulationDriver, RiderRequest, SimulationReport, DriverReport};
+
+#[derive(Debug)]
+struct DriverState {
+    id: IdEventDriver,
+    location: LatLng,
+    #[doc = "When the driver gets to their destination, None when they are not driving"]
+    arrives_at: Option<i32>,
+    trips: usize,
+    busy_seconds: i64,
+}
+
+#[doc = "Runs virtual drivers and riders against a mock market on a manual clock"]
+pub struct Simulation {
+    market: Market,
+    clock: ClockManual,
+    id_event: Uuid,
+    drivers: Vec<SimulationDriver>,
+    config: SimulationConfig,
+}
+
+impl Simulation {
+    #[doc = "The market should be a mock market made with the same clock, with the event and drivers already set up"]
+    pub fn new(market: Market, clock: ClockManual, id_event: Uuid, drivers: Vec<SimulationDriver>, config: SimulationConfig) -> Self {
+        Self {
+            market,
+            clock,
+            id_event,
+            drivers,
+            config,
+        }
+    }
+
+    #[doc = "The reservations the riders will make, in the order they make them"]
+    pub fn plan_riders(&self, start: i32) -> Vec<RiderRequest> {
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        let window = self.config.rider_window.num_seconds().max(0);
+        let mut requests: Vec<RiderRequest> = (0..self.config.riders)
+            .map(|idx| {
+                let location = &self.config.pickup_locations[rng.gen_range(0..self.config.pickup_locations.len())];
+                RiderRequest {
+                    id_reservation: Uuid::from_bytes(rng.gen()),
+                    phone: Phone::new(&format!("+18002{:06}", idx)).expect("Invalid phone number"),
+                    at: start + rng.gen_range(0..=window) as i32,
+                    passenger_count: rng.gen_range(1..=self.config.max_passengers.max(1)),
+                    place_id: location.id.clone(),
+                }
+            })
+            .collect();
+        requests.sort_by_key(|request| request.at);
+        requests
+    }
+
+    pub async fn run(&self) -> MarketResult<SimulationReport> {
+        let start = self.clock.now();
+        let end = start + self.config.max_duration.num_seconds() as i32;
+        let mut pending = self.plan_riders(start);
+        pending.reverse();
+
+        let mut requested_at: HashMap<Uuid, i32> = HashMap::new();
+        let mut wait_seconds = Vec::new();
+        let mut riders_completed = 0;
+
+        let mut drivers: Vec<DriverState> = Vec::new();
+        for driver in &self.drivers {
+            self.market.driver.ping(&self.id_event, &driver.id, &driver.start).await?;
+            drivers.push(DriverState {
+                id: driver.id,
+                location: driver.start,
+                arrives_at: None,
+                trips: 0,
+                busy_seconds: 0,
+            });
+        }
+
+        while self.clock.now() <= end {
+            let now = self.clock.now();
+
+            while pending.last().map_or(false, |request| request.at <= now) {
+                let request = pending.pop().unwrap();
+                self.reserve(&request).await?;
+                requested_at.insert(request.id_reservation, request.at);
+            }
+
+            for driver in drivers.iter_mut() {
+                if driver.arrives_at.map_or(false, |arrives_at| arrives_at > now) { continue }
+
+                if driver.arrives_at.take().is_some() {
+                    let strategy = self.get_driver(driver.id).await?;
+                    self.complete_stop(driver, &strategy, &requested_at, &mut wait_seconds, &mut riders_completed).await?;
+                }
+
+                let mut strategy = self.get_driver(driver.id).await?;
+                if strategy.dest.is_none() {
+                    if let Some(reservation) = self.market.event.get_avaliable_reservation(&self.id_event, &driver.id).await? {
+                        debug!("Driver {} accepted {}", driver.id, reservation.id);
+                        strategy = self.market.driver.accept(&driver.id, &reservation.id).await?;
+                    }
+                }
+
+                if let Some(dest) = &strategy.dest {
+                    let travel = time_to(dest).num_seconds();
+                    driver.arrives_at = Some(now + travel as i32);
+                    driver.busy_seconds += travel;
+                }
+            }
+
+            let is_idle = drivers.iter().all(|driver| driver.arrives_at.is_none());
+            if pending.is_empty() && is_idle && riders_completed == requested_at.len() { break }
+
+            self.clock.advance(self.config.tick);
+        }
+
+        let elapsed_seconds = (self.clock.now() - start) as i64;
+        let drivers = drivers.into_iter()
+            .map(|driver| {
+                // Driving past the end of the run is not counted
+                let busy_seconds = driver.busy_seconds - driver.arrives_at.map_or(0, |arrives_at| (arrives_at - self.clock.now()).max(0) as i64);
+                let utilization = if elapsed_seconds > 0 { busy_seconds as f64 / elapsed_seconds as f64 } else { 0.0 };
+                (driver.id, DriverReport {
+                    id: driver.id,
+                    trips: driver.trips,
+                    busy_seconds,
+                    utilization,
+                })
+            })
+            .collect();
+
+        Ok(SimulationReport {
+            seed: self.config.seed,
+            elapsed_seconds,
+            riders_requested: requested_at.len() + pending.len(),
+            riders_picked_up: wait_seconds.len(),
+            riders_completed,
+            wait_seconds,
+            drivers,
+        })
+    }
+
+    async fn reserve(&self, request: &RiderRequest) -> MarketResult<()> {
+        let location = self.config.pickup_locations.iter()
+            .find(|location| location.id.eq(&request.place_id))
+            .expect("Rider place id is not a pickup location");
+        let form = FormReservation {
+            passenger_count: request.passenger_count,
+            is_dropoff: false,
+            stops: vec![location.stop()],
+        };
+        self.market.reservation.create(&request.phone, &request.id_reservation, &self.id_event, form).await?;
+        Ok(())
+    }
+
+    async fn get_driver(&self, id_driver: IdEventDriver) -> MarketResult<DriverStrategyEstimations> {
+        self.market.event.get_estimates(&self.id_event).await?.driver(&id_driver)
+    }
+
+    #[doc = "Do what the driver would do once they get to their destination"]
+    async fn complete_stop(&self, driver: &mut DriverState, strategy: &DriverStrategyEstimations, requested_at: &HashMap<Uuid, i32>, wait_seconds: &mut Vec<i64>, riders_completed: &mut usize) -> MarketResult<()> {
+        let now = self.clock.now();
+        let location = match &strategy.dest {
+            Some(DriverStopEstimation::Reservation(stop)) if !stop.is_dropoff => {
+                let ids = strategy.get_pickup_reservations()?;
+                self.market.driver.arrive(&self.id_event, &driver.id).await?;
+                self.market.driver.pickup(&self.id_event, &driver.id).await?;
+                for id in ids {
+                    if let Some(at) = requested_at.get(&id) { wait_seconds.push((now - at) as i64) }
+                }
+                stop.location.coords
+            },
+            Some(DriverStopEstimation::Reservation(stop)) => {
+                self.market.driver.dropoff(&self.id_event, &driver.id).await?;
+                stop.location.coords
+            },
+            Some(DriverStopEstimation::Event(_)) if !strategy.picked_up.is_empty() => {
+                let ids = strategy.get_dropoff_reservations();
+                self.market.driver.dropoff(&self.id_event, &driver.id).await?;
+                *riders_completed += ids.len();
+                driver.trips += 1;
+                self.config.event_location
+            },
+            Some(DriverStopEstimation::Event(_)) => {
+                self.market.driver.arrive(&self.id_event, &driver.id).await?;
+                self.market.driver.pickup(&self.id_event, &driver.id).await?;
+                self.config.event_location
+            },
+            None => {
+                warn!("Driver {} arrived without a destination", driver.id);
+                driver.location
+            },
+        };
+        driver.location = location;
+        self.market.driver.ping(&self.id_event, &driver.id, &location).await?;
+        Ok(())
+    }
+}
+
+#[doc = "How long until the driver gets to a stop"]
+fn time_to(stop: &DriverStopEstimation) -> Duration {
+    match stop {
+        DriverStopEstimation::Reservation(stop) => stop.pickup,
+        DriverStopEstimation::Event(stop) => stop.arrival,
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::{graphql::geo::model::LatLng, market::{geocoder::scenario::ScenarioLocation, strategy::model::IdEventDriver}, types::phone::Phone};
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    #[doc = "The seed for everything random in the run, the same seed gives the same run"]
+    pub seed: u64,
+    #[doc = "How many riders make a reservation"]
+    pub riders: usize,
+    #[doc = "Riders make their reservation at a random time in this window from the start"]
+    pub rider_window: Duration,
+    #[doc = "The most passengers a rider reserves for"]
+    pub max_passengers: i32,
+    #[doc = "Where riders are picked up from"]
+    pub pickup_locations: Vec<ScenarioLocation>,
+    #[doc = "Where the event is, this is where riders are taken"]
+    pub event_location: LatLng,
+    #[doc = "How far the clock moves each step"]
+    pub tick: Duration,
+    #[doc = "The run stops after this long, even if riders are still waiting"]
+    pub max_duration: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationDriver {
+    pub id: IdEventDriver,
+    pub start: LatLng,
+}
+
+#[doc = "A reservation a virtual rider will make"]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiderRequest {
+    pub id_reservation: Uuid,
+    pub phone: Phone,
+    pub at: i32,
+    pub passenger_count: i32,
+    pub place_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DriverReport {
+    pub id: IdEventDriver,
+    pub trips: usize,
+    #[doc = "Seconds spent driving to a stop"]
+    pub busy_seconds: i64,
+    #[doc = "The share of the run spent driving to a stop"]
+    pub utilization: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub elapsed_seconds: i64,
+    pub riders_requested: usize,
+    pub riders_picked_up: usize,
+    pub riders_completed: usize,
+    #[doc = "Seconds from making a reservation to being picked up, in pickup order"]
+    pub wait_seconds: Vec<i64>,
+    pub drivers: HashMap<IdEventDriver, DriverReport>,
+}
+
+impl SimulationReport {
+    pub fn riders_unserved(&self) -> usize {
+        self.riders_requested - self.riders_completed
+    }
+
+    pub fn wait_mean(&self) -> Option<f64> {
+        if self.wait_seconds.is_empty() { return None }
+        let total: i64 = self.wait_seconds.iter().sum();
+        Some(total as f64 / self.wait_seconds.len() as f64)
+    }
+
+    pub fn wait_max(&self) -> Option<i64> {
+        self.wait_seconds.iter().max().copied()
+    }
+
+    #[doc = "The wait that `percentile` percent of riders waited at most, using the nearest rank"]
+    pub fn wait_percentile(&self, percentile: f64) -> Option<i64> {
+        if self.wait_seconds.is_empty() { return None }
+        let mut waits = self.wait_seconds.clone();
+        waits.sort();
+        let rank = ((percentile / 100.0) * waits.len() as f64).ceil() as usize;
+        waits.get(rank.saturating_sub(1).min(waits.len() - 1)).copied()
+    }
+
+    pub fn utilization_mean(&self) -> f64 {
+        if self.drivers.is_empty() { return 0.0 }
+        self.drivers.values().map(|driver| driver.utilization).sum::<f64>() / self.drivers.len() as f64
+    }
+}
+
+impl std::fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Simulation (seed {}) ran for {}s", self.seed, self.elapsed_seconds)?;
+        writeln!(f, "Riders: {} requested, {} picked up, {} completed, {} unserved", self.riders_requested, self.riders_picked_up, self.riders_completed, self.riders_unserved())?;
+        if let (Some(mean), Some(p50), Some(p90), Some(max)) = (self.wait_mean(), self.wait_percentile(50.0), self.wait_percentile(90.0), self.wait_max()) {
+            writeln!(f, "Wait: mean {mean:.0}s, p50 {p50}s, p90 {p90}s, max {max}s")?;
+        }
+        writeln!(f, "Utilization: {:.1}%", self.utilization_mean() * 100.0)?;
+        let mut drivers: Vec<&DriverReport> = self.drivers.values().collect();
+        drivers.sort_by_key(|driver| driver.id);
+        for driver in drivers {
+            writeln!(f, "  Driver {}: {} trips, {:.1}% utilized", driver.id, driver.trips, driver.utilization * 100.0)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use juniper::GraphQLEnum;
+use serde::{Serialize, Deserialize};
+
+#[doc = "The language a user gets their notifications in"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, GraphQLEnum)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Self; 2] = [Self::En, Self::Es];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+        }
+    }
+
+    #[doc = "The locale for a language tag such as `es` or `es-MX`, None for languages we do not have"]
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        Self::ALL.into_iter().find(|locale| locale.as_str() == language)
+    }
+}
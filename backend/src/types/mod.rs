@@ -1 +1,2 @@
 pub mod phone;
+pub mod locale;
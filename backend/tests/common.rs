@@ -16,8 +16,9 @@ use nujade_backend::graphql::orgs::model::FormOrganization;
 use nujade_backend::graphql::reservations::messages::ReservationsClear;
 use nujade_backend::graphql::vehicles::FormVehicle;
 use nujade_backend::graphql::vehicles::messages::VehicleUpdate;
-use nujade_backend::market::Market;
+use nujade_backend::market::{Market, MarketMockConfig};
 use nujade_backend::market::geocoder::mock_location;
+use nujade_backend::market::geocoder::scenario::MockScenario;
 use nujade_backend::types::phone::Phone;
 use uuid::Uuid;
 
@@ -41,6 +42,16 @@ pub fn get_driver2_phone() -> Phone {
 }
 
 pub fn setup() -> Market {
+    setup_with_scenario(MockScenario::default())
+}
+
+#[allow(dead_code)]
+pub fn setup_with_scenario(scenario: MockScenario) -> Market {
+    setup_with(MarketMockConfig { scenario, ..Default::default() })
+}
+
+#[allow(dead_code)]
+pub fn setup_with(config: MarketMockConfig) -> Market {
 
     dotenv().ok();
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
@@ -49,12 +60,9 @@ pub fn setup() -> Market {
 
     // let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
 
-    let cfg = kv::Config::new("./kv_tst");
-    let kv = kv::Store::new(cfg).unwrap();
-
     // let schema = create_schema();
 
-    let market = Market::mock(db_addr, kv);
+    let market = Market::mock_with(db_addr, config);
     market
 }
 
@@ -67,7 +75,7 @@ async fn clear_reservations(market: &Market) {
 
 #[allow(dead_code)]
 pub async fn init(market: &Market) {
-    market.clear_cache().expect("Could not clear cache");
+    market.clear_cache().await.expect("Could not clear cache");
     clear_drivers(&market).await;
     init_org(&market).await;
     init_location(&market).await;
@@ -79,7 +87,7 @@ pub async fn init(market: &Market) {
 
 #[allow(dead_code)]
 pub async fn init_with_two_drivers(market: &Market) {
-    market.clear_cache().expect("Could not clear cache");
+    market.clear_cache().await.expect("Could not clear cache");
     clear_drivers(&market).await;
     init_org(&market).await;
     init_location(&market).await;
@@ -211,6 +219,7 @@ pub async fn init_event(market: &Market) {
         id_location: Some(id_location),
         obsolete_at: None,
         published_at: None,
+        auto_arrival: None,
     };
 
 
@@ -229,6 +238,7 @@ pub async fn init_event(market: &Market) {
         id_org,
         obsolete_at: None,
         published_at: form.published_at,
+        auto_arrival: form.auto_arrival.unwrap_or(false),
     } }).await;
     assert!(matches!(res, Ok(Ok(_))), "Error creating the test event. Got error: `{:?}`", res);
 
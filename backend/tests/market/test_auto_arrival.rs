@@ -0,0 +1,88 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+use nujade_backend::{graphql::{reservations::{FormReservation, messages::ReservationGet}, events::{DBEventInsertable, messages::EventUpdate}}, types::phone::Phone, market::{geocoder::mock_location, clock::{Clock, ClockManual}, MarketMockConfig}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_confirms_arrival_after_the_driver_stays_at_the_pickup() {
+    let clock = ClockManual::new(1_000_000);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let res = market.db.send(EventUpdate { event: DBEventInsertable {
+        id: id_event,
+        name: String::from("My event"),
+        bio: None,
+        image_url: None,
+        time_start: 10,
+        time_end: 10,
+        reservations_start: 10,
+        reservations_end: 10,
+        id_location: common::get_id_location(),
+        id_org: common::get_id_org(),
+        obsolete_at: None,
+        published_at: None,
+        auto_arrival: true,
+    } }).await;
+    assert!(matches!(res, Ok(Ok(_))), "Could not turn on auto arrival, got {:?}", res);
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000006").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("AE4FC295-6071-42A3-8EDF-4A5B6C7D8E9F").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::BENET_HALL_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let reservation = market.db.send(ReservationGet { id: id_reservation }).await.unwrap().unwrap();
+    assert!(!reservation.is_driver_arrived, "Expected the driver to have to stay at the pickup first");
+
+    clock.advance(Duration::seconds(30));
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::BENET_HALL_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let reservation = market.db.send(ReservationGet { id: id_reservation }).await.unwrap().unwrap();
+    assert!(reservation.is_driver_arrived);
+    assert!(reservation.driver_arrived_auto);
+    let arrived_at = clock.now();
+    assert_eq!(reservation.driver_arrived_at, Some(arrived_at));
+
+    // Leaving and coming back does not confirm the arrival again
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::BENET_HALL_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+    clock.advance(Duration::seconds(30));
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::BENET_HALL_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let reservation = market.db.send(ReservationGet { id: id_reservation }).await.unwrap().unwrap();
+    assert_eq!(reservation.driver_arrived_at, Some(arrived_at), "Expected the first arrival to be kept");
+}
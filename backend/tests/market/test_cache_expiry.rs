@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, clock::ClockManual, MarketMockConfig}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_expires_and_collects_estimates() {
+    let clock = ClockManual::new(1_000_000);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000002").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("6A0B8E51-2C3D-4E6F-8A9B-0C1D2E3F4A5B").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+
+    let first = market.event.cache_stats();
+
+    let est_res = market.event.get_estimates(&id_event).await;
+    assert!(est_res.is_ok(), "Estimation failed: {est_res:?}");
+    let second = market.event.cache_stats();
+    assert!(second.hits > first.hits, "Expected a cache hit, got {second:?}");
+    assert_eq!(second.misses, first.misses);
+
+    clock.advance(Duration::hours(1));
+
+    let est_res = market.event.get_estimates(&id_event).await;
+    assert!(est_res.is_ok(), "Estimation failed: {est_res:?}");
+    let third = market.event.cache_stats();
+    assert!(third.misses > second.misses, "Expected expired estimates to miss, got {third:?}");
+
+    // The test event has ended, so everything cached for it is collected
+    let gc_res = market.event.gc_cache().await;
+    assert!(gc_res.is_ok(), "Garbage collection failed: {gc_res:?}");
+    assert!(gc_res.unwrap().events_evicted >= 1);
+    assert!(!market.event.is_driver_online(&driver.id).await.unwrap());
+}
+
+#[actix_web::main]
+#[test]
+async fn it_keeps_the_cache_of_upcoming_events() {
+    // The test event ends at 10, so at 5 it has not started yet
+    let clock = ClockManual::new(5);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let gc_res = market.event.gc_cache().await;
+    assert!(gc_res.is_ok(), "Garbage collection failed: {gc_res:?}");
+    assert_eq!(gc_res.unwrap().events_evicted, 0, "Expected the upcoming event to stay cached");
+    assert!(market.event.is_driver_online(&driver.id).await.unwrap());
+
+    let snapshot = market.event.get_cache_snapshot(&id_event).await.expect("Could not get the cache snapshot");
+    let strategy = snapshot.strategy.expect("Expected the strategy to be kept");
+    assert!(strategy.drivers.contains_key(&driver.id), "Expected the driver to keep their strategy, got {strategy:?}");
+}
@@ -0,0 +1,72 @@
+use dotenv::dotenv;
+use nujade_backend::{graphql::geo::model::LatLng, market::store::{CacheStore, memory::CacheStoreMemory, redis::CacheStoreRedis}};
+
+#[actix_web::main]
+#[test]
+async fn it_stores_values_in_memory() {
+    let store: Box<dyn CacheStore> = Box::new(CacheStoreMemory::new());
+    let shared = store.box_clone();
+
+    let location = LatLng { lat: 34.68, lng: -82.83 };
+    let res = store.set_json("locations", "a", &location).await;
+    assert!(res.is_ok(), "Could not set value, got error: {res:?}");
+
+    let found: Option<LatLng> = shared.get_json("locations", "a").await.unwrap();
+    assert!(found.is_some_and(|found| found.is_close_to(&location)), "Clones should share values");
+    assert!(store.get("locations", "b").await.unwrap().is_none());
+    assert!(store.get("other", "a").await.unwrap().is_none());
+
+    store.set("locations", "b", String::from("{}")).await.unwrap();
+    let mut keys = store.keys("locations").await.unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["a", "b"]);
+
+    store.remove("locations", "a").await.unwrap();
+    assert!(store.get("locations", "a").await.unwrap().is_none());
+
+    store.clear("locations").await.unwrap();
+    assert!(store.keys("locations").await.unwrap().is_empty());
+
+    assert_compare_and_set(store.as_ref(), "locations").await;
+}
+
+#[actix_web::main]
+#[test]
+async fn it_stores_values_in_redis() {
+    dotenv().ok();
+    let url = std::env::var("REDIS_URL").unwrap_or(String::from("redis://127.0.0.1:6379"));
+    let store: Box<dyn CacheStore> = Box::new(CacheStoreRedis::new(&url).expect("Could not connect to redis"));
+    let shared = store.box_clone();
+    let bucket = "test_cache_store_redis";
+    store.clear(bucket).await.unwrap();
+
+    let location = LatLng { lat: 34.68, lng: -82.83 };
+    let res = store.set_json(bucket, "a", &location).await;
+    assert!(res.is_ok(), "Could not set value, got error: {res:?}");
+
+    let found: Option<LatLng> = shared.get_json(bucket, "a").await.unwrap();
+    assert!(found.is_some_and(|found| found.is_close_to(&location)), "Clones should share values");
+    assert!(store.get(bucket, "b").await.unwrap().is_none());
+
+    store.set(bucket, "b", String::from("{}")).await.unwrap();
+    let mut keys = store.keys(bucket).await.unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["a", "b"]);
+
+    store.remove(bucket, "a").await.unwrap();
+    assert!(store.get(bucket, "a").await.unwrap().is_none());
+
+    store.clear(bucket).await.unwrap();
+    assert!(store.keys(bucket).await.unwrap().is_empty());
+
+    assert_compare_and_set(store.as_ref(), bucket).await;
+    store.clear(bucket).await.unwrap();
+}
+
+async fn assert_compare_and_set(store: &dyn CacheStore, bucket: &str) {
+    assert!(store.compare_and_set(bucket, "c", None, String::from("1")).await.unwrap(), "Expected a missing value to be set");
+    assert!(!store.compare_and_set(bucket, "c", None, String::from("2")).await.unwrap(), "Expected a value that was set since not to be replaced");
+    assert!(!store.compare_and_set(bucket, "c", Some("0"), String::from("2")).await.unwrap(), "Expected a changed value not to be replaced");
+    assert!(store.compare_and_set(bucket, "c", Some("1"), String::from("2")).await.unwrap(), "Expected the value read to be replaced");
+    assert_eq!(store.get(bucket, "c").await.unwrap().as_deref(), Some("2"));
+}
@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+use nujade_backend::{graphql::{reservations::FormReservation, notifications::model::NotificationKind}, types::phone::Phone, market::{geocoder::mock_location, config::MarketConfig, MarketMockConfig}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_notifies_riders_once_when_the_driver_is_close() {
+    let market = common::setup_with(MarketMockConfig {
+        market: MarketConfig {
+            approaching_within: Duration::hours(24),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000005").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("9D3EB184-5F60-4192-BDCE-3F4A5B6C7D8E").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+
+    for _ in 0..3 {
+        let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+        assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+    }
+
+    let is_told = market.event.is_driver_approaching(&id_event, &id_reservation).await.unwrap();
+    assert!(is_told, "Expected the rider to have been told on the ping");
+
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    let approaching = notifications.iter().filter(|notification| notification.kind == NotificationKind::DriverApproaching).count();
+    assert_eq!(approaching, 1, "Expected one approaching notification across the pings, got: {:?}", notifications);
+}
@@ -0,0 +1,91 @@
+use std::str::FromStr;
+
+use nujade_backend::{graphql::{reservations::{FormReservation, messages::ReservationGet}, drivers::DBDriver, trails::model::DBDriverPing, reports::model::{DriverReport, FormSemester, Term}}, types::phone::Phone, market::geocoder::mock_location};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_totals_what_drivers_did() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000008").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("C061E4B7-8293-44C5-A0F1-6C7D8E9FA0B1").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 2,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    assert!(market.driver.accept(&driver.id, &id_reservation).await.is_ok());
+    assert!(market.driver.arrive(&id_event, &driver.id).await.is_ok());
+    assert!(market.driver.pickup(&id_event, &driver.id).await.is_ok());
+    let dropoff_res = market.driver.dropoff(&id_event, &driver.id).await;
+    assert!(dropoff_res.is_ok(), "Dropoff failed, got {:?}", dropoff_res);
+
+    let reservation = market.db.send(ReservationGet { id: id_reservation }).await.unwrap().unwrap();
+    assert!(reservation.is_complete);
+
+    let drivers = vec![DBDriver {
+        id: driver.id,
+        phone: driver_phone.to_string(),
+        id_event,
+        obsolete_at: None,
+        id_vehicle: None,
+    }];
+    let ping = |idx: i32, lat: f64, lng: f64, at: i32| DBDriverPing { id: idx, id_event, id_driver: driver.id, lat, lng, at };
+    let pings = vec![
+        ping(1, mock_location::TIGER_BLVD_LATLNG.lat, mock_location::TIGER_BLVD_LATLNG.lng, 1000),
+        ping(2, mock_location::BENET_HALL_LATLNG.lat, mock_location::BENET_HALL_LATLNG.lng, 1060),
+        ping(3, mock_location::CSP_LATLNG.lat, mock_location::CSP_LATLNG.lng, 1120),
+        // The driver was offline for an hour, this is not counted
+        ping(4, mock_location::TIGER_BLVD_LATLNG.lat, mock_location::TIGER_BLVD_LATLNG.lng, 4720),
+    ];
+
+    let report = DriverReport::build(&drivers, &pings, &[reservation]);
+    assert_eq!(report.rows.len(), 1);
+    let row = &report.rows[0];
+    let distance = mock_location::TIGER_BLVD_LATLNG.distance_meters(&mock_location::BENET_HALL_LATLNG)
+        + mock_location::BENET_HALL_LATLNG.distance_meters(&mock_location::CSP_LATLNG);
+    assert_eq!(row.phone, driver_phone);
+    assert_eq!(row.events, 1);
+    assert_eq!(row.trips, 1);
+    assert_eq!(row.passengers, 2);
+    assert_eq!(row.active_seconds, 120);
+    assert!((row.distance_meters - distance).abs() < 0.001);
+
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "phone,events,trips,passengers,distance_miles,active_hours");
+    assert!(lines[1].starts_with(&format!("{},1,1,2,", driver_phone)));
+
+    let loaded = DriverReport::load(&market.db, vec![id_event]).await;
+    assert!(loaded.is_ok(), "Could not load the report, got {:?}", loaded);
+
+    let semester = FormSemester { year: 2024, term: Term::Fall };
+    assert_eq!(semester.filename(), "drivers-2024-fall.csv");
+    let (start, end) = semester.range().expect("Expected a range");
+    assert_eq!(start, 1719792000);
+    assert_eq!(end, 1735689600);
+
+    let range = FormSemester { year: 2100, term: Term::Spring }.range();
+    assert!(range.is_none(), "Expected a year past the timestamps to be rejected, got {:?}", range);
+    let range = FormSemester { year: i32::MAX, term: Term::Fall }.range();
+    assert!(range.is_none(), "Expected the largest year to be rejected, got {:?}", range);
+}
@@ -0,0 +1,74 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::StreamExt;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, MarketMockConfig, messanger::memory::MessangerMemory, messages::MessageMarket, types::StreamMessageMarket}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+async fn next_matching(stream: &mut StreamMessageMarket, is_match: impl Fn(&MessageMarket) -> bool) -> MessageMarket {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+            .expect("Timed out waiting for a message")
+            .expect("The stream ended")
+            .expect("Got an error from the stream");
+        if is_match(&message) { return message }
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_publishes_driver_status_pool_and_strategy_changes() {
+    let market = common::setup_with(MarketMockConfig {
+        messanger: Box::new(MessangerMemory::new()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    let mut stream = market.messanger.subscribe(format!("event:{id_event}"), None).await.expect("Could not subscribe");
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let message = next_matching(&mut stream, |message| matches!(message, MessageMarket::DriverOnline(_))).await;
+    assert!(matches!(&message, MessageMarket::DriverOnline(msg) if msg.id_driver == driver.id && msg.id_event == id_event), "Expected the driver to come online, got: {:?}", message);
+
+    let rider_phone = Phone::new("+18002000046").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("5e2b9d71-0c4a-4f8e-b046-46a1c3d5e046").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await.expect("Could not reserve");
+
+    let message = next_matching(&mut stream, |message| matches!(message, MessageMarket::PoolChanged(_))).await;
+    let MessageMarket::PoolChanged(pool) = message else { unreachable!() };
+    assert!(pool.pool_size >= 1, "Expected the reservation in the pool, got: {:?}", pool);
+    assert!(pool.oldest_wait.is_some(), "Expected the wait of the oldest reservation");
+
+    let res = market.driver.accept(&driver.id, &reservation.id).await;
+    assert!(res.is_ok(), "Could not accept, got error: {:?}", res);
+
+    let message = next_matching(&mut stream, |message| matches!(message, MessageMarket::StrategyChanged(_))).await;
+    assert!(matches!(&message, MessageMarket::StrategyChanged(msg) if msg.strategy.id == driver.id), "Expected the driver's new queue, got: {:?}", message);
+
+    let res = market.reservation.cancel(&reservation.id).await;
+    assert!(res.is_ok(), "Could not cancel, got error: {:?}", res);
+
+    let res = market.event.remove_driver(&id_event, &driver).await;
+    assert!(res.is_ok(), "Could not remove the driver, got error: {:?}", res);
+
+    let message = next_matching(&mut stream, |message| matches!(message, MessageMarket::DriverOffline(_))).await;
+    assert!(matches!(&message, MessageMarket::DriverOffline(msg) if msg.id_driver == driver.id), "Expected the driver to go offline, got: {:?}", message);
+}
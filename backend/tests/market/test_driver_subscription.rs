@@ -0,0 +1,79 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::StreamExt;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, MarketMockConfig, messanger::memory::MessangerMemory, messages::MessageMarket, types::StreamMessageMarket}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+async fn next_message(stream: &mut StreamMessageMarket) -> MessageMarket {
+    tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+        .expect("Timed out waiting for a message")
+        .expect("The stream ended")
+        .expect("Got an error from the stream")
+}
+
+#[actix_web::main]
+#[test]
+async fn it_streams_driver_messages_through_memory() {
+    let messanger = MessangerMemory::new();
+    let market = common::setup_with(MarketMockConfig {
+        messanger: Box::new(messanger.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let mut stream_driver = market.messanger.subscribe(format!("driver:{}", driver.id), None).await.expect("Could not subscribe");
+    let mut stream_offers = market.messanger.subscribe(format!("drivers:{id_event}"), None).await.expect("Could not subscribe");
+    assert_eq!(messanger.topic_count(), 2);
+
+    let rider_phone = Phone::new("+18002000040").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("0b6f4a1e-40a2-4c5e-9c2d-4f1d2a0e0400").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await.expect("Could not reserve");
+
+    let message = next_message(&mut stream_offers).await;
+    assert!(matches!(&message, MessageMarket::DriverOffer(offer) if offer.id_reservation == id_reservation && offer.passenger_count == 1), "Expected an offer, got: {:?}", message);
+
+    let res = market.driver.accept(&driver.id, &reservation.id).await;
+    assert!(res.is_ok(), "Could not accept, got error: {:?}", res);
+
+    let message = next_message(&mut stream_driver).await;
+    assert!(matches!(&message, MessageMarket::DriverStrategy(msg) if msg.strategy.id == driver.id), "Expected the driver strategy, got: {:?}", message);
+
+    let res = market.reservation.message_driver(&reservation.id, String::from("I'm by the fountain")).await;
+    assert!(res.is_ok(), "Could not message the driver, got error: {:?}", res);
+
+    let message = next_message(&mut stream_driver).await;
+    assert!(matches!(&message, MessageMarket::RiderMessage(msg) if msg.message == "I'm by the fountain"), "Expected the rider message, got: {:?}", message);
+
+    let res = market.reservation.cancel(&reservation.id).await;
+    assert!(res.is_ok(), "Could not cancel, got error: {:?}", res);
+
+    let message = next_message(&mut stream_driver).await;
+    assert!(matches!(&message, MessageMarket::ReservationCancelled(msg) if msg.reservation.id == id_reservation), "Expected the cancellation, got: {:?}", message);
+
+    let message = next_message(&mut stream_driver).await;
+    assert!(matches!(&message, MessageMarket::DriverStrategy(msg) if msg.strategy.id == driver.id), "Expected the updated strategy, got: {:?}", message);
+
+    drop(stream_driver);
+    drop(stream_offers);
+    assert_eq!(messanger.topic_count(), 0);
+}
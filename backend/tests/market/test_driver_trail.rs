@@ -0,0 +1,70 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, clock::ClockManual, MarketMockConfig}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_records_the_trip_of_a_driver() {
+    let clock = ClockManual::new(2_000_000);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000007").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("BF50D3A6-7182-43B4-9FE0-5B6C7D8E9FA0").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    let trip_res = market.driver.get_trip(&id_reservation).await;
+    assert!(trip_res.is_err(), "Expected no trip before the reservation is accepted");
+
+    clock.advance(Duration::seconds(10));
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+
+    for location in [mock_location::CSP_LATLNG, mock_location::BENET_HALL_LATLNG] {
+        clock.advance(Duration::seconds(30));
+        let ping_res = market.driver.ping(&id_event, &driver.id, &location).await;
+        assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+    }
+
+    let trip = market.driver.get_trip(&id_reservation).await.unwrap();
+    assert_eq!(trip.id_driver, driver.id);
+    assert_eq!(trip.started_at, 2_000_010);
+    assert!(trip.ended_at.is_none());
+    let times: Vec<i32> = trip.points.iter().map(|point| point.at).collect();
+    assert_eq!(times, vec![2_000_040, 2_000_070], "Expected only the pings after accepting");
+
+    let geojson = trip.geojson();
+    assert_eq!(geojson["geometry"]["type"], "LineString");
+    assert_eq!(geojson["geometry"]["coordinates"][1][0], mock_location::BENET_HALL_LATLNG.lng);
+
+    clock.advance(Duration::days(31));
+    let removed = market.driver.prune_trails().await.unwrap();
+    assert!(removed >= 3);
+    let trail = market.driver.get_trail(&driver.id, 2_000_000, 2_000_100).await.unwrap();
+    assert!(trail.is_empty());
+}
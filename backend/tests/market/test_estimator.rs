@@ -0,0 +1,58 @@
+use std::{str::FromStr, collections::HashMap};
+
+use chrono::Duration;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::geocoder::mock_location, estimator::{Estimator, model::EstimatorConfig}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_only_publishes_changed_estimates() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+    let config = EstimatorConfig {
+        close_within: Duration::hours(24),
+        ..Default::default()
+    };
+
+    let mut published = HashMap::new();
+    let stats = Estimator::refresh_event(&market, &config, &mut published, &id_event).await.unwrap();
+    assert_eq!(stats.published, 0);
+    assert_eq!(stats.next_interval, config.interval_idle, "Expected an event with nothing to do to be idle");
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000004").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("8C2DA073-4E5F-4081-ACBD-2E3F4A5B6C7D").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+
+    let stats = Estimator::refresh_event(&market, &config, &mut published, &id_event).await.unwrap();
+    assert_eq!(stats.published, 1);
+    assert_eq!(stats.unchanged, 0);
+    assert_eq!(stats.next_interval, config.interval_close, "Expected the driver to be close to the pickup");
+    assert!(published.contains_key(&id_reservation));
+
+    let stats = Estimator::refresh_event(&market, &config, &mut published, &id_event).await.unwrap();
+    assert_eq!(stats.published, 0, "Expected the same estimate not to be sent again");
+    assert_eq!(stats.unchanged, 1);
+}
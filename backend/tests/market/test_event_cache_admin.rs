@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, estimate::driver::stop::model::DriverStopEstimation}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_inspects_and_resets_the_event_cache() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000003").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("7B1C9F62-3D4E-4F70-9BAC-1D2E3F4A5B6C").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let res_reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(res_reservation.is_ok(), "Could not reserve, got error: {:?}", res_reservation);
+
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+
+    let snapshot = market.event.get_cache_snapshot(&id_event).await.unwrap();
+    let strategy = snapshot.strategy.expect("Expected a strategy in cache");
+    assert!(strategy.drivers.get(&driver.id).unwrap().get_reservations().contains(&id_reservation));
+    assert!(snapshot.driver_locations.contains_key(&driver.id));
+    assert!(snapshot.estimates_drivers.is_some());
+
+    let rebuild_res = market.event.rebuild_cache(&id_event).await;
+    assert!(rebuild_res.is_ok(), "Rebuild failed: {rebuild_res:?}");
+    assert!(rebuild_res.unwrap().driver(&driver.id).unwrap().dest.is_some());
+
+    let reset_res = market.event.reset_driver_strategy(&id_event, &driver.id).await;
+    assert!(reset_res.is_ok(), "Reset failed: {reset_res:?}");
+    let reset = reset_res.unwrap().driver(&driver.id).unwrap();
+    assert!(reset.dest.is_none());
+    assert!(reset.queue.is_empty());
+
+    let avaliable = market.event.get_avaliable_reservation(&id_event, &driver.id).await.unwrap();
+    assert_eq!(avaliable.map(|reservation| reservation.id), Some(id_reservation), "Expected the reservation back in the pool");
+
+    // Riders already in the car stay with the driver
+    let accept_res = market.driver.accept(&driver.id, &id_reservation).await;
+    assert!(accept_res.is_ok(), "Accept not ok, got error {:?}", accept_res);
+    let arrive_res = market.driver.arrive(&id_event, &driver.id).await;
+    assert!(arrive_res.is_ok(), "Arrive not ok, got error {:?}", arrive_res);
+    let pickup_res = market.driver.pickup(&id_event, &driver.id).await;
+    assert!(pickup_res.is_ok(), "Pickup not ok, got error {:?}", pickup_res);
+
+    let reset_res = market.event.reset_driver_strategy(&id_event, &driver.id).await;
+    assert!(reset_res.is_ok(), "Reset failed: {reset_res:?}");
+    let reset = reset_res.unwrap().driver(&driver.id).unwrap();
+    assert!(reset.picked_up.contains_key(&id_reservation), "Expected the rider to still be in the car, got {reset:?}");
+    assert!(matches!(reset.dest, Some(DriverStopEstimation::Event(_))), "Expected the driver to still be going to the event, got {reset:?}");
+    assert!(reset.queue.is_empty());
+
+    let avaliable = market.event.get_avaliable_reservation(&id_event, &driver.id).await.unwrap();
+    assert_ne!(avaliable.map(|reservation| reservation.id), Some(id_reservation), "Expected the picked up reservation to stay out of the pool");
+
+    let evict_res = market.event.evict_cache(&id_event).await;
+    assert!(evict_res.is_ok(), "Evict failed: {evict_res:?}");
+    let snapshot = market.event.get_cache_snapshot(&id_event).await.unwrap();
+    assert!(snapshot.strategy.is_none());
+    assert!(snapshot.driver_locations.is_empty());
+}
@@ -0,0 +1,84 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::StreamExt;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, clock::ClockManual, MarketMockConfig, messanger::memory::MessangerMemory, messages::{MessageMarket, MessageEventSummary}, types::StreamMessageMarket}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+async fn next_summary(stream: &mut StreamMessageMarket) -> MessageEventSummary {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+            .expect("Timed out waiting for a summary")
+            .expect("The stream ended")
+            .expect("Got an error from the stream");
+        if let MessageMarket::EventSummary(summary) = message { return summary }
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_publishes_event_summaries_to_the_org_when_the_pool_changes() {
+    let clock = ClockManual::new(1_000_000);
+    let market = common::setup_with(MarketMockConfig {
+        messanger: Box::new(MessangerMemory::new()),
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let mut stream = market.messanger.subscribe(format!("org:{}", common::get_id_org()), None).await.expect("Could not subscribe");
+
+    let rider_phone = Phone::new("+18002000045").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("9a4e1c27-5b0d-4f63-a045-45c2d7e1f045").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 2,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    // Summaries are sent at most every few seconds
+    clock.advance(chrono::Duration::seconds(6));
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await.expect("Could not reserve");
+
+    let summary = next_summary(&mut stream).await;
+    assert_eq!(summary.id_event, id_event);
+    assert_eq!(summary.pool_size, 1);
+    assert_eq!(summary.drivers, 1);
+    assert_eq!(summary.drivers_busy, 0);
+    assert_eq!(summary.longest_pickup, None);
+
+    clock.advance(chrono::Duration::seconds(6));
+    let res = market.driver.accept(&driver.id, &reservation.id).await;
+    assert!(res.is_ok(), "Could not accept, got error: {:?}", res);
+
+    let summary = next_summary(&mut stream).await;
+    assert_eq!(summary.pool_size, 0);
+    assert_eq!(summary.drivers_busy, 1);
+    assert!(summary.longest_pickup.is_some(), "Expected the queued rider's pickup time");
+
+    clock.advance(chrono::Duration::seconds(6));
+    let res = market.reservation.cancel(&reservation.id).await;
+    assert!(res.is_ok(), "Could not cancel, got error: {:?}", res);
+
+    let summary = next_summary(&mut stream).await;
+    assert_eq!(summary.pool_size, 0);
+    assert_eq!(summary.drivers_busy, 0);
+
+    // Right after the last one nothing is sent, the next tick of the estimator catches up
+    market.event.publish_summary(&id_event).await;
+    let skipped = tokio::time::timeout(Duration::from_millis(200), stream.next()).await;
+    assert!(skipped.is_err(), "Expected no summary so soon after the last one, got {:?}", skipped.map(|message| message.map(|message| message.is_ok())));
+}
@@ -0,0 +1,91 @@
+use std::sync::{Arc, atomic::{AtomicBool, AtomicU32, Ordering}};
+
+use async_trait::async_trait;
+use chrono::Duration;
+use nujade_backend::{graphql::{reservations::{FormReservation, FormReservationGeocoded}, geo::model::LatLng}, market::{types::MarketResult, error::ErrorMarket, geocoder::{Geocoder, EstimateConfidence, mock_location, resilient::{CircuitBreaker, GeocoderResilient, ResilienceConfig}}}};
+
+#[doc = "A geocoder that fails until it is told the service is back"]
+#[derive(Debug, Clone)]
+struct GeocoderFlaky {
+    is_down: Arc<AtomicBool>,
+    calls: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl Geocoder for GeocoderFlaky {
+    fn box_clone(&self) -> Box<dyn Geocoder> {
+        Box::new(self.clone())
+    }
+
+    async fn geocode_form(&self, _form: &FormReservation) -> MarketResult<FormReservationGeocoded> {
+        Err(ErrorMarket::GeocodingFailed)
+    }
+
+    async fn estimate(&self, _from: LatLng, _to: LatLng) -> MarketResult<Duration> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.is_down.load(Ordering::SeqCst) { Err(ErrorMarket::GoogleMapsError) } else { Ok(Duration::seconds(120)) }
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_lets_one_request_through_after_the_cooldown() {
+    let breaker = CircuitBreaker::new(2, std::time::Duration::from_millis(50));
+    assert!(breaker.allows_request());
+
+    breaker.record_failure();
+    assert!(breaker.allows_request(), "Expected the circuit to stay closed under the threshold");
+    breaker.record_failure();
+    assert!(breaker.is_open(), "Expected the circuit to open at the threshold");
+    assert!(!breaker.allows_request());
+
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    assert!(breaker.allows_request(), "Expected a trial request after the cooldown");
+    assert!(!breaker.allows_request(), "Expected only one trial request at a time");
+
+    // A failed trial opens the circuit for another cooldown
+    breaker.record_failure();
+    assert!(!breaker.allows_request());
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    assert!(breaker.allows_request(), "Expected a trial request after the second cooldown");
+
+    breaker.record_success();
+    assert!(!breaker.is_open(), "Expected a good trial to close the circuit");
+    assert!(breaker.allows_request());
+    assert!(breaker.allows_request());
+}
+
+#[actix_web::main]
+#[test]
+async fn it_falls_back_to_the_straight_line_while_the_geocoder_is_down() {
+    let is_down = Arc::new(AtomicBool::new(true));
+    let calls = Arc::new(AtomicU32::new(0));
+    let inner = GeocoderFlaky { is_down: is_down.clone(), calls: calls.clone() };
+    let geocoder = GeocoderResilient::new(Box::new(inner), ResilienceConfig {
+        retries: 1,
+        backoff: std::time::Duration::from_millis(1),
+        failure_threshold: 1,
+        cooldown: std::time::Duration::from_millis(50),
+        ..Default::default()
+    });
+
+    let from = mock_location::BENET_HALL_LATLNG;
+    let to = mock_location::TIGER_BLVD_LATLNG;
+
+    let est = geocoder.estimate_route(from, to).await.expect("Expected a fallback estimate");
+    assert_eq!(est.confidence, EstimateConfidence::Low);
+    assert_eq!(calls.load(Ordering::SeqCst), 2, "Expected the request and one retry");
+    assert!(est.duration > Duration::zero(), "Expected a straight line estimate, got {:?}", est.duration);
+
+    // The circuit is open, so the geocoder is not asked
+    let est = geocoder.estimate_route(from, to).await.expect("Expected a fallback estimate");
+    assert_eq!(est.confidence, EstimateConfidence::Low);
+    assert_eq!(calls.load(Ordering::SeqCst), 2, "Expected no request while the circuit is open");
+
+    is_down.store(false, Ordering::SeqCst);
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    let est = geocoder.estimate_route(from, to).await.expect("Expected an estimate");
+    assert_eq!(est.confidence, EstimateConfidence::High);
+    assert_eq!(est.duration, Duration::seconds(120));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
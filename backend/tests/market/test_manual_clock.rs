@@ -0,0 +1,48 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, clock::ClockManual, MarketMockConfig}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_stamps_reservations_with_the_market_clock() {
+    let clock = ClockManual::new(1_000_000);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000002").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("0F1C3E29-7C8B-4D4B-9E0D-5B7B2B1F9A11").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await;
+    assert!(reservation.is_ok(), "Could not reserve, got error: {:?}", reservation);
+    assert_eq!(reservation.unwrap().made_at, 1_000_000);
+
+    clock.advance(Duration::minutes(5));
+    assert_eq!(market.clock.now(), 1_000_300);
+
+    let cancelled = market.reservation.cancel(&id_reservation).await;
+    assert!(cancelled.is_ok(), "Could not cancel, got error: {:?}", cancelled);
+    assert_eq!(cancelled.unwrap().cancelled_at, Some(1_000_300));
+}
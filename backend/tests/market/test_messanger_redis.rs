@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use dotenv::dotenv;
+use futures::StreamExt;
+use nujade_backend::{graphql::geo::model::LatLng, market::{messanger::{Messanger, redis::MessangerRedis}, messages::MessageMarket, types::StreamMessageMarket}};
+
+fn redis_url() -> String {
+    dotenv().ok();
+    std::env::var("REDIS_URL").unwrap_or(String::from("redis://127.0.0.1:6379"))
+}
+
+async fn next_message(stream: &mut StreamMessageMarket) -> MessageMarket {
+    tokio::time::timeout(Duration::from_secs(10), stream.next()).await
+        .expect("Timed out waiting for a message")
+        .expect("The stream ended")
+        .expect("Got an error from the stream")
+}
+
+async fn next_lat(stream: &mut StreamMessageMarket) -> f64 {
+    let message = next_message(stream).await;
+    match message {
+        MessageMarket::DriverLocation(msg) => msg.location.lat,
+        _ => panic!("Expected a driver location, got: {:?}", message),
+    }
+}
+
+fn location(lat: f64) -> MessageMarket {
+    MessageMarket::new_driver_location(1, LatLng { lat, lng: -82.83 })
+}
+
+#[actix_web::main]
+#[test]
+async fn it_rejects_a_bad_redis_url() {
+    let res = MessangerRedis::new("not a url");
+    assert!(res.is_err(), "Expected an error for a bad url, got {:?}", res);
+}
+
+// Killing the connection drops every pubsub connection on the server, so this is one test to not disturb the others
+#[actix_web::main]
+#[test]
+async fn it_counts_bad_messages_and_reconnects() {
+    let url = redis_url();
+    let messanger = MessangerRedis::new(&url).expect("Could not make the messanger");
+    let key = String::from("res:test-messanger-redis");
+
+    let mut stream = messanger.subscribe(key.clone(), None).await.expect("Could not subscribe");
+    // Nothing is sent to this one before its connection drops
+    let key_quiet = String::from("res:test-messanger-redis-quiet");
+    let mut stream_quiet = messanger.subscribe(key_quiet.clone(), None).await.expect("Could not subscribe");
+
+    let client = redis::Client::open(url.as_str()).unwrap();
+    let mut conn = client.get_async_connection().await.expect("Could not connect to redis");
+    let _: i64 = redis::cmd("PUBLISH").arg(&key).arg("not a message").query_async(&mut conn).await.expect("Could not publish");
+    messanger.publish(key.clone(), location(1.0)).await.expect("Could not publish");
+
+    assert_eq!(next_lat(&mut stream).await, 1.0, "Expected the bad message to be skipped");
+    assert_eq!(messanger.stats().decode_errors, 1);
+
+    // Drop the subscriber's connection from the server side, then send while it is reconnecting
+    let killed: i64 = redis::cmd("CLIENT").arg("KILL").arg("TYPE").arg("pubsub").query_async(&mut conn).await.expect("Could not kill the connection");
+    assert!(killed >= 1, "Expected the subscriber connection to be killed");
+    messanger.publish(key.clone(), location(2.0)).await.expect("Could not publish");
+    messanger.publish(key_quiet.clone(), location(5.0)).await.expect("Could not publish");
+
+    assert_eq!(next_lat(&mut stream).await, 2.0, "Expected the message sent while reconnecting");
+    assert_eq!(next_lat(&mut stream_quiet).await, 5.0, "Expected the message sent while reconnecting without a cursor");
+    messanger.publish(key.clone(), location(3.0)).await.expect("Could not publish");
+    assert_eq!(next_lat(&mut stream).await, 3.0);
+
+    let stats = messanger.stats();
+    assert!(stats.reconnects >= 1, "Expected a reconnect to be counted, got {:?}", stats);
+    assert_eq!(stats.published, 4);
+    assert_eq!(stats.publish_errors, 0);
+    assert_eq!(stats.subscriptions, 2);
+    assert_eq!(stats.decode_errors, 1);
+}
+
+#[actix_web::main]
+#[test]
+async fn it_tells_subscribers_when_the_log_no_longer_has_their_cursor() {
+    let messanger = MessangerRedis::new(&redis_url()).expect("Could not make the messanger");
+    let key = String::from("res:test-messanger-redis-truncated");
+
+    messanger.publish(key.clone(), location(1.0)).await.expect("Could not publish");
+
+    // A cursor from long before anything in the log
+    let mut stream = messanger.subscribe(key.clone(), Some(String::from("1-0"))).await.expect("Could not subscribe");
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::ReplayTruncated(msg) if msg.since == "1-0"), "Expected the replay to be truncated, got: {:?}", message);
+    assert_eq!(next_lat(&mut stream).await, 1.0);
+
+    // Following a snapshot starts after the last logged message
+    let (cursor, mut stream) = messanger.subscribe_from_end(key.clone()).await.expect("Could not subscribe");
+    assert!(cursor.is_some(), "Expected the snapshot to have a cursor");
+    messanger.publish(key.clone(), location(2.0)).await.expect("Could not publish");
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::DriverLocation(msg) if msg.location.lat == 2.0), "Expected only the message after the snapshot, got: {:?}", message);
+    assert_ne!(message.cursor(), cursor.as_deref(), "Expected the message to be after the snapshot");
+}
@@ -0,0 +1,92 @@
+use nujade_backend::{graphql::reservations::{FormReservation, stops::model::{FormReservationStop, FormLatLng}}, market::{geocoder::{mock_location, scenario::MockScenario}, error::ErrorMarket}};
+
+#[path = "../common.rs"]
+mod common;
+
+const SCENARIO: &str = r#"{
+    "locations": [
+        { "id": "Depot", "address": "Depot", "lat": 34.6951, "lng": -82.8341 },
+        { "id": "Stadium", "address": "Stadium", "lat": 34.6788, "lng": -82.8432 },
+        { "id": "CSP", "address": "CSP", "lat": 34.682813, "lng": -82.837402 }
+    ],
+    "legs": [
+        { "from": "Depot", "to": "Stadium", "seconds": 420 },
+        { "from": "CSP", "to": "Stadium", "seconds": 240 }
+    ]
+}"#;
+
+#[actix_web::main]
+#[test]
+async fn it_estimates_with_scenario_legs() {
+    let scenario = MockScenario::from_json(SCENARIO).expect("Invalid scenario");
+    let depot = scenario.location("Depot").unwrap().latlng();
+    let stadium = scenario.location("Stadium").unwrap().stop();
+
+    let market = common::setup_with_scenario(scenario);
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver_res = market.driver.find(&id_event, &driver_phone).await;
+    assert!(matches!(driver_res, Ok(_)), "Error getting the event driver. Got error: `{:?}`", driver_res);
+
+    let driver = driver_res.unwrap();
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &depot).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![stadium],
+    };
+
+    let est_res = market.event.get_estimate_reservation_new(&id_event, &form).await;
+    assert!(est_res.is_ok(), "Estimation of new event failed: {est_res:?}");
+    let est = est_res.unwrap();
+
+    assert_eq!(est.time_estimate.pickup.num_minutes(), 7);
+    assert_eq!(est.time_estimate.arrival.num_minutes(), 11);
+    assert_eq!(est.queue_position, 0);
+}
+
+#[actix_web::main]
+#[test]
+async fn it_errors_on_unknown_place_id() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![FormReservationStop {
+            place_id: "Nowhere".to_owned(),
+            address: "Nowhere".to_owned(),
+            location: FormLatLng::new(0.0, 0.0),
+        }],
+    };
+
+    let est_res = market.event.get_estimate_reservation_new(&id_event, &form).await;
+    assert!(matches!(est_res, Err(ErrorMarket::NoGeocodeResults)), "Expected no geocode results, got {est_res:?}");
+}
+
+#[test]
+fn it_estimates_unknown_pairs_by_distance() {
+    let scenario = MockScenario::from_json(SCENARIO).expect("Invalid scenario");
+    let depot = scenario.location("Depot").unwrap().latlng();
+    let csp = scenario.location("CSP").unwrap().latlng();
+
+    assert!(scenario.leg(&depot, &csp).is_none());
+
+    let est = scenario.estimate_by_distance(&depot, &csp);
+    assert!(est.num_seconds() > 0);
+    assert_eq!(est, scenario.estimate_by_distance(&csp, &depot));
+}
@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use nujade_backend::{graphql::{reservations::FormReservation, users::{FormUserInsert, messages::{UserUpdate, UserSMSOpt}}, notifications::model::{NotificationKind, NotificationStatus}}, types::phone::Phone, market::geocoder::mock_location};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+fn form() -> FormReservation {
+    FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_delivers_notifications_from_the_outbox_and_retries_failures() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000047").expect("Invalid phone number");
+    market.db.send(UserUpdate {
+        phone: rider_phone.clone(),
+        form: FormUserInsert { name: String::from("Outbox Rider"), profile_image: None },
+    }).await.expect("No db conn").expect("Could not create the rider");
+    market.db.send(UserSMSOpt { phone: rider_phone.clone(), opt_in: true }).await.expect("No db conn").expect("Could not opt in to SMS");
+
+    let id_reservation = Uuid::from_str("4c8a2e90-1d7b-4f35-9047-47b3e5a1c047").expect("Invalid uuid");
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form()).await.expect("Could not reserve");
+
+    let res = market.driver.accept(&driver.id, &reservation.id).await;
+    assert!(res.is_ok(), "Could not accept, got error: {:?}", res);
+
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    assert_eq!(notifications.len(), 1, "Expected one notification, got: {:?}", notifications);
+    assert_eq!(notifications[0].kind, NotificationKind::DriverAccepted);
+    assert_eq!(notifications[0].status, NotificationStatus::Pending);
+
+    // Enqueueing the same notification again does nothing
+    let res = market.notification.send_driver_accepted(&reservation, &driver.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    assert_eq!(notifications.len(), 1, "Expected the duplicate to be dropped, got: {:?}", notifications);
+
+    // A rider without an account can not be pushed to, so their notification is retried
+    let rider_phone_unknown = Phone::new("+18002000048").expect("Invalid phone number");
+    let id_reservation_unknown = Uuid::from_str("4c8a2e90-1d7b-4f35-9048-48b3e5a1c048").expect("Invalid uuid");
+    let reservation_unknown = market.reservation.create(&rider_phone_unknown, &id_reservation_unknown, &id_event, form()).await.expect("Could not reserve");
+    let res = market.notification.send_driver_accepted(&reservation_unknown, &driver.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+
+    // Other tests share the database, so only the notifications made here are delivered
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+    assert_eq!(report.sent, 1, "Expected the acceptance to be sent, got: {:?}", report);
+    let report = market.notification.deliver_due_for_reservation(&id_reservation_unknown).await.expect("Could not deliver");
+    assert_eq!(report.retrying, 1, "Expected the rider without an account to be retried, got: {:?}", report);
+
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    assert_eq!(notifications[0].status, NotificationStatus::Sent);
+    assert_eq!(notifications[0].attempts, 1);
+    assert!(notifications[0].sent_at.is_some(), "Expected when it was sent");
+
+    let notifications = market.notification.list_for_reservation(&id_reservation_unknown).await.expect("Could not list notifications");
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].status, NotificationStatus::Pending);
+    assert_eq!(notifications[0].attempts, 1);
+    assert!(notifications[0].last_error.is_some(), "Expected the error of the failed attempt");
+    assert!(notifications[0].next_attempt_at > notifications[0].created_at, "Expected the retry to be later");
+}
@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use nujade_backend::{graphql::{reservations::{FormReservation, messages::ReservationsClear}, users::{FormUserInsert, messages::{UserUpdate, UserSMSOpt}}, events::{DBEventInsertable, messages::EventUpdate}, notifications::{messages::{NotificationPreferenceUpsert, NotificationMuteInsert, NotificationMuteDelete}, model::{Notification, NotificationKind, NotificationStatus, NotificationChannel, DBNotificationPreference, DBNotificationMute}}}, types::phone::Phone, market::{geocoder::mock_location, Market}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+fn form() -> FormReservation {
+    FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    }
+}
+
+async fn add_rider(market: &Market, phone: &Phone, name: &str) {
+    market.db.send(UserUpdate {
+        phone: phone.clone(),
+        form: FormUserInsert { name: name.to_owned(), profile_image: None },
+    }).await.expect("No db conn").expect("Could not create the rider");
+    market.db.send(UserSMSOpt { phone: phone.clone(), opt_in: true }).await.expect("No db conn").expect("Could not opt in to SMS");
+}
+
+#[doc = "The newest notification of a kind, earlier runs can leave older ones"]
+async fn latest(market: &Market, id_reservation: &Uuid, kind: NotificationKind) -> Notification {
+    let notifications = market.notification.list_for_reservation(id_reservation).await.expect("Could not list notifications");
+    notifications.into_iter().rev().find(|notification| notification.kind == kind).expect("Expected a notification of the kind")
+}
+
+#[actix_web::main]
+#[test]
+async fn it_skips_kinds_the_rider_turned_off_and_tells_them_about_cancellations() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver = market.driver.find(&id_event, &common::get_driver_phone()).await.expect("Error getting the event driver");
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000053").expect("Invalid phone number");
+    add_rider(&market, &rider_phone, "Preferences Rider").await;
+    let id_reservation = Uuid::from_str("4c8a2e90-1d7b-4f35-9053-53b3e5a1c053").expect("Invalid uuid");
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form()).await.expect("Could not reserve");
+
+    // Riders can turn off a kind of notification on a channel
+    market.db.send(NotificationPreferenceUpsert {
+        preference: DBNotificationPreference {
+            phone: rider_phone.clone(),
+            channel: NotificationChannel::Sms.as_str().to_owned(),
+            kind: NotificationKind::DriverArrived.as_str().to_owned(),
+            is_enabled: false,
+            updated_at: 0,
+        },
+    }).await.expect("No db conn").expect("Could not save the preference");
+    let res = market.notification.send_driver_arrival(&reservation, &driver.id, false).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+    assert_eq!(report.skipped, 1, "Expected the muted arrival to be skipped, got: {:?}", report);
+    assert_eq!(latest(&market, &id_reservation, NotificationKind::DriverArrived).await.status, NotificationStatus::Skipped);
+
+    let res = market.reservation.cancel(&id_reservation).await;
+    assert!(res.is_ok(), "Could not cancel, got error: {:?}", res);
+
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+    assert_eq!(report.sent, 1, "Expected the cancellation to be sent, got: {:?}", report);
+    let cancelled = latest(&market, &id_reservation, NotificationKind::ReservationCancelled).await;
+    assert_eq!(cancelled.status, NotificationStatus::Sent);
+    assert!(cancelled.message.as_deref().is_some_and(|message| message.contains("was cancelled")), "Expected the default wording, got: {:?}", cancelled.message);
+}
+
+#[actix_web::main]
+#[test]
+async fn it_announces_to_riders_unless_the_org_muted_announcements() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    // An event of its own, so other tests' riders aren't sent the announcements
+    let id_event = Uuid::from_str("4c8a2e90-1d7b-4f35-9054-54b3e5a1c054").expect("Invalid uuid");
+    let res = market.db.send(EventUpdate { event: DBEventInsertable {
+        id: id_event,
+        name: String::from("Announcement event"),
+        bio: None,
+        image_url: None,
+        time_start: 10,
+        time_end: 10,
+        reservations_start: 10,
+        reservations_end: 10,
+        id_location: common::get_id_location(),
+        id_org: common::get_id_org(),
+        obsolete_at: None,
+        published_at: None,
+        auto_arrival: false,
+    } }).await;
+    assert!(matches!(res, Ok(Ok(_))), "Could not create the event, got {:?}", res);
+    market.db.send(ReservationsClear { id_event }).await.expect("No db conn").expect("Could not clear reservations");
+
+    let rider_phone = Phone::new("+18002000054").expect("Invalid phone number");
+    add_rider(&market, &rider_phone, "Announcement Rider").await;
+    let id_reservation = Uuid::from_str("4c8a2e90-1d7b-4f35-9055-55b3e5a1c055").expect("Invalid uuid");
+    market.reservation.create(&rider_phone, &id_reservation, &id_event, form()).await.expect("Could not reserve");
+
+    // Riders who cancelled aren't told
+    let rider_phone_cancelled = Phone::new("+18002000055").expect("Invalid phone number");
+    let id_reservation_cancelled = Uuid::from_str("4c8a2e90-1d7b-4f35-9056-56b3e5a1c056").expect("Invalid uuid");
+    market.reservation.create(&rider_phone_cancelled, &id_reservation_cancelled, &id_event, form()).await.expect("Could not reserve");
+    market.reservation.cancel(&id_reservation_cancelled).await.expect("Could not cancel");
+
+    let res = market.notification.send_event_announcement(&id_event, "   ").await;
+    assert!(res.is_err(), "Expected an empty announcement to be refused");
+
+    let count = market.notification.send_event_announcement(&id_event, "  Shuttles leave from Lot B  ").await.expect("Could not announce");
+    assert_eq!(count, 1, "Expected only the rider with a ride to come to be told");
+
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+    assert_eq!(report.sent, 1, "Expected the announcement to be sent, got: {:?}", report);
+    let announcement = latest(&market, &id_reservation, NotificationKind::EventAnnouncement).await;
+    assert_eq!(announcement.message.as_deref(), Some("Update about Announcement event: Shuttles leave from Lot B"));
+
+    // Orgs can mute a kind for all of their riders
+    market.db.send(NotificationMuteInsert { mute: DBNotificationMute {
+        id_org: common::get_id_org(),
+        kind: NotificationKind::EventAnnouncement.as_str().to_owned(),
+        updated_at: 0,
+    }}).await.expect("No db conn").expect("Could not mute");
+
+    market.notification.send_event_announcement(&id_event, "Shuttles are running late").await.expect("Could not announce");
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await;
+
+    market.db.send(NotificationMuteDelete { id_org: common::get_id_org(), kind: NotificationKind::EventAnnouncement }).await.expect("No db conn").expect("Could not unmute");
+
+    let report = report.expect("Could not deliver");
+    assert_eq!(report.skipped, 1, "Expected the muted announcement to be skipped, got: {:?}", report);
+    assert_eq!(latest(&market, &id_reservation, NotificationKind::EventAnnouncement).await.status, NotificationStatus::Skipped);
+}
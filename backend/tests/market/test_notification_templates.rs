@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+use nujade_backend::{graphql::{reservations::{FormReservation, messages::ReservationsClear}, users::{FormUserInsert, messages::{UserUpdate, UserLocaleSet}}, events::{DBEventInsertable, messages::EventUpdate}, drivers::{messages::EventDriverUpdate, model::FormEventDriver}, notifications::{messages::NotificationTemplateUpsert, model::{NotificationKind, DBNotificationTemplate}}, push_subscriptions::{messages::PushSubscriptionUpsert, model::DBPushSubscriptionInsertable}}, types::{phone::Phone, locale::Locale}, market::{geocoder::mock_location, template, MarketMockConfig, pusher::mock::PushServiceMock}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+fn form() -> FormReservation {
+    FormReservation {
+        passenger_count: 2,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_renders_notifications_from_templates_in_the_riders_language() {
+    let push_service = PushServiceMock::new();
+    let market = common::setup_with(MarketMockConfig {
+        push_service: Box::new(push_service.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    // Without a template of their own riders get the default wording, filled in with the driver's vehicle
+    let rider_phone = Phone::new("+18002000050").expect("Invalid phone number");
+    market.db.send(UserUpdate {
+        phone: rider_phone.clone(),
+        form: FormUserInsert { name: String::from("Template Rider"), profile_image: None },
+    }).await.expect("No db conn").expect("Could not create the rider");
+
+    let id_reservation = Uuid::from_str("4c8a2e90-1d7b-4f35-9050-50b3e5a1c050").expect("Invalid uuid");
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form()).await.expect("Could not reserve");
+    let res = market.notification.send_driver_accepted(&reservation, &driver.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+    let res = market.notification.send_driver_approaching(&reservation, &driver.id, Duration::seconds(150)).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+
+    // They are written when they are delivered
+    market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    assert_eq!(notifications.len(), 2, "Expected two notifications, got: {:?}", notifications);
+    assert_eq!(notifications[0].message.as_deref(), Some("Your driver is on the way! Look for a GREY INFINITI G37, plate PLATE."));
+    assert_eq!(notifications[1].message.as_deref(), Some("Your driver is 3 minutes away!"));
+
+    // Riders who chose Spanish get the org's Spanish wording
+    let rider_phone_es = Phone::new("+18002000051").expect("Invalid phone number");
+    market.db.send(UserUpdate {
+        phone: rider_phone_es.clone(),
+        form: FormUserInsert { name: String::from("Ana"), profile_image: None },
+    }).await.expect("No db conn").expect("Could not create the rider");
+    market.db.send(UserLocaleSet { phone: rider_phone_es.clone(), locale: Locale::Es }).await.expect("No db conn").expect("Could not set the locale");
+    market.db.send(PushSubscriptionUpsert {
+        subscription: DBPushSubscriptionInsertable {
+            phone: rider_phone_es.clone(),
+            endpoint: String::from("https://push.example/0051"),
+            p256dh: String::from("p256dh"),
+            auth: String::from("auth"),
+            created_at: 0,
+        },
+    }).await.expect("No db conn").expect("Could not register the browser");
+
+    let body = String::from("Hola {rider_name}, {org_name} te recoge en un {vehicle} para {passenger_count}.");
+    assert!(template::validate(&body).is_ok(), "Expected the template to be valid");
+    market.db.send(NotificationTemplateUpsert {
+        template: DBNotificationTemplate {
+            id_org: common::get_id_org(),
+            kind: NotificationKind::DriverAccepted.as_str().to_owned(),
+            locale: Locale::Es.as_str().to_owned(),
+            body,
+            updated_at: 0,
+            title: Some(String::from("{org_name} Viajes")),
+        },
+    }).await.expect("No db conn").expect("Could not save the template");
+
+    let id_reservation_es = Uuid::from_str("4c8a2e90-1d7b-4f35-9051-51b3e5a1c051").expect("Invalid uuid");
+    let reservation_es = market.reservation.create(&rider_phone_es, &id_reservation_es, &id_event, form()).await.expect("Could not reserve");
+    let res = market.notification.send_driver_accepted(&reservation_es, &driver.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+    let res = market.notification.send_driver_approaching(&reservation_es, &driver.id, Duration::seconds(45)).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+
+    market.notification.deliver_due_for_reservation(&id_reservation_es).await.expect("Could not deliver");
+
+    let notifications = market.notification.list_for_reservation(&id_reservation_es).await.expect("Could not list notifications");
+    assert_eq!(notifications.len(), 2, "Expected two notifications, got: {:?}", notifications);
+    assert_eq!(notifications[0].message.as_deref(), Some("Hola Ana, TEST_LOCAL te recoge en un INFINITI G37 grey para 2."));
+    assert_eq!(notifications[1].message.as_deref(), Some("¡Tu conductor está a 1 minuto!"));
+
+    // Who it is from comes from the org's template too
+    let sent = push_service.sent();
+    let pushed: Vec<&(String, String)> = sent.iter().filter(|(endpoint, _)| endpoint.contains("0051")).collect();
+    assert!(!pushed.is_empty(), "Expected a web push");
+    assert!(pushed[0].1.contains(r#""title":"TEST_LOCAL Viajes""#), "Expected the org's title, got: {}", pushed[0].1);
+
+    // A driver without a vehicle gets our wording instead of the org's one with gaps where the vehicle goes
+    let id_event_no_vehicle = Uuid::from_str("4c8a2e90-1d7b-4f35-9057-57b3e5a1c057").expect("Invalid uuid");
+    let res = market.db.send(EventUpdate { event: DBEventInsertable {
+        id: id_event_no_vehicle,
+        name: String::from("Walking event"),
+        bio: None,
+        image_url: None,
+        time_start: 10,
+        time_end: 10,
+        reservations_start: 10,
+        reservations_end: 10,
+        id_location: common::get_id_location(),
+        id_org: common::get_id_org(),
+        obsolete_at: None,
+        published_at: None,
+        auto_arrival: false,
+    } }).await;
+    assert!(matches!(res, Ok(Ok(_))), "Could not create the event, got {:?}", res);
+    market.db.send(ReservationsClear { id_event: id_event_no_vehicle }).await.expect("No db conn").expect("Could not clear reservations");
+
+    let driver_phone_no_vehicle = Phone::new("+18002000058").expect("Invalid phone number");
+    let res = market.db.send(EventDriverUpdate {
+        id_event: id_event_no_vehicle,
+        phone: driver_phone_no_vehicle.clone(),
+        form: FormEventDriver { id_vehicle: None, obsolete_at: None },
+    }).await;
+    assert!(matches!(res, Ok(Ok(_))), "Could not add the driver, got {:?}", res);
+    let driver_no_vehicle = market.driver.find(&id_event_no_vehicle, &driver_phone_no_vehicle).await.expect("Error getting the event driver");
+
+    let id_reservation_no_vehicle = Uuid::from_str("4c8a2e90-1d7b-4f35-9058-58b3e5a1c058").expect("Invalid uuid");
+    let reservation_no_vehicle = market.reservation.create(&rider_phone_es, &id_reservation_no_vehicle, &id_event_no_vehicle, form()).await.expect("Could not reserve");
+    let res = market.notification.send_driver_accepted(&reservation_no_vehicle, &driver_no_vehicle.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+    market.notification.deliver_due_for_reservation(&id_reservation_no_vehicle).await.expect("Could not deliver");
+
+    let notifications = market.notification.list_for_reservation(&id_reservation_no_vehicle).await.expect("Could not list notifications");
+    assert_eq!(notifications[0].message.as_deref(), Some("¡Tu conductor va en camino!"));
+
+    // Orgs can only use the placeholders we fill in
+    assert!(template::validate("Your driver {driver_phone} is here").is_err(), "Expected an unknown placeholder to be rejected");
+    assert!(template::validate("  ").is_err(), "Expected an empty template to be rejected");
+}
@@ -0,0 +1,75 @@
+use chrono::Duration;
+use nujade_backend::{graphql::trails::model::TrailPoint, market::{geocoder::mock_location, clock::ClockManual, MarketMockConfig}};
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_keeps_the_latest_sample_and_drops_old_ones() {
+    let start = 3_000_000;
+    let clock = ClockManual::new(start);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+    let driver = market.driver.find(&id_event, &driver_phone).await.unwrap();
+
+    let samples = vec![
+        TrailPoint { location: mock_location::TIGER_BLVD_LATLNG, at: start - 30 },
+        TrailPoint { location: mock_location::CSP_LATLNG, at: start - 10 },
+        TrailPoint { location: mock_location::BENET_HALL_LATLNG, at: start - 20 },
+    ];
+    let ping_res = market.driver.ping_batch(&id_event, &driver.id, samples).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let snapshot = market.event.get_cache_snapshot(&id_event).await.unwrap();
+    let location = snapshot.driver_locations.get(&driver.id).expect("Expected the driver location in cache");
+    assert_eq!((location.lat, location.lng), (mock_location::CSP_LATLNG.lat, mock_location::CSP_LATLNG.lng), "Expected the latest sample to be the location");
+
+    let samples = vec![
+        TrailPoint { location: mock_location::DOUTHIT_LATLNG, at: start - 40 },
+        TrailPoint { location: mock_location::BENET_HALL_LATLNG, at: start - 5 },
+    ];
+    let ping_res = market.driver.ping_batch(&id_event, &driver.id, samples).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let snapshot = market.event.get_cache_snapshot(&id_event).await.unwrap();
+    let location = snapshot.driver_locations.get(&driver.id).unwrap();
+    assert_eq!((location.lat, location.lng), (mock_location::BENET_HALL_LATLNG.lat, mock_location::BENET_HALL_LATLNG.lng));
+
+    let stale = vec![TrailPoint { location: mock_location::DOUTHIT_LATLNG, at: start - 50 }];
+    let ping_res = market.driver.ping_batch(&id_event, &driver.id, stale).await;
+    assert!(ping_res.is_ok(), "A batch of only old samples should still get the strategy, got {:?}", ping_res);
+
+    let retried = vec![TrailPoint { location: mock_location::BENET_HALL_LATLNG, at: start - 5 }];
+    let ping_res = market.driver.ping_batch(&id_event, &driver.id, retried).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+
+    let trail = market.driver.get_trail(&driver.id, start - 100, start).await.unwrap();
+    let times: Vec<i32> = trail.iter().map(|point| point.at).collect();
+    assert_eq!(times, vec![start - 30, start - 20, start - 10, start - 5], "Expected every sample but the out of order ones in the trail, once");
+
+    clock.advance(Duration::days(31));
+    assert!(market.driver.prune_trails().await.is_ok());
+
+    // Only the latest part of a long batch is kept
+    let now = clock.now();
+    let samples = (0..4000).map(|idx| TrailPoint { location: mock_location::CSP_LATLNG, at: now - 3999 + idx }).collect();
+    let ping_res = market.driver.ping_batch(&id_event, &driver.id, samples).await;
+    assert!(ping_res.is_ok(), "Ping failed, got {:?}", ping_res);
+    let trail = market.driver.get_trail(&driver.id, now - 4000, now).await.unwrap();
+    assert_eq!(trail.len(), 3600);
+    assert_eq!(trail.first().map(|point| point.at), Some(now - 3599));
+
+    // A driver that went offline is back online even if none of the samples are kept
+    let driver_db = market.driver.find(&id_event, &driver_phone).await.unwrap();
+    assert!(market.event.remove_driver(&id_event, &driver_db).await.is_ok());
+    let ahead = vec![TrailPoint { location: mock_location::CSP_LATLNG, at: now + 3600 }];
+    let ping_res = market.driver.ping_batch(&id_event, &driver.id, ahead).await;
+    assert!(ping_res.is_ok(), "A batch of only dropped samples from an offline driver should get the strategy, got {:?}", ping_res);
+}
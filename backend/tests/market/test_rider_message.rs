@@ -0,0 +1,68 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::StreamExt;
+use nujade_backend::{graphql::reservations::FormReservation, types::phone::Phone, market::{geocoder::mock_location, error::ErrorMarket, MarketMockConfig, messanger::memory::MessangerMemory, messages::MessageMarket}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_only_messages_an_assigned_driver() {
+    let market = common::setup_with(MarketMockConfig {
+        messanger: Box::new(MessangerMemory::new()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000039").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("5f0c6a43-6f2e-4d0b-9a39-3b1f0e2c0390").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await.expect("Could not reserve");
+
+    let res = market.reservation.message_driver(&reservation.id, String::from("I'm by the fountain")).await;
+    assert!(matches!(res, Err(ErrorMarket::NotAccepted)), "Messaged a reservation without a driver, got: {:?}", res);
+
+    let res = market.driver.accept(&driver.id, &reservation.id).await;
+    assert!(res.is_ok(), "Could not accept, got error: {:?}", res);
+
+    let mut stream_driver = market.messanger.subscribe(format!("driver:{}", driver.id), None).await.expect("Could not subscribe");
+
+    let res = market.reservation.message_driver(&reservation.id, String::from("   ")).await;
+    assert!(matches!(res, Err(ErrorMarket::BadValue(_))), "Sent an empty message, got: {:?}", res);
+
+    let res = market.reservation.message_driver(&reservation.id, "a".repeat(501)).await;
+    assert!(matches!(res, Err(ErrorMarket::BadValue(_))), "Sent a message that is too long, got: {:?}", res);
+
+    let res = market.reservation.message_driver(&reservation.id, String::from("I'm by the fountain")).await;
+    assert!(res.is_ok(), "Could not message the driver, got error: {:?}", res);
+
+    let message = tokio::time::timeout(Duration::from_secs(5), stream_driver.next()).await
+        .expect("Timed out waiting for the message")
+        .expect("The stream ended")
+        .expect("Got an error from the stream");
+    assert!(
+        matches!(&message, MessageMarket::RiderMessage(msg) if msg.id_reservation == id_reservation && msg.message == "I'm by the fountain"),
+        "Expected the driver to get the message, got: {:?}", message
+    );
+
+    let res = market.reservation.cancel(&reservation.id).await;
+    assert!(res.is_ok(), "Could not cancel, got error: {:?}", res);
+}
@@ -0,0 +1,60 @@
+use chrono::Duration;
+use nujade_backend::{market::{geocoder::{mock_location, scenario::ScenarioLocation}, clock::ClockManual, MarketMockConfig}, simulation::{Simulation, model::{SimulationConfig, SimulationDriver}}};
+
+#[path = "../common.rs"]
+mod common;
+
+fn config(seed: u64) -> SimulationConfig {
+    SimulationConfig {
+        seed,
+        riders: 6,
+        rider_window: Duration::minutes(15),
+        max_passengers: 2,
+        pickup_locations: vec![
+            ScenarioLocation::new(mock_location::BENET_HALL.id, mock_location::BENET_HALL.address, mock_location::BENET_HALL_LATLNG.lat, mock_location::BENET_HALL_LATLNG.lng),
+            ScenarioLocation::new(mock_location::DOUTHIT.id, mock_location::DOUTHIT.address, mock_location::DOUTHIT_LATLNG.lat, mock_location::DOUTHIT_LATLNG.lng),
+        ],
+        event_location: mock_location::CSP_LATLNG,
+        tick: Duration::seconds(30),
+        max_duration: Duration::hours(2),
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_runs_a_seeded_simulation() {
+    let clock = ClockManual::new(1_000_000);
+    let market = common::setup_with(MarketMockConfig {
+        clock: Box::new(clock.clone()),
+        ..Default::default()
+    });
+    common::init_with_two_drivers(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver1 = market.driver.find(&id_event, &common::get_driver_phone()).await.unwrap();
+    let driver2 = market.driver.find(&id_event, &common::get_driver2_phone()).await.unwrap();
+
+    let drivers = vec![
+        SimulationDriver { id: driver1.id, start: mock_location::TIGER_BLVD_LATLNG },
+        SimulationDriver { id: driver2.id, start: mock_location::CSP_LATLNG },
+    ];
+
+    let simulation = Simulation::new(market.clone(), clock.clone(), id_event, drivers, config(42));
+
+    let plan = simulation.plan_riders(1_000_000);
+    assert_eq!(plan, simulation.plan_riders(1_000_000), "The same seed should plan the same riders");
+    assert_eq!(plan.len(), 6);
+    assert!(plan.windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+    let report_res = simulation.run().await;
+    assert!(report_res.is_ok(), "Simulation failed, got error: {:?}", report_res);
+    let report = report_res.unwrap();
+
+    assert_eq!(report.riders_requested, 6);
+    assert_eq!(report.riders_picked_up, 6);
+    assert_eq!(report.riders_completed, 6, "Not every rider was taken to the event:\n{report}");
+    assert_eq!(report.riders_unserved(), 0);
+    assert_eq!(report.drivers.len(), 2);
+    assert!(report.wait_max().unwrap() >= 0);
+    assert!(report.utilization_mean() > 0.0 && report.utilization_mean() <= 1.0, "Bad utilization:\n{report}");
+}
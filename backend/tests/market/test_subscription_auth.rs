@@ -0,0 +1,111 @@
+use chrono::{Utc, Duration};
+use futures::StreamExt;
+use google_maps::GoogleMapsClient;
+use juniper::{InputValue, Variables};
+use nujade_backend::{graphql::{context::Context, handlers::{connection_token, until_token_expires}, until_expired}, sms::ClientTwilio, market::{Market, clock::ClockManual, types::StreamMessageMarket}};
+use tokio::sync::watch;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_authenticates_subscribers_from_a_token() {
+    let market = common::setup();
+    let ctx = Context::new(
+        market.db.clone(),
+        ClientTwilio::new("", ""),
+        String::from("test-secret"),
+        GoogleMapsClient::new(""),
+        market,
+        None,
+        true,
+    );
+    assert!(ctx.expires_at().is_none());
+
+    let phone = common::get_driver_phone();
+    let token = ctx.jwt.sign(&phone);
+
+    let ctx_authed = ctx.as_token_user(&token).expect("Could not authenticate with a signed token");
+    assert_eq!(ctx_authed.phone(), phone);
+
+    // Tokens are signed for a year
+    let expires_at = ctx_authed.expires_at().expect("The token expiry was not kept");
+    let expected = (Utc::now() + Duration::days(365)).timestamp();
+    assert!((expires_at - expected).abs() < 60, "Expected the token to expire around {expected}, got {expires_at}");
+
+    let subscriber = ctx_authed.as_subscriber();
+    assert_eq!(subscriber.expires_at(), Some(expires_at));
+
+    let res = ctx.as_token_user("not a token");
+    assert!(res.is_err(), "Authenticated with an invalid token");
+}
+
+fn context(market: Market) -> Context {
+    Context::new(
+        market.db.clone(),
+        ClientTwilio::new("", ""),
+        String::from("test-secret"),
+        GoogleMapsClient::new(""),
+        market,
+        None,
+        true,
+    )
+}
+
+#[actix_web::main]
+#[test]
+async fn it_authenticates_from_the_connection_init_payload() {
+    let ctx = context(common::setup());
+    let phone = common::get_driver_phone();
+    let token = ctx.jwt.sign(&phone);
+
+    let mut params = Variables::new();
+    params.insert(String::from("token"), InputValue::scalar(token.clone()));
+    assert_eq!(connection_token(&params), Some(token.clone()));
+
+    let mut params = Variables::new();
+    params.insert(String::from("Authorization"), InputValue::scalar(format!("Bearer {token}")));
+    let found = connection_token(&params).expect("Expected the token from the authorization header");
+    assert_eq!(found, token);
+
+    let ctx_authed = ctx.as_token_user(&found).expect("Could not authenticate with the connection token");
+    assert_eq!(ctx_authed.phone(), phone);
+    assert!(ctx_authed.expires_at().is_some());
+
+    let mut params = Variables::new();
+    params.insert(String::from("token"), InputValue::scalar(String::new()));
+    assert_eq!(connection_token(&params), None);
+    assert_eq!(connection_token(&Variables::new()), None);
+
+    // A user from the authorization header keeps the expiry of their token
+    let expires_at = Utc::now().timestamp() + 60;
+    let ctx_header = ctx.as_user(phone).expiring_at(Some(expires_at));
+    assert_eq!(ctx_header.as_subscriber().expires_at(), Some(expires_at));
+}
+
+#[actix_web::main]
+#[test]
+async fn it_ends_subscriptions_when_the_token_expires() {
+    let ctx = context(common::setup());
+    let phone = common::get_driver_phone();
+
+    let ctx_expired = ctx.as_user(phone.clone()).expiring_at(Some(Utc::now().timestamp() - 10));
+    let stream: StreamMessageMarket = Box::pin(futures::stream::pending());
+    let mut stream = until_expired(&ctx_expired, stream);
+    let next = tokio::time::timeout(std::time::Duration::from_secs(3), stream.next()).await.expect("Expected the stream to end");
+    assert!(matches!(next, Some(Err(_))), "Expected an expired token error, got {:?}", next.map(|res| res.is_ok()));
+    let next = tokio::time::timeout(std::time::Duration::from_secs(3), stream.next()).await.expect("Expected the stream to end");
+    assert!(next.is_none(), "Expected the stream to end after the token expired");
+
+    // The connection learns when the token expires after it opens
+    let clock = ClockManual::new(1_000);
+    let (expires_at_tx, expires_at_rx) = watch::channel(None);
+    let mut payload = Box::pin(until_token_expires(futures::stream::pending::<i32>(), expires_at_rx, Box::new(clock)));
+    let next = tokio::time::timeout(std::time::Duration::from_millis(200), payload.next()).await;
+    assert!(next.is_err(), "Expected the connection to stay open without an expiry");
+
+    expires_at_tx.send(Some(1_001)).expect("Could not set the expiry");
+    let next = tokio::time::timeout(std::time::Duration::from_secs(3), payload.next()).await.expect("Expected the connection to close");
+    assert!(next.is_none(), "Expected the connection to close when the token expired");
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use nujade_backend::{graphql::geo::model::LatLng, market::{messanger::{Messanger, memory::MessangerMemory}, messages::MessageMarket, types::StreamMessageMarket}};
+
+async fn next_message(stream: &mut StreamMessageMarket) -> MessageMarket {
+    tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+        .expect("Timed out waiting for a message")
+        .expect("The stream ended")
+        .expect("Got an error from the stream")
+}
+
+async fn next_location(stream: &mut StreamMessageMarket) -> (f64, String) {
+    let message = next_message(stream).await;
+    let cursor = message.cursor().expect("Message has no cursor").to_string();
+    match message {
+        MessageMarket::DriverLocation(msg) => (msg.location.lat, cursor),
+        _ => panic!("Expected a driver location, got: {:?}", message),
+    }
+}
+
+fn location(lat: f64) -> MessageMarket {
+    MessageMarket::new_driver_location(1, LatLng { lat, lng: -82.83 })
+}
+
+#[actix_web::main]
+#[test]
+async fn it_replays_missed_messages_before_going_live() {
+    let messanger: Box<dyn Messanger> = Box::new(MessangerMemory::new());
+    let key = String::from("res:replay");
+
+    let mut stream = messanger.subscribe(key.clone(), None).await.expect("Could not subscribe");
+    messanger.publish(key.clone(), location(1.0)).await.expect("Could not publish");
+    let (lat, cursor) = next_location(&mut stream).await;
+    assert_eq!(lat, 1.0);
+
+    // The connection drops, and two messages are sent before it comes back
+    drop(stream);
+    messanger.publish(key.clone(), location(2.0)).await.expect("Could not publish");
+    messanger.publish(key.clone(), location(3.0)).await.expect("Could not publish");
+
+    let mut stream = messanger.subscribe(key.clone(), Some(cursor)).await.expect("Could not subscribe");
+    messanger.publish(key.clone(), location(4.0)).await.expect("Could not publish");
+
+    let mut lats = Vec::new();
+    for _ in 0..3 {
+        lats.push(next_location(&mut stream).await.0);
+    }
+    assert_eq!(lats, vec![2.0, 3.0, 4.0], "Messages were missed or sent out of order");
+
+    let res = messanger.subscribe(key.clone(), Some(String::from("not a cursor"))).await;
+    assert!(res.is_err(), "Subscribed with an invalid cursor");
+}
+
+#[actix_web::main]
+#[test]
+async fn it_only_replays_what_the_log_still_has() {
+    let messanger: Box<dyn Messanger> = Box::new(MessangerMemory::new().with_log(2, Duration::from_secs(60)));
+    let key = String::from("res:replay-bounded");
+
+    for lat in [1.0, 2.0, 3.0, 4.0] {
+        messanger.publish(key.clone(), location(lat)).await.expect("Could not publish");
+    }
+
+    // The second message was pruned, so the subscriber is told before getting what is left
+    let mut stream = messanger.subscribe(key.clone(), Some(String::from("1"))).await.expect("Could not subscribe");
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::ReplayTruncated(msg) if msg.since == "1"), "Expected the replay to be truncated, got: {:?}", message);
+    assert_eq!(next_location(&mut stream).await.0, 3.0);
+    assert_eq!(next_location(&mut stream).await.0, 4.0);
+
+    // Nothing was pruned after the second to last message
+    let mut stream = messanger.subscribe(key.clone(), Some(String::from("3"))).await.expect("Could not subscribe");
+    assert_eq!(next_location(&mut stream).await.0, 4.0);
+
+    // A cursor from before a restart
+    let mut stream = messanger.subscribe(key.clone(), Some(String::from("40"))).await.expect("Could not subscribe");
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::ReplayTruncated(_)), "Expected the replay to be truncated, got: {:?}", message);
+}
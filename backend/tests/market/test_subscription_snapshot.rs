@@ -0,0 +1,97 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::StreamExt;
+use nujade_backend::{graphql::{reservations::FormReservation, geo::model::LatLng, with_snapshot}, types::phone::Phone, market::{geocoder::mock_location, messages::MessageMarket, messanger::{Messanger, memory::MessangerMemory}, types::StreamMessageMarket}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+#[actix_web::main]
+#[test]
+async fn it_snapshots_the_reservation_and_event() {
+    let market = common::setup();
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver_phone = common::get_driver_phone();
+
+    let driver = market.driver.find(&id_event, &driver_phone).await.expect("Error getting the event driver");
+
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000043").expect("Invalid phone number");
+    let id_reservation = Uuid::from_str("3d0f2b6c-8a41-4e7e-b043-43a1c5e0d043").expect("Invalid uuid");
+
+    let form = FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    };
+
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form).await.expect("Could not reserve");
+
+    // Waiting in the pool, there is no driver to share a location yet
+    let snapshot = market.reservation.get_snapshot(&reservation.id).await.expect("Could not get the snapshot");
+    assert!(matches!(snapshot.get(0), Some(MessageMarket::ReservationUpdate(msg)) if msg.reservation.id == id_reservation), "Expected the reservation first, got: {:?}", snapshot);
+    assert!(!snapshot.iter().any(|msg| matches!(msg, MessageMarket::DriverLocation(_))), "Got a driver location without a driver: {:?}", snapshot);
+
+    let res = market.driver.accept(&driver.id, &reservation.id).await;
+    assert!(res.is_ok(), "Could not accept, got error: {:?}", res);
+
+    let snapshot = market.reservation.get_snapshot(&reservation.id).await.expect("Could not get the snapshot");
+    assert_eq!(snapshot.len(), 3, "Expected the reservation, estimate and driver location, got: {:?}", snapshot);
+    assert!(matches!(&snapshot[0], MessageMarket::ReservationUpdate(msg) if msg.reservation.id_driver == Some(driver.id)));
+    assert!(matches!(&snapshot[1], MessageMarket::ReservationEstimation(_)));
+    assert!(matches!(&snapshot[2], MessageMarket::DriverLocation(msg) if msg.id == driver.id));
+    assert!(snapshot.iter().all(|msg| msg.cursor().is_none()), "Snapshots are not in the log and should not have a cursor");
+
+    let snapshot = market.event.get_snapshot(&id_event).await.expect("Could not get the event snapshot");
+    assert!(matches!(snapshot.as_slice(), [MessageMarket::EventEstimations(msg)] if msg.strategy.drivers.contains_key(&driver.id)), "Expected the event estimations, got: {:?}", snapshot);
+
+    let res = market.reservation.cancel(&reservation.id).await;
+    assert!(res.is_ok(), "Could not cancel, got error: {:?}", res);
+
+    let snapshot = market.reservation.get_snapshot(&reservation.id).await.expect("Could not get the snapshot");
+    assert!(matches!(snapshot.as_slice(), [MessageMarket::ReservationUpdate(msg)] if msg.reservation.is_cancelled), "Expected only the cancelled reservation, got: {:?}", snapshot);
+}
+
+async fn next_message(stream: &mut StreamMessageMarket) -> MessageMarket {
+    tokio::time::timeout(Duration::from_secs(5), stream.next()).await
+        .expect("Timed out waiting for a message")
+        .expect("The stream ended")
+        .expect("Got an error from the stream")
+}
+
+fn location(lat: f64) -> MessageMarket {
+    MessageMarket::new_driver_location(1, LatLng { lat, lng: -82.83 })
+}
+
+#[actix_web::main]
+#[test]
+async fn it_gives_the_snapshot_the_cursor_it_is_at() {
+    let messanger: Box<dyn Messanger> = Box::new(MessangerMemory::new());
+    let key = String::from("res:snapshot-cursor");
+
+    messanger.publish(key.clone(), location(1.0)).await.expect("Could not publish");
+    let (cursor, stream) = messanger.subscribe_from_end(key.clone()).await.expect("Could not subscribe");
+    assert_eq!(cursor.as_deref(), Some("1"), "Expected the snapshot to be at the last message");
+
+    messanger.publish(key.clone(), location(2.0)).await.expect("Could not publish");
+    let mut stream = with_snapshot(vec![location(1.0)], cursor.clone(), stream);
+
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::DriverLocation(msg) if msg.location.lat == 1.0), "Expected the snapshot first, got: {:?}", message);
+    assert_eq!(message.cursor(), Some("1"));
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::DriverLocation(msg) if msg.location.lat == 2.0), "Expected the message after the snapshot, got: {:?}", message);
+    assert_eq!(message.cursor(), Some("2"));
+
+    // Catching up from the snapshot only gets what came after it
+    let mut stream = messanger.subscribe(key.clone(), cursor).await.expect("Could not subscribe");
+    let message = next_message(&mut stream).await;
+    assert!(matches!(&message, MessageMarket::DriverLocation(msg) if msg.location.lat == 2.0), "Expected only the message after the snapshot, got: {:?}", message);
+}
@@ -0,0 +1,117 @@
+use std::str::FromStr;
+
+use nujade_backend::{graphql::{reservations::FormReservation, users::{FormUserInsert, messages::{UserUpdate, UserSMSOpt}}, notifications::model::NotificationStatus, push_subscriptions::{messages::{PushSubscriptionUpsert, PushSubscriptionsForUser}, model::DBPushSubscriptionInsertable}}, types::phone::Phone, market::{geocoder::mock_location, Market, MarketMockConfig, pusher::mock::PushServiceMock}};
+use uuid::Uuid;
+
+#[path = "../common.rs"]
+mod common;
+
+fn form() -> FormReservation {
+    FormReservation {
+        passenger_count: 1,
+        is_dropoff: false,
+        stops: vec![
+            mock_location::BENET_HALL.stop()
+        ]
+    }
+}
+
+async fn add_rider(market: &Market, phone: &Phone, name: &str, endpoints: &[&str]) {
+    market.db.send(UserUpdate {
+        phone: phone.clone(),
+        form: FormUserInsert { name: name.to_owned(), profile_image: None },
+    }).await.expect("No db conn").expect("Could not create the rider");
+    for endpoint in endpoints {
+        market.db.send(PushSubscriptionUpsert {
+            subscription: DBPushSubscriptionInsertable {
+                phone: phone.clone(),
+                endpoint: endpoint.to_string(),
+                p256dh: String::from("p256dh"),
+                auth: String::from("auth"),
+                created_at: 0,
+            },
+        }).await.expect("No db conn").expect("Could not register the browser");
+    }
+}
+
+#[actix_web::main]
+#[test]
+async fn it_pushes_to_browsers_and_forgets_expired_ones() {
+    let push_service = PushServiceMock::new();
+    let market = common::setup_with(MarketMockConfig {
+        push_service: Box::new(push_service.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver = market.driver.find(&id_event, &common::get_driver_phone()).await.expect("Error getting the event driver");
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    // A rider with browsers registered gets web pushes instead of texts
+    let rider_phone = Phone::new("+18002000049").expect("Invalid phone number");
+    add_rider(&market, &rider_phone, "Browser Rider", &["https://push.example/0049-current", "https://push.example/0049-expired"]).await;
+    push_service.expire("https://push.example/0049-expired");
+
+    let id_reservation = Uuid::from_str("4c8a2e90-1d7b-4f35-9049-49b3e5a1c049").expect("Invalid uuid");
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form()).await.expect("Could not reserve");
+    let res = market.notification.send_driver_accepted(&reservation, &driver.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+    assert_eq!(report.sent, 1, "Expected the web push to be sent, got: {:?}", report);
+
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    assert_eq!(notifications[0].status, NotificationStatus::Sent);
+
+    let sent = push_service.sent();
+    let pushed: Vec<&(String, String)> = sent.iter().filter(|(endpoint, _)| endpoint.contains("0049")).collect();
+    assert_eq!(pushed.len(), 1, "Expected one web push, got: {:?}", pushed);
+    assert_eq!(pushed[0].0, "https://push.example/0049-current");
+    assert!(pushed[0].1.contains("Your driver is on the way!"), "Expected the message in the payload, got: {}", pushed[0].1);
+    assert!(pushed[0].1.contains(r#""title":"TEST_LOCAL""#), "Expected the org's name as the title, got: {}", pushed[0].1);
+
+    let subscriptions = market.db.send(PushSubscriptionsForUser { phone: rider_phone }).await.expect("No db conn").expect("Could not list browsers");
+    assert_eq!(subscriptions.len(), 1, "Expected the expired browser to be removed, got: {:?}", subscriptions);
+}
+
+#[actix_web::main]
+#[test]
+async fn it_texts_riders_whose_browsers_have_all_expired() {
+    let push_service = PushServiceMock::new();
+    let market = common::setup_with(MarketMockConfig {
+        push_service: Box::new(push_service.clone()),
+        ..Default::default()
+    });
+    common::init(&market).await;
+
+    let id_event = common::get_id_event();
+    let driver = market.driver.find(&id_event, &common::get_driver_phone()).await.expect("Error getting the event driver");
+    let ping_res = market.driver.ping(&id_event, &driver.id, &mock_location::TIGER_BLVD_LATLNG).await;
+    assert!(ping_res.is_ok(), "Could not ping, got error: {:?}", ping_res);
+
+    let rider_phone = Phone::new("+18002000052").expect("Invalid phone number");
+    add_rider(&market, &rider_phone, "Expired Browser Rider", &["https://push.example/0052-expired"]).await;
+    market.db.send(UserSMSOpt { phone: rider_phone.clone(), opt_in: true }).await.expect("No db conn").expect("Could not opt in to SMS");
+    push_service.expire("https://push.example/0052-expired");
+
+    let id_reservation = Uuid::from_str("4c8a2e90-1d7b-4f35-9052-52b3e5a1c052").expect("Invalid uuid");
+    let reservation = market.reservation.create(&rider_phone, &id_reservation, &id_event, form()).await.expect("Could not reserve");
+    let res = market.notification.send_driver_accepted(&reservation, &driver.id).await;
+    assert!(res.is_ok(), "Could not enqueue, got error: {:?}", res);
+
+    // The browser is gone, so the rider is texted in the same attempt instead
+    let report = market.notification.deliver_due_for_reservation(&id_reservation).await.expect("Could not deliver");
+    assert_eq!(report.sent, 1, "Expected the text to be sent, got: {:?}", report);
+
+    let notifications = market.notification.list_for_reservation(&id_reservation).await.expect("Could not list notifications");
+    assert_eq!(notifications[0].status, NotificationStatus::Sent);
+    assert_eq!(notifications[0].attempts, 1);
+
+    let sent = push_service.sent();
+    assert!(sent.iter().all(|(endpoint, _)| !endpoint.contains("0052")), "Expected no web push, got: {:?}", sent);
+
+    let subscriptions = market.db.send(PushSubscriptionsForUser { phone: rider_phone }).await.expect("No db conn").expect("Could not list browsers");
+    assert!(subscriptions.is_empty(), "Expected the expired browser to be removed, got: {:?}", subscriptions);
+}
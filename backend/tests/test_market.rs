@@ -17,4 +17,29 @@ mod market {
     mod test_estimation_double_pickup_two_drivers;
     mod test_estimation_pickup_after_complete_pickup;
     mod test_estimation_dropoff_after_complete_dropoff;
+    mod test_mock_scenario;
+    mod test_manual_clock;
+    mod test_simulation;
+    mod test_cache_expiry;
+    mod test_cache_store;
+    mod test_event_cache_admin;
+    mod test_estimator;
+    mod test_driver_approaching;
+    mod test_auto_arrival;
+    mod test_driver_trail;
+    mod test_driver_report;
+    mod test_ping_batch;
+    mod test_rider_message;
+    mod test_driver_subscription;
+    mod test_subscription_replay;
+    mod test_subscription_snapshot;
+    mod test_subscription_auth;
+    mod test_event_summary;
+    mod test_driver_status;
+    mod test_notification_outbox;
+    mod test_web_push;
+    mod test_notification_preferences;
+    mod test_notification_templates;
+    mod test_geocoder_resilient;
+    mod test_messanger_redis;
 }